@@ -0,0 +1,351 @@
+///Helpers for players and bots: generation of keys, shuffling of the deck
+///with the proof checked by the `poker` runtime module, locking of the deck
+///and opening of dealt cards
+
+use poker_runtime::{cards::Card, commutative, knowledge, lock, shuffle};
+use rand::{Rng, seq::SliceRandom};
+
+const SIZE: usize = commutative::ELEMENT_SIZE;
+
+///Key pair of the commutative cipher; only the public part is registered,
+///the decryption part is revealed when cards must be opened
+pub struct KeyPair {
+    pub encryption: Vec<u8>,
+    pub decryption: Vec<u8>,
+    pub public: Vec<u8>,
+}
+
+///Key pairs of a participant for one round
+pub struct RoundKeys {
+    pub hand: KeyPair,
+    pub flop: KeyPair,
+    pub turn: KeyPair,
+    pub river: KeyPair,
+}
+
+///The deck to submit together with the proof;
+///the key must be kept to remove its encryption in the lock pass
+pub struct Shuffled {
+    pub deck: Vec<u8>,
+    pub key: Vec<u8>,
+    pub proof: shuffle::Proof,
+}
+
+///The locked deck to submit together with the proof
+pub struct Locked {
+    pub deck: Vec<u8>,
+    pub proof: lock::Proof,
+}
+
+///Random exponent of the cipher
+pub fn exponent<R: Rng>(rng: &mut R) -> Vec<u8> {
+    loop {
+        let mut bytes = vec![0; SIZE];
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= 0x7f;
+        if commutative::is_exponent(&bytes) {
+            return bytes;
+        }
+    }
+}
+
+pub fn keypair<R: Rng>(rng: &mut R) -> KeyPair {
+    let decryption = exponent(rng);
+    KeyPair {
+        encryption: commutative::inverse(&decryption).unwrap(),
+        public: commutative::public(&decryption).unwrap(),
+        decryption,
+    }
+}
+
+pub fn round_keys<R: Rng>(rng: &mut R) -> RoundKeys {
+    RoundKeys {
+        hand: keypair(rng),
+        flop: keypair(rng),
+        turn: keypair(rng),
+        river: keypair(rng),
+    }
+}
+
+///First pass: encrypts every card with a new key and permutes the deck
+pub fn shuffle<R: Rng>(input: &[u8], rng: &mut R) -> Shuffled {
+    let key = exponent(rng);
+    let permutation = permutation(input.len() / SIZE, rng);
+    let deck = permute(&commutative::encrypt(input, &key).unwrap(), &permutation);
+    let proof = prove(input, &deck, &key, &permutation, shuffle::ROUNDS, rng);
+
+    Shuffled { deck, key, proof }
+}
+
+///Proves that `output[permutation[i]] = input[i]^key`, see `shuffle` module of the runtime;
+///the runtime accepts only `shuffle::ROUNDS` rounds
+pub fn prove<R: Rng>(input: &[u8], output: &[u8], key: &[u8], permutation: &[u8], rounds: usize, rng: &mut R) -> shuffle::Proof {
+    let size = input.len() / SIZE;
+
+    let masks: Vec<(Vec<u8>, Vec<u8>)> = (0..rounds)
+        .map(|_| (exponent(rng), self::permutation(size, rng)))
+        .collect();
+
+    let decks: Vec<Vec<u8>> = masks.iter()
+        .map(|(mask, order)| permute(&commutative::encrypt(input, mask).unwrap(), order))
+        .collect();
+
+    let bits = shuffle::challenge(input, output, &decks[..]);
+    let openings = masks.into_iter()
+        .zip(bits.into_iter())
+        .map(|((mask, order), bit)| if bit {
+            let mut rest = vec![0; size];
+            for i in 0..size {
+                rest[order[i] as usize] = permutation[i];
+            }
+            let unmask = commutative::inverse(&mask).unwrap();
+            let exponent = commutative::multiply(key, &unmask).unwrap();
+            shuffle::Opening::Output { exponent, permutation: rest }
+        } else {
+            shuffle::Opening::Input { exponent: mask, permutation: order }
+        })
+        .collect();
+
+    shuffle::Proof { decks, openings }
+}
+
+///Second pass: removes encryption with the shuffle key from the cards which are going
+///to be dealt, and encrypts them with the keys of their groups; `decks` are all decks
+///submitted before, starting with the initial one, as the runtime keeps them
+pub fn lock<R: Rng>(decks: &[Vec<u8>], players: usize, shuffle_key: &[u8], keys: &RoundKeys, rng: &mut R) -> Locked {
+    let position = lock::position(decks, players).expect("it is the turn to lock the deck");
+    let unlock = commutative::inverse(shuffle_key).unwrap();
+
+    let deck = decks[decks.len() - 1].chunks(SIZE)
+        .enumerate()
+        .flat_map(|(i, card)| {
+            let exponent = match lock::group(i, players, position) {
+                Some(lock::Group::Others) => unlock.clone(),
+                Some(group) => commutative::multiply(&unlock, &pair(keys, group).encryption).unwrap(),
+                //burnt and undealt cards are never revealed
+                None => return card.to_vec(),
+            };
+            commutative::encrypt(card, &exponent).unwrap()
+        })
+        .collect::<Vec<u8>>();
+
+    let proof = prove_lock(decks, players, &deck, &unlock, keys, rng);
+    Locked { deck, proof }
+}
+
+///Proves that the deck is locked correctly, see `lock` module of the runtime
+pub fn prove_lock<R: Rng>(decks: &[Vec<u8>], players: usize, output: &[u8], unlock: &[u8], keys: &RoundKeys, rng: &mut R) -> lock::Proof {
+    let statement = lock::statement(decks, players, output).expect("decks are valid");
+    let public: Vec<&[u8]> = vec![&keys.hand.public[..], &keys.flop.public[..], &keys.turn.public[..], &keys.river.public[..]];
+    let mut secrets = vec![unlock];
+    secrets.extend(lock::GROUPS[1..].iter().map(|&group| &pair(keys, group).decryption[..]));
+
+    loop {
+        let nonces: Vec<Vec<u8>> = secrets.iter().map(|_| exponent(rng)).collect();
+
+        let cards = statement.groups.iter()
+            .zip(lock::GROUPS.iter())
+            .zip(nonces.iter())
+            .map(|(((before, after), &group), nonce)| {
+                let power = commutative::encrypt(before, &nonces[0]).unwrap();
+                if group == lock::Group::Others {
+                    return power;
+                }
+                let inverse = commutative::encrypt(after, &commutative::negate(nonce).unwrap()).unwrap();
+                commutative::combine(&[&power, &inverse], &[1, 1]).unwrap()
+            })
+            .collect();
+
+        let mut proof = lock::Proof {
+            unlock: commutative::encrypt(&statement.shuffled.1, &nonces[0]).unwrap(),
+            keys: nonces[1..].iter().map(|nonce| commutative::public(nonce).unwrap()).collect(),
+            cards,
+            responses: vec![],
+        };
+        let challenge = lock::challenge(&statement, &public, &proof);
+
+        //a response can't be zero, then other nonces are taken
+        let responses: Result<Vec<Vec<u8>>, _> = nonces.iter()
+            .zip(secrets.iter())
+            .map(|(nonce, secret)| commutative::add(nonce, &commutative::multiply(&challenge, secret).unwrap()))
+            .collect();
+        if let Ok(responses) = responses {
+            proof.responses = responses;
+            return proof;
+        }
+    }
+}
+
+///Proofs of knowledge of secret keys, in the order `preflop` takes them: hand, flop,
+///turn and river; `context` is the SCALE encoding of the game id and the account
+pub fn prove_keys<R: Rng>(keys: &RoundKeys, context: &[u8], rng: &mut R) -> Vec<knowledge::Proof> {
+    vec![&keys.hand, &keys.flop, &keys.turn, &keys.river].into_iter()
+        .map(|pair| prove_knowledge(pair, context, rng))
+        .collect()
+}
+
+///Proves knowledge of the decryption key, see `knowledge` module of the runtime
+pub fn prove_knowledge<R: Rng>(pair: &KeyPair, context: &[u8], rng: &mut R) -> knowledge::Proof {
+    loop {
+        let nonce = exponent(rng);
+        let commitment = commutative::public(&nonce).unwrap();
+        let challenge = knowledge::challenge(&pair.public, &commitment, context);
+        let product = commutative::multiply(&challenge, &pair.decryption).unwrap();
+
+        //the response can't be zero, then another nonce is taken
+        if let Ok(response) = commutative::add(&nonce, &product) {
+            return knowledge::Proof { commitment, response };
+        }
+    }
+}
+
+///Opens cards encrypted only with the key pair, e.g. your own pocket cards
+pub fn open(cards: &[u8], pair: &KeyPair) -> Option<Vec<Card>> {
+    commutative::decrypt(cards, &pair.decryption).ok()
+        .and_then(|decrypted| commutative::decode(&decrypted))
+}
+
+fn pair(keys: &RoundKeys, group: lock::Group) -> &KeyPair {
+    match group {
+        lock::Group::Flop => &keys.flop,
+        lock::Group::Turn => &keys.turn,
+        lock::Group::River => &keys.river,
+        _ => &keys.hand,
+    }
+}
+
+fn permutation<R: Rng>(size: usize, rng: &mut R) -> Vec<u8> {
+    let mut permutation: Vec<u8> = (0..size as u8).collect();
+    permutation.shuffle(rng);
+    permutation
+}
+
+fn permute(data: &[u8], permutation: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; data.len()];
+    for (i, chunk) in data.chunks(SIZE).enumerate() {
+        let j = permutation[i] as usize;
+        result[j * SIZE..(j + 1) * SIZE].copy_from_slice(chunk);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_runtime::cards::{self, spades, hearts, clubs, diamonds};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn deck(size: usize) -> Vec<u8> {
+        let all: Vec<Card> = (1..14)
+            .flat_map(|n| vec![spades(n), hearts(n), clubs(n), diamonds(n)])
+            .collect();
+        commutative::encode(all.iter().take(size).collect())
+    }
+
+    #[test]
+    fn dealt_cards_are_opened_by_owners_only() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let players = 2;
+        let initial = deck(2 * players + 8);
+
+        let keys: Vec<RoundKeys> = (0..players).map(|_| round_keys(&mut rng)).collect();
+        let shuffle_keys: Vec<Vec<u8>> = (0..players).map(|_| exponent(&mut rng)).collect();
+
+        let mut decks = vec![initial.clone()];
+        for key in shuffle_keys.iter() {
+            let deck = decks.last().unwrap();
+            let order = permutation(deck.len() / SIZE, &mut rng);
+            decks.push(permute(&commutative::encrypt(deck, key).unwrap(), &order));
+        }
+        for (position, key) in shuffle_keys.iter().enumerate() {
+            let locked = lock(&decks, players, key, &keys[position], &mut rng);
+            let public = vec![&keys[position].hand.public[..], &keys[position].flop.public[..],
+                &keys[position].turn.public[..], &keys[position].river.public[..]];
+            assert!(lock::verify(&decks, players, &locked.deck, &public, &locked.proof));
+
+            let other = &keys[(position + 1) % players];
+            let public = vec![&other.hand.public[..], &other.flop.public[..], &other.turn.public[..], &other.river.public[..]];
+            assert!(!lock::verify(&decks, players, &locked.deck, &public, &locked.proof));
+            decks.push(locked.deck);
+        }
+        let deck = decks.last().unwrap();
+
+        let card = |i: usize| deck[i * SIZE..(i + 1) * SIZE].to_vec();
+        let mut dealt = vec![];
+        for position in 0..players {
+            let mut hand = card(position);
+            hand.extend(card(position + players));
+            let opened = open(&hand, &keys[position].hand).unwrap();
+            assert_eq!(opened.len(), 2);
+
+            let other = &keys[(position + 1) % players].hand;
+            assert_eq!(open(&hand, other), None);
+            dealt.extend(opened);
+        }
+
+        let shared = 2 * players;
+        let mut flop: Vec<u8> = (shared + 1..shared + 4).flat_map(|i| card(i)).collect();
+        for pair in keys.iter().rev() {
+            flop = commutative::decrypt(&flop, &pair.flop.decryption).unwrap();
+        }
+        dealt.extend(commutative::decode(&flop).unwrap());
+
+        let mut turn = card(shared + 5);
+        assert_eq!(open(&turn, &keys[0].turn), None);
+        for pair in keys.iter() {
+            turn = commutative::decrypt(&turn, &pair.turn.decryption).unwrap();
+        }
+        dealt.extend(commutative::decode(&turn).unwrap());
+
+        assert_eq!(dealt.len(), 8);
+        let mut unique = dealt.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), dealt.len());
+    }
+
+    #[test]
+    fn public_keys_match_secrets() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let pair = keypair(&mut rng);
+        assert!(commutative::keypair_is_valid(&pair.public, &pair.decryption));
+
+        let message = deck(1);
+        let encrypted = commutative::encrypt(&message, &pair.encryption).unwrap();
+        assert_eq!(commutative::decrypt(&encrypted, &pair.decryption).unwrap(), message);
+    }
+
+    #[test]
+    fn keys_are_proven() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let keys = round_keys(&mut rng);
+        let proofs = prove_keys(&keys, b"context", &mut rng);
+
+        let public = vec![&keys.hand.public, &keys.flop.public, &keys.turn.public, &keys.river.public];
+        assert_eq!(proofs.len(), 4);
+        for (key, proof) in public.iter().zip(proofs.iter()) {
+            assert!(knowledge::verify(key, proof, b"context"));
+            assert!(!knowledge::verify(key, proof, b"another context"));
+        }
+        assert!(!knowledge::verify(&keys.flop.public, &proofs[0], b"context"));
+    }
+
+    //the full proof takes a while, the same deck is proven with fewer rounds
+    #[test]
+    fn proof_is_accepted_by_runtime() {
+        const ROUNDS: usize = 4;
+        let mut rng = StdRng::seed_from_u64(13);
+        let initial = deck(cards::DECK_SIZE);
+
+        let key = exponent(&mut rng);
+        let order = permutation(cards::DECK_SIZE, &mut rng);
+        let shuffled = permute(&commutative::encrypt(&initial, &key).unwrap(), &order);
+        let proof = prove(&initial, &shuffled, &key, &order, ROUNDS, &mut rng);
+        assert!(commutative::is_deck(&shuffled, cards::DECK_SIZE));
+        assert!(shuffle::verify_rounds(&initial, &shuffled, &proof, ROUNDS));
+        assert!(!shuffle::verify(&initial, &shuffled, &proof));
+
+        let other = prove(&initial, &shuffled, &exponent(&mut rng), &order, ROUNDS, &mut rng);
+        assert!(!shuffle::verify_rounds(&initial, &shuffled, &other, ROUNDS));
+    }
+}
@@ -0,0 +1,60 @@
+//! Runtime API of the poker module, for clients which need
+//! the state of a game derived from the storage
+
+use client::decl_runtime_apis;
+use parity_codec::{Codec, Encode, Decode};
+use rstd::prelude::*;
+
+pub use crate::poker::GameId;
+use crate::stage::StageId;
+use crate::betting::LegalActions;
+
+///Participant sitting at the table
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Seat<AccountId, Balance> {
+	pub who: AccountId,
+	///Chips in front of the participant, without his current bet
+	pub chips: Balance,
+	///Bet in the current betting round
+	pub bet: Balance,
+	///Participant of the current hand who hasn't folded
+	pub in_hand: bool,
+}
+
+///Snapshot of a table, enough to render it with one call
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Table<AccountId, Balance> {
+	///Participants in clockwise order
+	pub seats: Vec<Seat<AccountId, Balance>>,
+	///Index of the seat with the dealer button
+	pub button: u32,
+	pub stage: StageId,
+	pub small_blind: Balance,
+	pub big_blind: Balance,
+	pub ante: Balance,
+	///Chips in all pots, without current bets
+	pub pot: Balance,
+	///Participant who has to act now, with his options
+	pub to_act: Option<AccountId>,
+	pub actions: Option<LegalActions<Balance>>,
+	///Opened shared cards, two bytes per card: nominal and suit
+	pub shared_cards: Vec<u8>,
+}
+
+decl_runtime_apis! {
+	pub trait PokerApi<AccountId, Balance> where AccountId: Codec, Balance: Codec {
+		///Participant who has to bet now, with minimal and maximal
+		///total bets he can raise to; `None` if nobody can raise
+		fn raise_range(game: GameId) -> Option<(AccountId, Balance, Balance)>;
+
+		///Everything clients show about the table; `None` if there is no such table
+		fn table(game: GameId) -> Option<Table<AccountId, Balance>>;
+
+		///What the participant can do now: whether he can check, how much he needs
+		///to call, the range of raises and whether big blind has the option;
+		///`None` if it is not his turn to bet
+		fn legal_actions(game: GameId, who: AccountId) -> Option<LegalActions<Balance>>;
+	}
+}
@@ -0,0 +1,81 @@
+use rstd::prelude::*;
+
+///Number of tables needed to seat all players
+pub fn tables_needed(players: u32, seats: u32) -> u32 {
+    (players + seats - 1) / seats
+}
+
+///Tables where participants of the table which has just finished a hand go,
+///one entry per moved participant. When there are more tables than needed,
+///the table is broken and everybody leaves it; otherwise participants are moved
+///until it has at most one participant more than the smallest table.
+pub fn moves(sizes: &[u32], current: usize, seats: u32) -> Vec<usize> {
+    let players = sizes.iter().sum();
+    let breaking = sizes.len() > tables_needed(players, seats) as usize;
+
+    let mut sizes = sizes.to_vec();
+    let mut moves = vec![];
+    loop {
+        let smallest = (0..sizes.len())
+            .filter(|&table| table != current)
+            .min_by_key(|&table| sizes[table]);
+        let target = match smallest {
+            Some(target) => target,
+            None => break,
+        };
+
+        let done = if breaking {
+            sizes[current] == 0
+        } else {
+            sizes[current] <= sizes[target] + 1
+        };
+        if done {
+            break;
+        }
+
+        sizes[current] -= 1;
+        sizes[target] += 1;
+        moves.push(target);
+    }
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_cover_all_players() {
+        assert_eq!(tables_needed(0, 9), 0);
+        assert_eq!(tables_needed(1, 9), 1);
+        assert_eq!(tables_needed(9, 9), 1);
+        assert_eq!(tables_needed(10, 9), 2);
+        assert_eq!(tables_needed(27, 9), 3);
+        assert_eq!(tables_needed(5, 2), 3);
+    }
+
+    #[test]
+    fn balanced_tables_stay() {
+        assert_eq!(moves(&[9], 0, 9), vec![]);
+        assert_eq!(moves(&[5, 6], 1, 6), vec![]);
+        assert_eq!(moves(&[6, 6, 5], 0, 6), vec![]);
+        //the small table waits for others to send participants
+        assert_eq!(moves(&[6, 3, 6], 1, 6), vec![]);
+    }
+
+    #[test]
+    fn participants_go_to_the_smallest_tables() {
+        assert_eq!(moves(&[6, 4], 0, 6), vec![1]);
+        assert_eq!(moves(&[8, 5, 4], 0, 8), vec![2, 1]);
+        assert_eq!(moves(&[4, 6, 6, 5], 1, 6), vec![0]);
+    }
+
+    #[test]
+    fn extra_table_is_broken() {
+        assert_eq!(moves(&[3, 6], 0, 9), vec![1, 1, 1]);
+        assert_eq!(moves(&[4, 4, 3], 2, 6), vec![0, 1, 0]);
+        //the table which has just finished is broken, even if it is not the smallest
+        assert_eq!(moves(&[2, 5], 1, 9), vec![0, 0, 0, 0, 0]);
+        assert_eq!(moves(&[1, 1], 0, 2), vec![1]);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::stage::*;
+
+use parity_codec::{Encode, Decode};
+use runtime_primitives::traits::SimpleArithmetic;
+use rstd::cmp::{min, max};
+
+///How much a participant can bet at once
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum BettingStructure<Balance> {
+    ///Any amount up to the whole stack
+    NoLimit,
+    ///Up to the size of the pot after calling
+    PotLimit,
+    ///Only fixed steps: the small bet on preflop and flop, the big bet
+    ///on turn and river, and only a limited number of raises per betting round
+    FixedLimit { small_bet: Balance, big_bet: Balance, max_raises: u32 },
+}
+
+impl<Balance> Default for BettingStructure<Balance> {
+    fn default() -> Self {
+        BettingStructure::NoLimit
+    }
+}
+
+///State of the betting round from the point of view of the participant who acts
+pub struct Situation<Balance> {
+    pub stage: StageId,
+    ///Current maximum bet
+    pub level: Balance,
+    ///Bet of the participant in this betting round
+    pub bet: Balance,
+    ///Chips of the participant, including his bet
+    pub stack: Balance,
+    ///Chips in all pots together with current bets of everybody
+    pub pot: Balance,
+    pub big_blind: Balance,
+    ///Increment of the last full raise in this betting round
+    pub last_raise: Balance,
+    ///Number of raises in this betting round
+    pub raises: u32,
+}
+
+///What the participant who acts can do besides folding
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LegalActions<Balance> {
+    pub check: bool,
+    ///Chips to add to the bet to call, it is an all-in when the stack is not enough
+    pub call: Option<Balance>,
+    ///Minimal and maximal total bets to raise to
+    pub raise: Option<(Balance, Balance)>,
+    ///Big blind acts preflop after everybody has called him,
+    ///he can check and close the round or raise
+    pub option: bool,
+}
+
+///Smallest increment of a full raise: a re-raise must be at least as large as the
+///previous raise; an all-in for less doesn't reopen betting for those who have acted
+pub fn min_increment<Balance>(structure: &BettingStructure<Balance>, s: &Situation<Balance>) -> Balance
+    where Balance: SimpleArithmetic + Copy {
+    match *structure {
+        BettingStructure::FixedLimit { small_bet, big_bet, .. } => {
+            if s.stage == TURN || s.stage == RIVER { big_bet } else { small_bet }
+        },
+        _ => max(s.last_raise, s.big_blind)
+    }
+}
+
+///Minimal and maximal total bet the participant can raise to;
+///when he doesn't have enough chips for the minimal raise, he can only go all-in
+pub fn raise_range<Balance>(structure: &BettingStructure<Balance>, s: &Situation<Balance>) -> Option<(Balance, Balance)>
+    where Balance: SimpleArithmetic + Copy {
+    if s.stack <= s.level {
+        return None;
+    }
+
+    let lowest = s.level + min_increment(structure, s);
+    let highest = match *structure {
+        BettingStructure::NoLimit => s.stack,
+        BettingStructure::PotLimit => {
+            //the raise is counted as if the participant called first
+            let call = s.level - s.bet;
+            s.level + s.pot + call
+        },
+        BettingStructure::FixedLimit { max_raises, .. } => {
+            if s.raises >= max_raises {
+                return None;
+            }
+            lowest
+        },
+    };
+
+    let highest = min(highest, s.stack);
+    Some((min(lowest, highest), highest))
+}
+
+///Actions of the participant; `reopened` is false when he has acted
+///and only an incomplete all-in raise has followed, then he can't raise
+pub fn legal_actions<Balance>(structure: &BettingStructure<Balance>, s: &Situation<Balance>, reopened: bool) -> LegalActions<Balance>
+    where Balance: SimpleArithmetic + Copy {
+    let check = s.bet >= s.level;
+    LegalActions {
+        check,
+        call: if check { None } else { Some(min(s.level, s.stack) - s.bet) },
+        raise: if reopened { raise_range(structure, s) } else { None },
+        //nobody has raised, so only big blind has a bet which can be checked
+        option: check && s.stage == PREFLOP && s.level == s.big_blind,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn situation(stage: StageId, level: u64, bet: u64, stack: u64, pot: u64) -> Situation<u64> {
+        //on later streets the first bet is a raise from zero
+        let last_raise = if stage == PREFLOP { 10 } else { level };
+        Situation { stage, level, bet, stack, pot, big_blind: 10, last_raise, raises: 0 }
+    }
+
+    #[test]
+    fn no_limit_goes_up_to_the_stack() {
+        let limit = BettingStructure::NoLimit;
+        assert_eq!(raise_range(&limit, &situation(PREFLOP, 10, 5, 1000, 15)), Some((20, 1000)));
+        assert_eq!(raise_range(&limit, &situation(FLOP, 0, 0, 1000, 40)), Some((10, 1000)));
+        assert_eq!(raise_range(&limit, &situation(FLOP, 100, 0, 1000, 140)), Some((200, 1000)));
+    }
+
+    #[test]
+    fn re_raise_is_at_least_the_last_raise() {
+        let limit = BettingStructure::NoLimit;
+        //raise from 10 to 35, the next one must be to 60 at least
+        let odd = Situation { last_raise: 25, ..situation(PREFLOP, 35, 10, 1000, 50) };
+        assert_eq!(min_increment(&limit, &odd), 25);
+        assert_eq!(raise_range(&limit, &odd), Some((60, 1000)));
+
+        //raise can't be smaller than big blind
+        let small = Situation { last_raise: 4, ..situation(FLOP, 4, 0, 1000, 44) };
+        assert_eq!(raise_range(&limit, &small), Some((14, 1000)));
+
+        let fixed = BettingStructure::FixedLimit { small_bet: 10, big_bet: 20, max_raises: 3 };
+        assert_eq!(min_increment(&fixed, &odd), 10);
+        assert_eq!(min_increment(&fixed, &situation(RIVER, 0, 0, 1000, 40)), 20);
+    }
+
+    #[test]
+    fn pot_limit_is_bounded_by_the_pot() {
+        let limit = BettingStructure::PotLimit;
+        //small blind calls 5, then the pot is 20 and he can raise by it up to 30
+        assert_eq!(raise_range(&limit, &situation(PREFLOP, 10, 5, 1000, 15)), Some((20, 30)));
+        assert_eq!(raise_range(&limit, &situation(FLOP, 0, 0, 1000, 40)), Some((10, 40)));
+        //a bet of 30 into 40: calling makes 100, so the raise is up to 130
+        assert_eq!(raise_range(&limit, &situation(TURN, 30, 0, 1000, 70)), Some((60, 130)));
+        assert_eq!(raise_range(&limit, &situation(TURN, 30, 0, 100, 70)), Some((60, 100)));
+    }
+
+    #[test]
+    fn fixed_limit_has_steps_and_cap() {
+        let limit = BettingStructure::FixedLimit { small_bet: 10, big_bet: 20, max_raises: 3 };
+        assert_eq!(raise_range(&limit, &situation(PREFLOP, 10, 5, 1000, 15)), Some((20, 20)));
+        assert_eq!(raise_range(&limit, &situation(FLOP, 0, 0, 1000, 40)), Some((10, 10)));
+        assert_eq!(raise_range(&limit, &situation(TURN, 20, 0, 1000, 60)), Some((40, 40)));
+        assert_eq!(raise_range(&limit, &situation(RIVER, 0, 0, 1000, 60)), Some((20, 20)));
+
+        let capped = Situation { raises: 3, ..situation(FLOP, 40, 30, 1000, 120) };
+        assert_eq!(raise_range(&limit, &capped), None);
+    }
+
+    #[test]
+    fn short_stack_can_only_go_all_in() {
+        assert_eq!(raise_range(&BettingStructure::NoLimit, &situation(FLOP, 100, 0, 150, 140)), Some((150, 150)));
+        assert_eq!(raise_range(&BettingStructure::PotLimit, &situation(FLOP, 100, 0, 150, 140)), Some((150, 150)));
+        assert_eq!(raise_range(&BettingStructure::NoLimit, &situation(FLOP, 100, 0, 100, 140)), None);
+        assert_eq!(raise_range(&BettingStructure::NoLimit, &situation(FLOP, 100, 0, 60, 140)), None);
+    }
+
+    #[test]
+    fn legal_actions_follow_the_bet() {
+        let limit = BettingStructure::NoLimit;
+        assert_eq!(legal_actions(&limit, &situation(FLOP, 0, 0, 1000, 40), true),
+            LegalActions { check: true, call: None, raise: Some((10, 1000)), option: false });
+        assert_eq!(legal_actions(&limit, &situation(PREFLOP, 10, 5, 1000, 15), true),
+            LegalActions { check: false, call: Some(5), raise: Some((20, 1000)), option: false });
+
+        //calling for less is an all-in
+        assert_eq!(legal_actions(&limit, &situation(FLOP, 100, 0, 60, 140), true),
+            LegalActions { check: false, call: Some(60), raise: None, option: false });
+
+        //an incomplete raise doesn't reopen betting
+        assert_eq!(legal_actions(&limit, &situation(FLOP, 100, 50, 1000, 240), false),
+            LegalActions { check: false, call: Some(50), raise: None, option: false });
+    }
+
+    #[test]
+    fn big_blind_has_the_option() {
+        let limit = BettingStructure::NoLimit;
+        assert_eq!(legal_actions(&limit, &situation(PREFLOP, 10, 10, 1000, 20), true),
+            LegalActions { check: true, call: None, raise: Some((20, 1000)), option: true });
+
+        //after a raise it is an ordinary call
+        let raised = Situation { last_raise: 20, ..situation(PREFLOP, 30, 10, 1000, 40) };
+        assert_eq!(legal_actions(&limit, &raised, true).option, false);
+        assert_eq!(legal_actions(&limit, &situation(FLOP, 0, 0, 1000, 40), true).option, false);
+    }
+}
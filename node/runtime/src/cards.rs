@@ -3,6 +3,8 @@
 use rstd::prelude::*;
 use core::debug_assert;
 
+pub const DECK_SIZE: usize = 52;
+
 pub type Nominal = u8;
 
 pub const J: Nominal = 11;
@@ -68,38 +70,130 @@ pub fn decode(bytes: &[u8]) -> Vec<Card> {
         .collect()
 }
 
-//a bit unfair random generation, because 16 % 13 != 0
-pub fn from_random(byte: u8) -> Card {
-    let high = byte >> 4;
-    let low  = byte & 15;
-    debug_assert!(byte == low + high * 16);
+///Checks that no card repeats, e.g. among all cards opened in a round
+pub fn are_distinct(cards: &[Card]) -> bool {
+    let mut unique = cards.to_vec();
+    unique.sort();
+    unique.dedup();
+    unique.len() == cards.len()
+}
+
+///All cards in the order of nominals, suits go in the order of their identifiers
+pub fn deck() -> Vec<Card> {
+    (1..14)
+        .flat_map(|nominal| (1..5).map(move |suit| Card { nominal, suit }))
+        .collect()
+}
+
+///Fisher–Yates shuffle of the whole deck driven by random bytes;
+///returns `None` only if the source of randomness is exhausted
+pub fn shuffle<I: Iterator<Item = u8>>(random: &mut I) -> Option<Vec<Card>> {
+    let mut cards = deck();
+    for i in (1..cards.len()).rev() {
+        let j = uniform(random, i as u8 + 1)?;
+        cards.swap(i, j as usize);
+    }
+    Some(cards)
+}
 
-    let card = Card {
-        nominal: high % 13 + 1,
-        suit: low % 4 + 1,
-    };
+///Uniformly distributed number from `0` to `bound - 1`: bytes which don't
+///fit into the largest multiple of `bound` are rejected, so no value is more frequent
+fn uniform<I: Iterator<Item = u8>>(random: &mut I, bound: u8) -> Option<u8> {
+    debug_assert!(bound > 0);
 
-    card.is_valid();
-    card
+    let limit = 256 - 256 % bound as u16;
+    random.find(|&byte| (byte as u16) < limit)
+        .map(|byte| byte % bound)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    //xorshift is good enough to check the distribution
+    fn bytes(seed: u64) -> impl Iterator<Item = u8> {
+        let mut state = seed;
+        (0..).map(move |_: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        })
+    }
+
+    #[test]
+    fn deck_is_complete() {
+        let cards = deck();
+        assert_eq!(cards.len(), DECK_SIZE);
+        assert_eq!(cards[0], spades(A));
+        assert_eq!(cards[DECK_SIZE - 1], diamonds(K));
+        assert!(cards.iter().all(|card| card.is_valid()));
+
+        let mut unique = cards.clone();
+        unique.dedup();
+        assert_eq!(unique.len(), DECK_SIZE);
+    }
+
     #[test]
-    fn generate_from_random() {
-        assert!(from_random(3) == diamonds(A));
-        assert!(from_random(16) == spades(2));
-        assert!(from_random(211) == diamonds(A));
-        assert!(from_random(224) == spades(2));
-
-        assert!(from_random(0  << 4) == spades(A));
-        assert!(from_random(12 << 4) == spades(K));
-
-        assert!(from_random(13 << 4) == spades(A));
-        assert!(from_random(14 << 4) == spades(2));
-        assert!(from_random(15 << 4) == spades(3));
-        //this means that A, 2 and 3 are a bit more frequent
+    fn repeated_cards_are_detected() {
+        assert!(are_distinct(&[spades(A), hearts(A), spades(K)]));
+        assert!(!are_distinct(&[spades(A), hearts(A), spades(A)]));
+        assert!(are_distinct(&[]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut random = bytes(42);
+        let mut cards = shuffle(&mut random).unwrap();
+        assert!(cards != deck());
+
+        cards.sort();
+        assert_eq!(cards, deck());
+    }
+
+    #[test]
+    fn shuffle_fails_without_randomness() {
+        assert_eq!(shuffle(&mut vec![0; 10].into_iter()), None);
+        assert_eq!(shuffle(&mut vec![255; 100].into_iter()), None);
+    }
+
+    #[test]
+    fn every_value_is_equally_likely() {
+        //every possible byte is tried exactly once
+        for bound in 1..=DECK_SIZE as u8 {
+            let mut counts = vec![0; bound as usize];
+            for byte in 0..=255u8 {
+                if let Some(value) = uniform(&mut Some(byte).into_iter(), bound) {
+                    counts[value as usize] += 1;
+                }
+            }
+            assert!(counts.iter().all(|&count| count == counts[0]), "bias for {}", bound);
+        }
+    }
+
+    #[test]
+    fn cards_are_distributed_uniformly() {
+        const SAMPLES: usize = 52 * 200;
+
+        let mut random = bytes(7);
+        let mut top = vec![0f64; DECK_SIZE];
+        let mut positions = vec![0f64; DECK_SIZE];
+        for _ in 0..SAMPLES {
+            let cards = shuffle(&mut random).unwrap();
+            let first = deck().iter().position(|card| card == &cards[0]).unwrap();
+            top[first] += 1.0;
+            let ace = cards.iter().position(|card| card == &spades(A)).unwrap();
+            positions[ace] += 1.0;
+        }
+
+        //chi-squared statistic with 51 degrees of freedom,
+        //the critical value for significance level 0.001 is 87.97
+        let expected = (SAMPLES / DECK_SIZE) as f64;
+        let chi_squared = |counts: &[f64]| counts.iter()
+            .map(|count| (count - expected) * (count - expected) / expected)
+            .sum::<f64>();
+
+        assert!(chi_squared(&top) < 87.97);
+        assert!(chi_squared(&positions) < 87.97);
+    }
+}
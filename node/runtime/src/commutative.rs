@@ -0,0 +1,717 @@
+///Commutative (SRA / Pohlig–Hellman) exponentiation cipher for mental poker
+
+///All participants share the same safe prime `P = 2Q + 1`, it is the 2048-bit
+///MODP group from RFC 3526. A key pair consists of two exponents `e` and `d`
+///such that `e * d = 1 (mod Q)`: encryption is `m^e mod P` and decryption
+///is `c^d mod P`. Since exponents commute, layers of encryption made by different
+///participants can be removed in any order, e.g. `D_a(D_b(E_a(E_b(m)))) == m`.
+
+///Cards are encoded as quadratic residues, so they stay in the subgroup of order `Q`
+///and encryption doesn't leak the Legendre symbol of a card.
+
+///Encryption exponents are never published, since `e` reveals `d`; instead,
+///a participant publishes `g^d`, which allows to check his secret when it is revealed.
+
+use crate::cards::{self, Card};
+
+use rstd::result;
+use rstd::prelude::*;
+
+type Result = result::Result<Vec<u8>, &'static str>;
+
+const LIMBS: usize = 32;
+
+///Size of a group element and of an exponent in big-endian format
+pub const ELEMENT_SIZE: usize = LIMBS * 8;
+
+type Limbs = [u64; LIMBS];
+
+///Generator of the subgroup of quadratic residues
+const GENERATOR: u64 = 4;
+
+struct Modulus {
+    value: Limbs,
+    ///`R^2 mod value` with `R = 2^2048`, converts numbers to Montgomery form
+    r2: Limbs,
+    ///`-value^(-1) mod 2^64`
+    inv: u64,
+}
+
+///Prime modulus of the group, limbs are little-endian
+const GROUP: Modulus = Modulus {
+    value: [
+        0xffffffffffffffff, 0x15728e5a8aacaa68, 0x15d2261898fa0510, 0x3995497cea956ae5,
+        0xde2bcbf695581718, 0xb5c55df06f4c52c9, 0x9b2783a2ec07a28f, 0xe39e772c180e8603,
+        0x32905e462e36ce3b, 0xf1746c08ca18217c, 0x670c354e4abc9804, 0x9ed529077096966d,
+        0x1c62f356208552bb, 0x83655d23dca3ad96, 0x69163fa8fd24cf5f, 0x98da48361c55d39a,
+        0xc2007cb8a163bf05, 0x49286651ece45b3d, 0xae9f24117c4b1fe6, 0xee386bfb5a899fa5,
+        0x0bff5cb6f406b7ed, 0xf44c42e9a637ed6b, 0xe485b576625e7ec6, 0x4fe1356d6d51c245,
+        0x302b0a6df25f1437, 0xef9519b3cd3a431b, 0x514a08798e3404dd, 0x020bbea63b139b22,
+        0x29024e088a67cc74, 0xc4c6628b80dc1cd1, 0xc90fdaa22168c234, 0xffffffffffffffff,
+    ],
+    r2: [
+        0x477122ce125fb664, 0xb03548fb9b38d313, 0x4c2153ff6fd412c1, 0x2a092b50873f9bc6,
+        0xbbc71629fcb7f5f9, 0x4bec06e136bd84e7, 0x27ba725a6b020cb1, 0xf8115426ed939eeb,
+        0x4bc1b1878a0e30d9, 0x5620820e258633ff, 0x074ed6ab785a3071, 0xf228105f81f1cb61,
+        0x570e436f4e2e6f7f, 0x5ca52ff7d7450bd9, 0x552272d275f10a7e, 0xac2b7925739c7978,
+        0xa2f88257325b54d0, 0xbc821c9de8d72bd5, 0xdbd442b3866d2986, 0x9478951b70c4b2ce,
+        0x5d998fb394910c76, 0xf273b2937e300867, 0x8c106bbe38569f92, 0xf83c92cb14e992c5,
+        0xd85d6e7eed6880dd, 0xeb5b276fbe06a1df, 0x2a492090fa11e105, 0x63bdd96d19ea00be,
+        0x272382970a1698ab, 0x8a3a686c9240c974, 0x3ed8570366613000, 0x0cd37a33628b3197,
+    ],
+    inv: 0x0000000000000001,
+};
+
+///Prime order of the subgroup of quadratic residues, exponents are taken modulo it
+const ORDER: Modulus = Modulus {
+    value: [
+        0x7fffffffffffffff, 0x0ab9472d45565534, 0x8ae9130c4c7d0288, 0x1ccaa4be754ab572,
+        0xef15e5fb4aac0b8c, 0xdae2aef837a62964, 0xcd93c1d17603d147, 0xf1cf3b960c074301,
+        0x19482f23171b671d, 0x78ba3604650c10be, 0xb3861aa7255e4c02, 0xcf6a9483b84b4b36,
+        0x0e3179ab1042a95d, 0xc1b2ae91ee51d6cb, 0x348b1fd47e9267af, 0xcc6d241b0e2ae9cd,
+        0xe1003e5c50b1df82, 0x24943328f6722d9e, 0xd74f9208be258ff3, 0xf71c35fdad44cfd2,
+        0x85ffae5b7a035bf6, 0x7a262174d31bf6b5, 0xf242dabb312f3f63, 0xa7f09ab6b6a8e122,
+        0x98158536f92f8a1b, 0xf7ca8cd9e69d218d, 0x28a5043cc71a026e, 0x0105df531d89cd91,
+        0x948127044533e63a, 0x62633145c06e0e68, 0xe487ed5110b4611a, 0x7fffffffffffffff,
+    ],
+    r2: [
+        0x8ee2459c24bf6cca, 0x2b31aa1e375532c1, 0x1c73beeccc8ba3b0, 0x052a18b9ac95f374,
+        0x694fdd4a2866c3c1, 0x338432c8a5bba7e6, 0xcd76f554d46907f2, 0xff3c9b0607704a6c,
+        0xd7b6c778606c05f2, 0x23cf47ef24bbfba2, 0xcc10dfaebe562a18, 0x6468d77cb844abb0,
+        0x30904093e65ba3a8, 0xc09000035a0bd9a9, 0x5bd42dd22b8a05ba, 0xbbde3e1ecfee2068,
+        0xa5b0793b4f97ce75, 0x8d55dbd975aef212, 0xbc104c8393b54ad7, 0x20e5f560f65b4881,
+        0xd85fb472e83add5a, 0x5a35837b3ffcb6b8, 0xfc00917bb173a487, 0x71253136ca869f19,
+        0x7cb293c9ce32c1b4, 0x925009c4f47460a8, 0x8af913cd2af4d80f, 0xc681e58cd05d93ed,
+        0xa4fc43e703f78248, 0x0c77d45f3fe5fe3a, 0xacb8a1bf238fab97, 0x0cd37a33628b3197,
+    ],
+    inv: 0x8000000000000001,
+};
+
+///Encrypts a sequence of group elements with the key;
+///the result can be encrypted again or decrypted in any order
+pub fn encrypt(data: &[u8], key: &[u8]) -> Result {
+    let exponent = to_exponent(key)?;
+    if data.is_empty() || data.len() % ELEMENT_SIZE != 0 {
+        return Err("Data must consist of group elements");
+    }
+
+    let mut result = Vec::with_capacity(data.len());
+    for chunk in data.chunks(ELEMENT_SIZE) {
+        let element = to_limbs(chunk);
+        if !is_element(&element) {
+            return Err("Invalid group element");
+        }
+        result.extend(from_limbs(&pow(&GROUP, &element, &exponent)));
+    }
+    Ok(result)
+}
+
+///Removes one layer of encryption made with the corresponding key
+pub fn decrypt(data: &[u8], secret: &[u8]) -> Result {
+    encrypt(data, secret)
+}
+
+///Computes the public key `g^d` of the secret
+pub fn public(secret: &[u8]) -> Result {
+    let secret = to_exponent(secret)?;
+    Ok(from_limbs(&pow(&GROUP, &generator(), &secret)))
+}
+
+///Checks that the revealed secret corresponds to the public key
+pub fn keypair_is_valid(public: &[u8], secret: &[u8]) -> bool {
+    if public.len() != ELEMENT_SIZE {
+        return false;
+    }
+
+    match to_exponent(secret) {
+        Ok(secret) => from_limbs(&pow(&GROUP, &generator(), &secret)) == public,
+        _ => false
+    }
+}
+
+///Checks that the key is `g^d` for some exponent `d`: a quadratic residue other than 1,
+///since the generator has prime order, every such residue is a public key
+pub fn is_public_key(key: &[u8]) -> bool {
+    if key.len() != ELEMENT_SIZE {
+        return false;
+    }
+
+    let element = to_limbs(key);
+    is_element(&element) && is_residue(&element) && element != one()
+}
+
+///Computes the encryption exponent for the decryption exponent and vice versa,
+///using Fermat's little theorem: `e^(-1) = e^(Q-2) mod Q`
+#[cfg(any(test, feature = "std"))]
+pub fn inverse(exponent: &[u8]) -> Result {
+    let exponent = to_exponent(exponent)?;
+
+    let mut power = ORDER.value;
+    power[0] -= 2;
+    Ok(from_limbs(&pow(&ORDER, &exponent, &power)))
+}
+
+///Checks that the key can be used as an exponent of the cipher
+#[cfg(any(test, feature = "std"))]
+pub fn is_exponent(key: &[u8]) -> bool {
+    to_exponent(key).is_ok()
+}
+
+///Multiplies exponents modulo `Q`
+#[cfg(any(test, feature = "std"))]
+pub fn multiply(a: &[u8], b: &[u8]) -> Result {
+    let a = to_exponent(a)?;
+    let b = to_exponent(b)?;
+    Ok(from_limbs(&mul(&ORDER, &mul(&ORDER, &a, &b), &ORDER.r2)))
+}
+
+///Adds exponents modulo `Q`; fails when the sum is 0, which is not an exponent
+#[cfg(any(test, feature = "std"))]
+pub fn add(a: &[u8], b: &[u8]) -> Result {
+    let a = to_exponent(a)?;
+    let b = to_exponent(b)?;
+
+    let mut sum = [0; LIMBS];
+    let mut carry = false;
+    for i in 0..LIMBS {
+        let (x, c1) = a[i].overflowing_add(b[i]);
+        let (x, c2) = x.overflowing_add(carry as u64);
+        sum[i] = x;
+        carry = c1 || c2;
+    }
+    if carry || !less(&sum, &ORDER.value) {
+        subtract(&mut sum, &ORDER.value);
+    }
+
+    let sum = from_limbs(&sum);
+    to_exponent(&sum)?;
+    Ok(sum)
+}
+
+///Computes `Q - e`, so that `m^e * m^(Q-e) = 1` for every element `m`
+#[cfg(any(test, feature = "std"))]
+pub fn negate(exponent: &[u8]) -> Result {
+    let exponent = to_exponent(exponent)?;
+    let mut negated = ORDER.value;
+    subtract(&mut negated, &exponent);
+    Ok(from_limbs(&negated))
+}
+
+///Computes product of `elements[i]^weights[i]` at once,
+///it is much faster than separate exponentiations for small weights
+pub fn combine(elements: &[&[u8]], weights: &[u64]) -> Result {
+    if elements.len() != weights.len() {
+        return Err("Every element must have a weight");
+    }
+
+    let mut bases = Vec::with_capacity(elements.len());
+    for element in elements.iter() {
+        if element.len() != ELEMENT_SIZE || !is_element(&to_limbs(element)) {
+            return Err("Invalid group element");
+        }
+        bases.push(mul(&GROUP, &to_limbs(element), &GROUP.r2));
+    }
+
+    let mut result = mul(&GROUP, &one(), &GROUP.r2);
+    for bit in (0..64).rev() {
+        result = mul(&GROUP, &result, &result);
+        for (base, weight) in bases.iter().zip(weights.iter()) {
+            if (weight >> bit) & 1 == 1 {
+                result = mul(&GROUP, &result, base);
+            }
+        }
+    }
+    Ok(from_limbs(&mul(&GROUP, &result, &one())))
+}
+
+///Checks that `a = b` or `a = -b (mod P)`
+pub fn equal_up_to_sign(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != ELEMENT_SIZE || b.len() != ELEMENT_SIZE {
+        return false;
+    }
+    if a == b {
+        return true;
+    }
+
+    let mut negated = GROUP.value;
+    subtract(&mut negated, &to_limbs(b));
+    from_limbs(&negated) == a
+}
+
+///Checks that the data consists of `size` different quadratic residues,
+///i.e. possible encryptions of different cards
+pub fn is_deck(data: &[u8], size: usize) -> bool {
+    if data.len() != size * ELEMENT_SIZE {
+        return false;
+    }
+
+    let mut elements: Vec<&[u8]> = data.chunks(ELEMENT_SIZE).collect();
+    if !elements.iter().all(|chunk| {
+        let element = to_limbs(chunk);
+        is_element(&element) && is_residue(&element)
+    }) {
+        return false;
+    }
+
+    elements.sort();
+    elements.dedup();
+    elements.len() == size
+}
+
+///Cards are mapped to small squares `(index + 2)^2`
+pub fn encode(cards: Vec<&Card>) -> Vec<u8> {
+    cards.into_iter()
+        .map(|card| {
+            let root = ((card.nominal - 1) * 4 + (card.suit - 1)) as u64 + 2;
+            let mut element = [0; LIMBS];
+            element[0] = root * root;
+            from_limbs(&element)
+        })
+        .flatten()
+        .collect()
+}
+
+///Returns `None` if some of the elements is not an encoded card,
+///e.g. when not all layers of encryption are removed
+pub fn decode(data: &[u8]) -> Option<Vec<Card>> {
+    if data.len() % ELEMENT_SIZE != 0 {
+        return None;
+    }
+
+    data.chunks(ELEMENT_SIZE)
+        .map(|chunk| {
+            let element = to_limbs(chunk);
+            if element[1..].iter().any(|&limb| limb != 0) {
+                return None;
+            }
+
+            (0..cards::DECK_SIZE as u64)
+                .find(|index| (index + 2) * (index + 2) == element[0])
+                .map(|index| Card {
+                    nominal: (index / 4) as u8 + 1,
+                    suit: (index % 4) as u8 + 1
+                })
+        })
+        .collect()
+}
+
+fn to_exponent(bytes: &[u8]) -> result::Result<Limbs, &'static str> {
+    if bytes.len() != ELEMENT_SIZE {
+        return Err("Key has wrong size");
+    }
+
+    let exponent = to_limbs(bytes);
+    if is_zero(&exponent) || !less(&exponent, &ORDER.value) {
+        return Err("Key is out of range");
+    }
+    Ok(exponent)
+}
+
+fn to_limbs(bytes: &[u8]) -> Limbs {
+    debug_assert!(bytes.len() == ELEMENT_SIZE);
+
+    let mut limbs = [0; LIMBS];
+    for (i, chunk) in bytes.rchunks(8).enumerate() {
+        limbs[i] = chunk.iter().fold(0, |limb, &byte| (limb << 8) | byte as u64);
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &Limbs) -> Vec<u8> {
+    limbs.iter().rev()
+        .flat_map(|limb| (0..8).rev().map(move |i| (limb >> (8 * i)) as u8))
+        .collect()
+}
+
+fn is_element(a: &Limbs) -> bool {
+    !is_zero(a) && less(a, &GROUP.value)
+}
+
+///Jacobi symbol `(a/P)` is 1 for quadratic residues,
+///it is computed with the binary algorithm without exponentiation
+fn is_residue(a: &Limbs) -> bool {
+    let mut a = *a;
+    let mut n = GROUP.value;
+    let mut positive = true;
+
+    while !is_zero(&a) {
+        while a[0] & 1 == 0 {
+            shift_right(&mut a);
+            if n[0] & 7 == 3 || n[0] & 7 == 5 {
+                positive = !positive;
+            }
+        }
+        if less(&a, &n) {
+            rstd::mem::swap(&mut a, &mut n);
+            if a[0] & 3 == 3 && n[0] & 3 == 3 {
+                positive = !positive;
+            }
+        }
+        subtract(&mut a, &n);
+    }
+    positive && n == one()
+}
+
+fn generator() -> Limbs {
+    let mut limbs = [0; LIMBS];
+    limbs[0] = GENERATOR;
+    limbs
+}
+
+fn one() -> Limbs {
+    let mut limbs = [0; LIMBS];
+    limbs[0] = 1;
+    limbs
+}
+
+fn is_zero(a: &Limbs) -> bool {
+    a.iter().all(|&limb| limb == 0)
+}
+
+fn less(a: &Limbs, b: &Limbs) -> bool {
+    for i in (0..LIMBS).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+fn shift_right(a: &mut Limbs) {
+    for i in 0..LIMBS - 1 {
+        a[i] = (a[i] >> 1) | (a[i + 1] << 63);
+    }
+    a[LIMBS - 1] >>= 1;
+}
+
+fn subtract(a: &mut Limbs, b: &Limbs) {
+    let mut borrow = false;
+    for i in 0..LIMBS {
+        let (x, b1) = a[i].overflowing_sub(b[i]);
+        let (x, b2) = x.overflowing_sub(borrow as u64);
+        a[i] = x;
+        borrow = b1 || b2;
+    }
+}
+
+///Montgomery multiplication: `a * b / R mod m`
+fn mul(m: &Modulus, a: &Limbs, b: &Limbs) -> Limbs {
+    let mut t = [0u64; LIMBS + 2];
+
+    for i in 0..LIMBS {
+        let mut carry = 0u128;
+        for j in 0..LIMBS {
+            let x = t[j] as u128 + a[j] as u128 * b[i] as u128 + carry;
+            t[j] = x as u64;
+            carry = x >> 64;
+        }
+        let x = t[LIMBS] as u128 + carry;
+        t[LIMBS] = x as u64;
+        t[LIMBS + 1] = (x >> 64) as u64;
+
+        let k = t[0].wrapping_mul(m.inv);
+        let x = t[0] as u128 + k as u128 * m.value[0] as u128;
+        let mut carry = x >> 64;
+        for j in 1..LIMBS {
+            let x = t[j] as u128 + k as u128 * m.value[j] as u128 + carry;
+            t[j - 1] = x as u64;
+            carry = x >> 64;
+        }
+        let x = t[LIMBS] as u128 + carry;
+        t[LIMBS - 1] = x as u64;
+        t[LIMBS] = t[LIMBS + 1] + (x >> 64) as u64;
+    }
+
+    let mut result = [0; LIMBS];
+    result.copy_from_slice(&t[..LIMBS]);
+    if t[LIMBS] != 0 || !less(&result, &m.value) {
+        subtract(&mut result, &m.value);
+    }
+    result
+}
+
+fn pow(m: &Modulus, base: &Limbs, exponent: &Limbs) -> Limbs {
+    let base = mul(m, base, &m.r2);
+    let mut result = mul(m, &one(), &m.r2);
+
+    for i in (0..LIMBS * 64).rev() {
+        result = mul(m, &result, &result);
+        if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+            result = mul(m, &result, &base);
+        }
+    }
+    mul(m, &result, &one())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{Card, spades, hearts, clubs, diamonds, A, J, Q, K};
+
+    fn key(seed: u64) -> Vec<u8> {
+        let mut limbs = [0; LIMBS];
+        for i in 0..LIMBS {
+            limbs[i] = seed.wrapping_mul(i as u64 + 1).rotate_left(i as u32);
+        }
+        limbs[LIMBS - 1] >>= 2;
+        from_limbs(&limbs)
+    }
+
+    fn keypair(seed: u64) -> (Vec<u8>, Vec<u8>) {
+        let key = key(seed);
+        let secret = inverse(&key).unwrap();
+        (key, secret)
+    }
+
+    fn small(n: u64) -> Vec<u8> {
+        let mut limbs = [0; LIMBS];
+        limbs[0] = n;
+        from_limbs(&limbs)
+    }
+
+    fn deck() -> Vec<Card> {
+        (1..14)
+            .flat_map(|n| vec![spades(n), hearts(n), clubs(n), diamonds(n)])
+            .collect()
+    }
+
+    #[test]
+    fn cards_are_encoded() {
+        let deck = deck();
+        let encoded = encode(deck.iter().collect());
+        assert_eq!(encoded.len(), 52 * ELEMENT_SIZE);
+        assert_eq!(decode(&encoded), Some(deck));
+
+        assert_eq!(&encode(vec![&spades(A)])[ELEMENT_SIZE - 2..], &[0, 4]);
+        assert_eq!(decode(&from_limbs(&one())), None);
+    }
+
+    #[test]
+    fn small_exponents_are_computed_exactly() {
+        let mut three = [0; LIMBS];
+        three[0] = 3;
+        let cube = encrypt(&encode(vec![&spades(A)]), &from_limbs(&three)).unwrap();
+        assert_eq!(&cube[ELEMENT_SIZE - 1..], &[64]);
+        assert!(cube[..ELEMENT_SIZE - 1].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn composition_is_identity() {
+        let message = encode(vec![&hearts(Q), &clubs(7), &spades(A)]);
+        let (key, secret) = keypair(0x1234_5678_9abc_def1);
+
+        let encrypted = encrypt(&message, &key).unwrap();
+        assert_ne!(encrypted, message);
+        assert_eq!(decode(&encrypted), None);
+        assert_eq!(decrypt(&encrypted, &secret).unwrap(), message);
+    }
+
+    #[test]
+    fn layers_are_removed_in_any_order() {
+        let message = encode(vec![&diamonds(K), &hearts(2)]);
+        let (key_a, secret_a) = keypair(0x0bad_cafe_dead_beef);
+        let (key_b, secret_b) = keypair(0x1337_c0de_f00d_babe);
+
+        let ab = encrypt(&encrypt(&message, &key_b).unwrap(), &key_a).unwrap();
+        let ba = encrypt(&encrypt(&message, &key_a).unwrap(), &key_b).unwrap();
+        assert_eq!(ab, ba);
+
+        for encrypted in vec![ab, ba] {
+            let a_first = decrypt(&decrypt(&encrypted, &secret_a).unwrap(), &secret_b).unwrap();
+            let b_first = decrypt(&decrypt(&encrypted, &secret_b).unwrap(), &secret_a).unwrap();
+            assert_eq!(a_first, message);
+            assert_eq!(b_first, message);
+        }
+    }
+
+    #[test]
+    fn three_layers_are_removed_in_any_order() {
+        let message = encode(vec![&spades(10)]);
+        let pairs: Vec<(Vec<u8>, Vec<u8>)> = vec![11, 23, 37].into_iter()
+            .map(|seed: u64| keypair(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+            .collect();
+
+        let encrypted = pairs.iter()
+            .fold(message.clone(), |data, (key, _)| encrypt(&data, key).unwrap());
+
+        let orders = vec![[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        for order in orders.iter() {
+            let decrypted = order.iter()
+                .fold(encrypted.clone(), |data, &i| decrypt(&data, &pairs[i].1).unwrap());
+            assert_eq!(decrypted, message);
+        }
+    }
+
+    #[test]
+    fn inverse_removes_encryption() {
+        let (key, secret) = keypair(0x7777_0000_1111_2222);
+        assert_eq!(inverse(&secret).unwrap(), key);
+
+        let message = encode(vec![&clubs(J)]);
+        let encrypted = encrypt(&message, &secret).unwrap();
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), message);
+    }
+
+    #[test]
+    fn validation_works() {
+        let (_, secret_a) = keypair(5);
+        let (_, secret_b) = keypair(7);
+        let public_a = public(&secret_a).unwrap();
+        let public_b = public(&secret_b).unwrap();
+
+        let mut two = [0; LIMBS];
+        two[0] = 2;
+        assert_eq!(public(&from_limbs(&two)).unwrap(), encode(vec![&clubs(A)]));
+
+        assert!(keypair_is_valid(&public_a, &secret_a));
+        assert!(keypair_is_valid(&public_b, &secret_b));
+        assert!(!keypair_is_valid(&public_a, &secret_b));
+        assert!(!keypair_is_valid(&secret_a, &public_a));
+        assert!(!keypair_is_valid(&public_a[1..], &secret_a));
+        assert!(!keypair_is_valid(&public_a, &secret_a[1..]));
+    }
+
+    #[test]
+    fn exponents_are_multiplied() {
+        let (key_a, secret_a) = keypair(13);
+        let (key_b, _) = keypair(17);
+        assert_eq!(multiply(&key_a, &secret_a).unwrap(), small(1));
+        assert_eq!(multiply(&small(6), &small(7)).unwrap(), small(42));
+
+        let message = encode(vec![&hearts(9)]);
+        let twice = encrypt(&encrypt(&message, &key_a).unwrap(), &key_b).unwrap();
+        assert_eq!(encrypt(&message, &multiply(&key_a, &key_b).unwrap()).unwrap(), twice);
+    }
+
+    #[test]
+    fn exponents_are_added() {
+        assert_eq!(add(&small(6), &small(7)).unwrap(), small(13));
+
+        //`Q - 1 + 2 = 1 (mod Q)`
+        let mut last = ORDER.value;
+        last[0] -= 1;
+        assert_eq!(add(&from_limbs(&last), &small(2)).unwrap(), small(1));
+        assert!(add(&from_limbs(&last), &small(1)).is_err());
+
+        let (_, secret_a) = keypair(23);
+        let (_, secret_b) = keypair(29);
+        let sum = add(&secret_a, &secret_b).unwrap();
+        let product = combine(&[&public(&secret_a).unwrap(), &public(&secret_b).unwrap()], &[1, 1]).unwrap();
+        assert_eq!(public(&sum).unwrap(), product);
+    }
+
+    #[test]
+    fn negated_exponents_cancel() {
+        let (key, _) = keypair(37);
+        let negated = negate(&key).unwrap();
+        assert!(add(&key, &negated).is_err());
+
+        let message = encode(vec![&hearts(K)]);
+        let power = encrypt(&message, &key).unwrap();
+        let inverse = encrypt(&message, &negated).unwrap();
+        assert_eq!(combine(&[&power, &inverse], &[1, 1]).unwrap(), small(1));
+        assert!(negate(&small(0)).is_err());
+    }
+
+    #[test]
+    fn public_keys_are_recognized() {
+        let (_, secret) = keypair(31);
+        assert!(is_public_key(&public(&secret).unwrap()));
+        assert!(is_public_key(&encode(vec![&spades(2)])));
+
+        assert!(!is_public_key(&small(1)));
+        assert!(!is_public_key(&small(0)));
+        let mut negated = GROUP.value;
+        subtract(&mut negated, &to_limbs(&public(&secret).unwrap()));
+        assert!(!is_public_key(&from_limbs(&negated)));
+        assert!(!is_public_key(&from_limbs(&GROUP.value)));
+        assert!(!is_public_key(&public(&secret).unwrap()[1..]));
+    }
+
+    #[test]
+    fn elements_are_combined() {
+        let a = encode(vec![&spades(5)]);
+        let b = encode(vec![&diamonds(Q)]);
+
+        assert_eq!(combine(&[&a, &b], &[3, 0]).unwrap(), encrypt(&a, &small(3)).unwrap());
+        assert_eq!(combine(&[&a, &a], &[1, 2]).unwrap(), encrypt(&a, &small(3)).unwrap());
+
+        let (key, _) = keypair(19);
+        let big = encrypt(&a, &key).unwrap();
+        let expected = encrypt(&big, &small(0xdead_beef)).unwrap();
+        assert_eq!(combine(&[&big], &[0xdead_beef]).unwrap(), expected);
+        assert!(combine(&[&a], &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn sign_is_ignored() {
+        let a = encode(vec![&clubs(3)]);
+        let mut negated = GROUP.value;
+        subtract(&mut negated, &to_limbs(&a));
+        let negated = from_limbs(&negated);
+
+        assert!(equal_up_to_sign(&a, &a));
+        assert!(equal_up_to_sign(&negated, &a));
+        assert!(equal_up_to_sign(&a, &negated));
+        assert!(!equal_up_to_sign(&a, &encode(vec![&clubs(4)])));
+    }
+
+    #[test]
+    fn residues_are_recognized() {
+        for n in 2..12 {
+            let mut limbs = [0; LIMBS];
+            limbs[0] = n;
+            let euler = pow(&GROUP, &limbs, &ORDER.value) == one();
+            assert_eq!(is_residue(&limbs), euler);
+        }
+
+        let (key, _) = keypair(23);
+        let encrypted = to_limbs(&encrypt(&encode(vec![&spades(K)]), &key).unwrap());
+        assert!(is_residue(&encrypted));
+
+        let mut negated = GROUP.value;
+        subtract(&mut negated, &encrypted);
+        assert!(!is_residue(&negated));
+    }
+
+    #[test]
+    fn decks_are_validated() {
+        let (key, _) = keypair(9);
+        let cards = deck();
+        let encrypted = encrypt(&encode(cards.iter().collect()), &key).unwrap();
+        assert!(is_deck(&encrypted, cards::DECK_SIZE));
+
+        let mut duplicated = encrypted.clone();
+        duplicated[..ELEMENT_SIZE].copy_from_slice(&encrypted[ELEMENT_SIZE..2 * ELEMENT_SIZE]);
+        assert!(!is_deck(&duplicated, cards::DECK_SIZE));
+
+        let mut invalid = encrypted.clone();
+        invalid[..ELEMENT_SIZE].copy_from_slice(&[0; ELEMENT_SIZE]);
+        assert!(!is_deck(&invalid, cards::DECK_SIZE));
+
+        let mut negated = encrypted.clone();
+        let mut element = GROUP.value;
+        subtract(&mut element, &to_limbs(&encrypted[..ELEMENT_SIZE]));
+        negated[..ELEMENT_SIZE].copy_from_slice(&from_limbs(&element));
+        assert!(!is_deck(&negated, cards::DECK_SIZE));
+
+        assert!(!is_deck(&encrypted[ELEMENT_SIZE..], cards::DECK_SIZE));
+    }
+
+    #[test]
+    fn invalid_input_is_rejected() {
+        let (key, _) = keypair(3);
+        let zero = vec![0; ELEMENT_SIZE];
+        let modulus = from_limbs(&GROUP.value);
+        let order = from_limbs(&ORDER.value);
+        let card = encode(vec![&spades(A)]);
+
+        assert!(encrypt(&zero, &key).is_err());
+        assert!(encrypt(&modulus, &key).is_err());
+        assert!(encrypt(&card[1..], &key).is_err());
+        assert!(encrypt(&[], &key).is_err());
+        assert!(encrypt(&card, &zero).is_err());
+        assert!(encrypt(&card, &order).is_err());
+        assert!(encrypt(&card, &key[1..]).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use parity_codec::{Encode, Decode};
+
+///Reason why a transaction of the poker module has failed;
+///it is encoded in events, so clients can react to it and translate it.
+///Codes are indices of variants, new ones go to the end
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum PokerError {
+    //tables and seats
+    NoSuchGame,
+    AlreadySeated,
+    GameFull,
+    ///Seats at tables of multi-table tournaments are assigned by the tournament
+    ManagedTable,
+    NotParticipant,
+    NotInRound,
+    CantSeat,
+    CantLeave,
+    GameInProgress,
+
+    //chips
+    BuyInOutOfLimits,
+    ///The account can't reserve the buy-in
+    InsufficientFunds,
+    StackNotEmpty,
+    StackEmpty,
+    StackAboveMaximum,
+    ZeroAmount,
+    ChipsNotForSale,
+
+    //tournaments
+    RegistrationClosed,
+    FixedBuyIn,
+    TournamentNotStarted,
+
+    //order of actions
+    NotYourTurn,
+    WaitForNextRound,
+    WaitForEndOfRound,
+    WaitForPlayers,
+
+    //shuffling
+    PreflopAlreadyStarted,
+    NotRevealingNonces,
+    NonceAlreadyRevealed,
+    NonceMismatch,
+    NotShuffling,
+    DeckNotDistinct,
+    InvalidShuffleProof,
+    NotEnoughRandomness,
+
+    //cards
+    CardsNotDealt,
+    BettingNotFinished,
+    FoldedCardsHidden,
+    StageAlreadySubmitted,
+    SecretMismatch,
+    NoCardsToFold,
+    InvalidPocketCards,
+    InvalidSharedCards,
+
+    //betting
+    CantCheck,
+    InsufficientChips,
+    RaiseNotAboveBet,
+    RaisingClosed,
+    RaiseAboveLimit,
+    RaiseTooSmall,
+
+    //rules of tables
+    InvalidBlinds,
+    AnteAboveBigBlind,
+    BuyInBelowBigBlind,
+    InvalidBuyInRange,
+    InvalidSeats,
+    InvalidTimeout,
+    InvalidFixedLimit,
+
+    //rules of sit-and-go tournaments
+    InvalidBuyIn,
+    NoLevels,
+    InvalidLevel,
+    StackBelowBigBlind,
+    InvalidEscalation,
+    InvalidPlaces,
+    InvalidPayouts,
+
+    //keys
+    InvalidKey,
+    InvalidKeyProof,
+
+    //locking of the deck
+    ShuffleFinished,
+    ShuffleNotFinished,
+    InvalidLockProof,
+
+    //cards
+    InvalidSecretSize,
+
+    //chips
+    ///The participant has lost all chips and must rebuy before the next hand
+    NoChips,
+
+    //multi-table tournaments
+    NoSuchTournament,
+    TournamentTooSmall,
+    EscalationByHands,
+    AlreadyRegistered,
+    TournamentFull,
+    TournamentAlreadyStarted,
+    NotRegistered,
+    NotHost,
+    NotEnoughEntrants,
+
+    //proofs
+    TooManyProofs,
+    NoSuchShuffle,
+    ///The challenged proof is not the one submitted with the shuffle
+    ProofMismatch,
+    ///The challenged round is valid, the challenger has paid for its verification
+    ShuffleRoundValid,
+}
+
+impl PokerError {
+    ///Text in English, the same as transactions return
+    pub fn message(&self) -> &'static str {
+        match self {
+            PokerError::NoSuchGame => "There is no such game, you are free to set up a new one.",
+            PokerError::AlreadySeated => "You are already sitting at this table.",
+            PokerError::GameFull => "Sorry man, no room.",
+            PokerError::ManagedTable => "Seats at this table are assigned by the tournament",
+            PokerError::NotParticipant => "The account is not a participant of this game",
+            PokerError::NotInRound => "The account is not a participant of current round",
+            PokerError::CantSeat => "The participant can't be seated at this table",
+            PokerError::CantLeave => "The participant can't leave this table now",
+            PokerError::GameInProgress => "Can't quit while the game is in progress",
+            PokerError::BuyInOutOfLimits => "The buy-in is out of limits of the table",
+            PokerError::InsufficientFunds => "Get some money first...",
+            PokerError::StackNotEmpty => "You still have chips, use top-up instead",
+            PokerError::StackEmpty => "Your stack is empty, use rebuy instead",
+            PokerError::StackAboveMaximum => "The stack would exceed the maximum of the table",
+            PokerError::ZeroAmount => "Choose a positive amount",
+            PokerError::ChipsNotForSale => "Chips can't be bought in a tournament",
+            PokerError::RegistrationClosed => "Registration for the tournament is closed",
+            PokerError::FixedBuyIn => "The buy-in of the tournament is fixed",
+            PokerError::TournamentNotStarted => "The tournament hasn't started yet",
+            PokerError::NotYourTurn => "Wait for your turn, please.",
+            PokerError::WaitForNextRound => "Wait for the next round, please.",
+            PokerError::WaitForEndOfRound => "Wait for the end of the round, please.",
+            PokerError::WaitForPlayers => "Wait for other players, please.",
+            PokerError::PreflopAlreadyStarted => "For current round, preflop stage is already initialized",
+            PokerError::NotRevealingNonces => "Nonces are not being revealed now",
+            PokerError::NonceAlreadyRevealed => "The nonce is already revealed",
+            PokerError::NonceMismatch => "The nonce doesn't match the commitment",
+            PokerError::NotShuffling => "The deck is not being shuffled now",
+            PokerError::DeckNotDistinct => "The deck must consist of different encrypted cards",
+            PokerError::InvalidShuffleProof => "The shuffle proof is invalid",
+            PokerError::NotEnoughRandomness => "Not enough randomness to shuffle the deck",
+            PokerError::CardsNotDealt => "Cards are not dealt yet",
+            PokerError::BettingNotFinished => "Betting round is not finished yet",
+            PokerError::FoldedCardsHidden => "Folded cards are not revealed",
+            PokerError::StageAlreadySubmitted => "The next stage is already initialized for this player",
+            PokerError::SecretMismatch => "The secret doesn't match the registered key",
+            PokerError::NoCardsToFold => "There are no cards to fold",
+            PokerError::InvalidPocketCards => "Critical error: revealed pocket cards are invalid!",
+            PokerError::InvalidSharedCards => "Critical error: decrypted cards are invalid!",
+            PokerError::CantCheck => "There is already a bet, you can't check.",
+            PokerError::InsufficientChips => "You don't have enough chips for such a raise.",
+            PokerError::RaiseNotAboveBet => "Raise must be more than the current bet.",
+            PokerError::RaisingClosed => "Raising is not allowed anymore in this betting round.",
+            PokerError::RaiseAboveLimit => "Raise exceeds the limit of the table.",
+            PokerError::RaiseTooSmall => "Raise is less than the minimal one.",
+            PokerError::InvalidBlinds => "Small blind must be positive and not more than big blind",
+            PokerError::AnteAboveBigBlind => "Ante can't be more than big blind",
+            PokerError::BuyInBelowBigBlind => "Minimal buy-in must cover big blind",
+            PokerError::InvalidBuyInRange => "Maximal buy-in can't be less than minimal one",
+            PokerError::InvalidSeats => "A table has from 2 to 9 seats",
+            PokerError::InvalidTimeout => "Action timeout is out of the allowed range",
+            PokerError::InvalidFixedLimit => "Fixed limit requires positive bets, the big one not less than the small one, and a raise cap",
+            PokerError::InvalidBuyIn => "Buy-in and starting stack must be positive",
+            PokerError::NoLevels => "At least one level of blinds is required",
+            PokerError::InvalidLevel => "Every level must have positive blinds and ante not more than big blind",
+            PokerError::StackBelowBigBlind => "Starting stack must cover the first big blind",
+            PokerError::InvalidEscalation => "Blinds can't escalate every 0 blocks or hands",
+            PokerError::InvalidPlaces => "Prizes must be paid to at least one and at most all entrants",
+            PokerError::InvalidPayouts => "Percentages of prizes must sum up to 100",
+            PokerError::InvalidKey => "Public keys must be elements of the group of the cipher",
+            PokerError::InvalidKeyProof => "Knowledge of secret keys is not proven",
+            PokerError::ShuffleFinished => "The deck is already shuffled, lock it now",
+            PokerError::ShuffleNotFinished => "The deck is not shuffled yet",
+            PokerError::InvalidLockProof => "The lock proof is invalid",
+            PokerError::InvalidSecretSize => "Secret keys must be exponents of the cipher",
+            PokerError::NoChips => "You have no chips left, rebuy to play the next hand",
+            PokerError::NoSuchTournament => "There is no such tournament",
+            PokerError::TournamentTooSmall => "A tournament needs at least 2 entrants",
+            PokerError::EscalationByHands => "Blinds of a multi-table tournament go up every number of blocks",
+            PokerError::AlreadyRegistered => "You are already registered",
+            PokerError::TournamentFull => "The tournament is full",
+            PokerError::TournamentAlreadyStarted => "The tournament has already started",
+            PokerError::NotRegistered => "You are not registered",
+            PokerError::NotHost => "Only the host can start the tournament",
+            PokerError::NotEnoughEntrants => "Wait for at least 2 entrants",
+            PokerError::TooManyProofs => "Too many proofs in this block, submit it in the next one",
+            PokerError::NoSuchShuffle => "There is no such round of a shuffle to challenge",
+            PokerError::ProofMismatch => "The proof is not the one submitted with the shuffle",
+            PokerError::ShuffleRoundValid => "The challenged round of the shuffle is valid",
+        }
+    }
+}
+
+impl From<PokerError> for &'static str {
+    fn from(error: PokerError) -> &'static str {
+        error.message()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_encoded_as_one_byte() {
+        assert_eq!(PokerError::NoSuchGame.encode(), vec![0]);
+        assert_eq!(PokerError::InvalidPayouts.encode(), vec![PokerError::InvalidPayouts as u8]);
+        assert_eq!(PokerError::NotYourTurn.message(), "Wait for your turn, please.");
+    }
+}
@@ -0,0 +1,54 @@
+use crate::stage::StageId;
+
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+
+///Identifier of a hand, unique among all tables
+pub type HandId = u64;
+
+///Entry of the log of a hand; the log is enough to replay the hand:
+///to decrypt cards with revealed secrets and to recompute the pots
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Action<AccountId, Balance> {
+    ///Participants in the order from the button, with their stacks
+    Started(Vec<(AccountId, Balance)>),
+    ///Public keys of the participant: hand, flop, turn and river
+    Keys(AccountId, Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>),
+    ///Encoded nonce which seeds the initial order of cards
+    Nonce(AccountId, Vec<u8>),
+    ///Locked deck which cards are dealt from
+    Dealt(Vec<u8>),
+    Ante(AccountId, Balance),
+    Blind(AccountId, Balance),
+    Check(AccountId),
+    ///Total bet of the participant in the betting round
+    Call(AccountId, Balance),
+    Raise(AccountId, Balance),
+    AllIn(AccountId, Balance),
+    Fold(AccountId),
+    TimedOut(AccountId),
+    ///Secret key of the stage submitted by the participant
+    Secret(AccountId, StageId, Vec<u8>),
+    ///Shared cards opened at the stage
+    Cards(StageId, Vec<u8>),
+    ///Pocket cards opened at showdown
+    Showdown(AccountId, Vec<u8>),
+    Rake(Balance),
+    PotAwarded(AccountId, Balance),
+    ///The round is cancelled: late participants have lost their chips, the others
+    ///got them back; when opened cards are invalid, everybody got them back
+    Cancelled,
+    ///Secret of the stage which doesn't match the key of the participant;
+    ///he is punished as if he hasn't submitted it in time
+    WrongSecret(AccountId, StageId, Vec<u8>),
+    ///Chips committed in the cancelled round which went back to the participant
+    Refunded(AccountId, Balance),
+    ///Chips committed in the cancelled round which the late participant has lost to the others
+    Forfeited(AccountId, Balance),
+    ///Small and big blinds of the table, the rake is capped by a number of big blinds
+    Blinds(Balance, Balance),
+    ///Shuffle proof of the participant which has failed a challenge;
+    ///he is punished as if he hasn't shuffled in time
+    InvalidShuffle(AccountId),
+}
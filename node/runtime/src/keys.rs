@@ -1,9 +1,11 @@
 use crate::stage::*;
+use crate::commutative;
 
 use parity_codec::{Encode, Decode};
 use rstd::prelude::*;
 
-pub const KEY_SIZE: usize = 32;
+///Keys and secrets are exponents of the commutative cipher
+pub const KEY_SIZE: usize = commutative::ELEMENT_SIZE;
 
 #[derive(Encode, Decode, Default, Clone)]
 pub struct PublicStorage {
@@ -27,16 +29,20 @@ impl PublicStorage {
             FLOP => self.flop,
             TURN => self.turn,
             RIVER => self.river,
+            SHOWDOWN => self.hand,
 
             _ => panic!("Illegal argument")
         }
     }
 
+    ///Keys in the order of stages: hand, flop, turn and river
+    pub fn all(&self) -> Vec<&[u8]> {
+        vec![&self.hand[..], &self.flop[..], &self.turn[..], &self.river[..]]
+    }
+
     pub fn is_valid(&self) -> bool {
-        self.hand.len()  == KEY_SIZE &&
-        self.flop.len()  == KEY_SIZE &&
-        self.turn.len()  == KEY_SIZE &&
-        self.river.len() == KEY_SIZE
+        self.all().into_iter()
+            .all(|key| key.len() == KEY_SIZE && commutative::is_public_key(key))
     }
 
 }
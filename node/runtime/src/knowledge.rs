@@ -0,0 +1,97 @@
+///Zero-knowledge proof that a participant knows the secret of his public key
+
+///It is the Schnorr protocol made non-interactive with the Fiat–Shamir heuristic.
+///For the public key `y = g^d` the prover picks a random `k` and publishes
+///the commitment `t = g^k` and the response `s = k + c * d (mod Q)`, where
+///the challenge `c` is derived from `y`, `t` and the context. The verifier checks
+///`g^s = t * y^c`. Somebody who doesn't know `d` can't answer, so he can't register
+///a key which he wouldn't be able to open later. The context binds the proof
+///to the participant and the table, so proofs of others can't be copied.
+
+use crate::commutative;
+
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+use runtime_io::blake2_256;
+
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Proof {
+    ///`g^k`
+    pub commitment: Vec<u8>,
+    ///`k + c * d (mod Q)`
+    pub response: Vec<u8>,
+}
+
+///Challenge as an exponent of the cipher; it is shorter than `Q`,
+///so it is never out of range, it can be zero only if the hash is
+pub fn challenge(public: &[u8], commitment: &[u8], context: &[u8]) -> Vec<u8> {
+    let hash = (public, commitment, context).using_encoded(blake2_256);
+    let mut challenge = vec![0; commutative::ELEMENT_SIZE - hash.len()];
+    challenge.extend_from_slice(&hash);
+    challenge
+}
+
+pub fn verify(public: &[u8], proof: &Proof, context: &[u8]) -> bool {
+    if !commutative::is_public_key(public) {
+        return false;
+    }
+
+    let challenge = challenge(public, &proof.commitment[..], context);
+    let expected = commutative::encrypt(public, &challenge)
+        .and_then(|power| commutative::combine(&[&proof.commitment[..], &power[..]], &[1, 1]));
+
+    match (commutative::public(&proof.response[..]), expected) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exponent(seed: u64) -> Vec<u8> {
+        let mut bytes = vec![0; commutative::ELEMENT_SIZE];
+        for (i, byte) in bytes.iter_mut().enumerate().skip(1) {
+            *byte = (seed.wrapping_mul(i as u64 + 7) >> (i % 56)) as u8;
+        }
+        bytes
+    }
+
+    fn prove(secret: &[u8], nonce: &[u8], context: &[u8]) -> Proof {
+        let public = commutative::public(secret).unwrap();
+        let commitment = commutative::public(nonce).unwrap();
+        let challenge = challenge(&public[..], &commitment[..], context);
+        let response = commutative::add(nonce, &commutative::multiply(&challenge, secret).unwrap()).unwrap();
+        Proof { commitment, response }
+    }
+
+    #[test]
+    fn knowledge_is_proven() {
+        let secret = exponent(0x1357_9bdf_2468_ace0);
+        let public = commutative::public(&secret).unwrap();
+        let proof = prove(&secret, &exponent(42), b"alice");
+
+        assert!(verify(&public, &proof, b"alice"));
+        //the proof is bound to the context
+        assert!(!verify(&public, &proof, b"bob"));
+    }
+
+    #[test]
+    fn forged_proofs_are_rejected() {
+        let secret = exponent(0xfeed_f00d);
+        let public = commutative::public(&secret).unwrap();
+        let other = commutative::public(&exponent(77)).unwrap();
+        let proof = prove(&secret, &exponent(43), b"alice");
+
+        assert!(!verify(&other, &proof, b"alice"));
+        assert!(!verify(&public, &Proof { response: exponent(44), ..proof.clone() }, b"alice"));
+        assert!(!verify(&public, &Proof { commitment: other, ..proof.clone() }, b"alice"));
+        assert!(!verify(&public, &Proof::default(), b"alice"));
+
+        //somebody who knows only the public key can't register it
+        let wrong = prove(&exponent(45), &exponent(46), b"alice");
+        assert!(!verify(&public, &wrong, b"alice"));
+    }
+}
@@ -32,6 +32,7 @@ pub use balances::Call as BalancesCall;
 pub use runtime_primitives::{Permill, Perbill};
 pub use timestamp::BlockPeriod;
 pub use support::{StorageValue, construct_runtime};
+use support::traits::{Currency, OnUnbalanced};
 
 /// The type that is used for identifying authorities.
 pub type AuthorityId = <AuthoritySignature as Verify>::Signer;
@@ -54,13 +55,28 @@ pub type BlockNumber = u64;
 /// Index of an account's extrinsic in the chain.
 pub type Nonce = u64;
 
+/// Balance of an account.
+pub type Balance = u128;
+
 /// Used for the module poker in `./poker.rs`
 mod keys;
-mod naive_rsa;
-mod ranking;
-mod cards;
-mod stage;
+pub mod error;
+pub mod commutative;
+pub mod shuffle;
+pub mod lock;
+pub mod knowledge;
+pub mod pots;
+mod table;
+pub mod betting;
+mod sit_and_go;
+mod balancing;
+pub mod ranking;
+pub mod cards;
+pub mod stage;
+pub mod history;
 mod poker;
+mod tournament;
+pub mod api;
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -172,7 +188,7 @@ impl timestamp::Trait for Runtime {
 
 impl balances::Trait for Runtime {
 	/// The type for recording an account's balance.
-	type Balance = u128;
+	type Balance = Balance;
 	/// What to do if an account's free balance gets zeroed.
 	type OnFreeBalanceZero = ();
 	/// What to do if a new account is created.
@@ -191,9 +207,45 @@ impl sudo::Trait for Runtime {
 	type Proposal = Call;
 }
 
+/// Smallest units of the balance in one coin, accounts of the genesis get about a million coins.
+pub const COIN: Balance = 1_000_000_000_000;
+
+/// Percentage of a pot which the house takes, tools verifying hands use it too.
+pub const RAKE_PERCENT: u32 = 5;
+/// The rake is not more than this number of big blinds.
+pub const RAKE_CAP: u32 = 3;
+
 /// Used for the module poker in `./poker.rs`
 impl poker::Trait for Runtime {
 	type Event = Event;
+	//tables can choose up to 30 minutes with 10 seconds blocks
+	const ACTION_TIMEOUT: u64 = 180;
+	type OnTableChange = Tournament;
+	const RAKE_PERCENT: u32 = RAKE_PERCENT;
+	const RAKE_CAP: u32 = RAKE_CAP;
+	type OnRake = House;
+	//a shuffle proof takes about 1 second to verify natively: 48 rounds over 52 cards,
+	//0.4 ms per card and round; so a shuffle costs 2.5 coins, a lock 0.3 coin,
+	//and verification filling a whole 10 seconds block is priced at about 23 coins
+	const CARD_PROOF_FEE: u64 = (COIN / 1_000) as u64;
+	//a block takes about 4 MB of transactions
+	const MAX_PROOFS_PER_BLOCK: u32 = 4;
+	//about 30 days with 10 seconds blocks
+	const HAND_RETENTION: u64 = 259_200;
+}
+
+/// The rake and fees for proofs go to the account of the network operator, the same as the sudo key.
+pub struct House;
+
+impl OnUnbalanced<balances::NegativeImbalance<Runtime>> for House {
+	fn on_unbalanced(amount: balances::NegativeImbalance<Runtime>) {
+		Balances::resolve_creating(&Sudo::key(), amount);
+	}
+}
+
+/// Used for the module tournament in `./tournament.rs`
+impl tournament::Trait for Runtime {
+	type Event = Event;
 }
 
 construct_runtime!(
@@ -211,6 +263,7 @@ construct_runtime!(
 		Sudo: sudo,
 		// Used for the module poker in `./poker.rs`
 		Poker: poker::{Module, Call, Storage, Event<T>},
+		Tournament: tournament::{Module, Call, Storage, Event<T>},
 	}
 );
 
@@ -302,4 +355,18 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl api::PokerApi<Block, AccountId, Balance> for Runtime {
+		fn raise_range(game: poker::GameId) -> Option<(AccountId, Balance, Balance)> {
+			Poker::raise_range(game)
+		}
+
+		fn table(game: poker::GameId) -> Option<api::Table<AccountId, Balance>> {
+			Poker::table(game)
+		}
+
+		fn legal_actions(game: poker::GameId, who: AccountId) -> Option<betting::LegalActions<Balance>> {
+			Poker::legal_actions(game, &who)
+		}
+	}
 }
@@ -0,0 +1,388 @@
+///Zero-knowledge proof of a correct lock of the deck
+
+///In the second pass a participant removes his shuffle encryption from the cards
+///which are going to be dealt and encrypts them with keys of their groups. With `u`
+///the inverse of his shuffle key and `d` the secret of a registered public key `g^d`,
+///every card of a group must satisfy `output[i]^d = input[i]^u`, pocket cards
+///of other participants satisfy `output[i] = input[i]^u`, and other cards stay as they are.
+
+///The shuffle key is fixed by the first pass: products of all cards before and after
+///the shuffle of the participant are `A` and `B = A^s`, so `u` is the only exponent
+///with `B^u = A`. Cards of every group are combined with small random weights derived
+///from both decks, then all equations are proven at once with the Chaum–Pedersen protocol,
+///made non-interactive with the Fiat–Shamir heuristic: for every secret `x` the prover
+///picks a random `k`, publishes commitments made with `k` instead of `x` and the response
+///`z = k + c * x (mod Q)`, which is checked by the verifier.
+
+use crate::commutative;
+
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+use rstd::result;
+use runtime_io::blake2_256;
+
+///Groups of dealt cards in the order of commitments and responses of the proof
+pub const GROUPS: [Group; 5] = [Group::Others, Group::Hand, Group::Flop, Group::Turn, Group::River];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Group {
+    ///Pocket cards of other participants, left without encryption of the locker
+    Others,
+    ///Own pocket cards and shared cards of every stage, encrypted with keys of the locker
+    Hand,
+    Flop,
+    Turn,
+    River,
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Proof {
+    ///`B^k` for the inverse of the shuffle key
+    pub unlock: Vec<u8>,
+    ///`g^k` for hand, flop, turn and river keys
+    pub keys: Vec<Vec<u8>>,
+    ///Commitments for cards of every group
+    pub cards: Vec<Vec<u8>>,
+    ///Responses for the inverse of the shuffle key, then for hand, flop, turn and river keys
+    pub responses: Vec<Vec<u8>>,
+}
+
+///Values the proof is about, the prover and the verifier compute them from decks
+pub struct Statement {
+    ///Products of all cards before and after the shuffle of the locker, `A` and `B`
+    pub shuffled: (Vec<u8>, Vec<u8>),
+    ///Weighted products of cards of every group before and after the lock
+    pub groups: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+///Group of the card at `index` in the order of dealing for the locker at `position`,
+///`None` for burnt and undealt cards
+pub fn group(index: usize, players: usize, position: usize) -> Option<Group> {
+    let shared = 2 * players;
+    if index == position || index == position + players {
+        Some(Group::Hand)
+    } else if index < shared {
+        Some(Group::Others)
+    } else if index > shared && index < shared + 4 {
+        Some(Group::Flop)
+    } else if index == shared + 5 {
+        Some(Group::Turn)
+    } else if index == shared + 7 {
+        Some(Group::River)
+    } else {
+        None
+    }
+}
+
+///Position of the locker in the order of dealing: `decks` are all decks submitted before,
+///starting with the initial one, the locked deck follows them
+pub fn position(decks: &[Vec<u8>], players: usize) -> Option<usize> {
+    decks.len().checked_sub(players + 1)
+        .filter(|&position| position < players)
+}
+
+pub fn statement(decks: &[Vec<u8>], players: usize, output: &[u8]) -> Option<Statement> {
+    let position = position(decks, players)?;
+    let input = decks.last()?;
+    let size = input.len() / commutative::ELEMENT_SIZE;
+    if input.len() != output.len() || input.len() % commutative::ELEMENT_SIZE != 0 || size < 2 * players + 8 {
+        return None;
+    }
+
+    let product = |deck: &[u8]| -> Option<Vec<u8>> {
+        let cards: Vec<&[u8]> = deck.chunks(commutative::ELEMENT_SIZE).collect();
+        commutative::combine(&cards[..], &vec![1; cards.len()][..]).ok()
+    };
+    let shuffled = (product(&decks[position][..])?, product(&decks[position + 1][..])?);
+
+    let weights = weights(input, output, size);
+    let input: Vec<&[u8]> = input.chunks(commutative::ELEMENT_SIZE).collect();
+    let output: Vec<&[u8]> = output.chunks(commutative::ELEMENT_SIZE).collect();
+
+    let mut groups = Vec::with_capacity(GROUPS.len());
+    for &g in GROUPS.iter() {
+        let indices: Vec<usize> = (0..size)
+            .filter(|&i| group(i, players, position) == Some(g))
+            .collect();
+        let weights: Vec<u64> = indices.iter().map(|&i| weights[i]).collect();
+        let before: Vec<&[u8]> = indices.iter().map(|&i| input[i]).collect();
+        let after: Vec<&[u8]> = indices.iter().map(|&i| output[i]).collect();
+        groups.push((
+            commutative::combine(&before[..], &weights[..]).ok()?,
+            commutative::combine(&after[..], &weights[..]).ok()?,
+        ));
+    }
+
+    Some(Statement { shuffled, groups })
+}
+
+///Challenge as an exponent of the cipher, the same way as in the `knowledge` module;
+///responses of the proof are not hashed
+pub fn challenge(statement: &Statement, keys: &[&[u8]], proof: &Proof) -> Vec<u8> {
+    let hash = (&statement.shuffled, &statement.groups, keys, &proof.unlock, &proof.keys, &proof.cards)
+        .using_encoded(blake2_256);
+    let mut challenge = vec![0; commutative::ELEMENT_SIZE - hash.len()];
+    challenge.extend_from_slice(&hash);
+    challenge
+}
+
+///Verifies the locked deck `output` against previous `decks`
+///and public keys of the locker: hand, flop, turn and river
+pub fn verify(decks: &[Vec<u8>], players: usize, output: &[u8], keys: &[&[u8]], proof: &Proof) -> bool {
+    if keys.len() != 4 || proof.keys.len() != keys.len()
+        || proof.cards.len() != GROUPS.len() || proof.responses.len() != GROUPS.len() {
+        return false;
+    }
+    let statement = match statement(decks, players, output) {
+        Some(statement) => statement,
+        None => return false
+    };
+
+    //burnt and undealt cards are never revealed, but they must not be touched
+    let position = position(decks, players).unwrap_or_default();
+    let input = decks.last().map(|deck| &deck[..]).unwrap_or(&[]);
+    let untouched = input.chunks(commutative::ELEMENT_SIZE)
+        .zip(output.chunks(commutative::ELEMENT_SIZE))
+        .enumerate()
+        .all(|(i, (before, after))| group(i, players, position).is_some() || before == after);
+    if !untouched {
+        return false;
+    }
+
+    let challenge = challenge(&statement, keys, proof);
+    let unlock = &proof.responses[0][..];
+
+    //`B^u = A`
+    let (product, shuffled) = &statement.shuffled;
+    if !holds(commutative::encrypt(shuffled, unlock), &proof.unlock, product, &challenge) {
+        return false;
+    }
+
+    //`g^d` are the registered keys
+    let keys_hold = keys.iter()
+        .zip(proof.keys.iter())
+        .zip(proof.responses[1..].iter())
+        .all(|((key, commitment), response)|
+            holds(commutative::public(response), commitment, key, &challenge));
+    if !keys_hold {
+        return false;
+    }
+
+    //`input^u = output^d` for every group, `d = 1` for pocket cards of others,
+    //and the commitment is `input^k / output^k'`
+    statement.groups.iter()
+        .zip(proof.cards.iter())
+        .zip(proof.responses.iter())
+        .enumerate()
+        .all(|(g, (((before, after), commitment), response))| {
+            let exponent = if GROUPS[g] == Group::Others { &challenge } else { response };
+            holds(commutative::encrypt(before, unlock), commitment, after, exponent)
+        })
+}
+
+///Checks `left = commitment * power^exponent`
+fn holds(left: result::Result<Vec<u8>, &'static str>, commitment: &[u8], power: &[u8], exponent: &[u8]) -> bool {
+    let right = commutative::encrypt(power, exponent)
+        .and_then(|power| commutative::combine(&[commitment, &power[..]], &[1, 1]));
+
+    match (left, right) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => false,
+    }
+}
+
+fn weights(input: &[u8], output: &[u8], size: usize) -> Vec<u64> {
+    let seed = (input, output).using_encoded(blake2_256);
+    (0..size as u32)
+        .map(|i| {
+            let hash = (seed, i).using_encoded(blake2_256);
+            hash[..8].iter().fold(0, |weight, &byte| (weight << 8) | byte as u64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{spades, hearts, clubs, diamonds, Card};
+
+    const PLAYERS: usize = 2;
+
+    fn exponent(seed: u64) -> Vec<u8> {
+        let seed = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut bytes: Vec<u8> = (0..commutative::ELEMENT_SIZE as u64)
+            .map(|i| (seed.wrapping_mul(i + 1).wrapping_add(i) >> 16) as u8)
+            .collect();
+        bytes[0] = 0x3f;
+        bytes
+    }
+
+    fn initial() -> Vec<u8> {
+        let cards: Vec<Card> = (1..4)
+            .flat_map(|n| vec![spades(n), hearts(n), clubs(n), diamonds(n)])
+            .collect();
+        commutative::encode(cards.iter().collect())
+    }
+
+    ///Secrets of a participant: the shuffle key, then hand, flop, turn and river keys
+    fn secrets(seed: u64) -> Vec<Vec<u8>> {
+        (0..5).map(|i| exponent(seed * 10 + i)).collect()
+    }
+
+    fn publics(secrets: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        secrets[1..].iter().map(|d| commutative::public(d).unwrap()).collect()
+    }
+
+    fn lock(input: &[u8], position: usize, secrets: &[Vec<u8>]) -> Vec<u8> {
+        let unlock = commutative::inverse(&secrets[0]).unwrap();
+        input.chunks(commutative::ELEMENT_SIZE)
+            .enumerate()
+            .flat_map(|(i, card)| {
+                let exponent = match group(i, PLAYERS, position) {
+                    None => return card.to_vec(),
+                    Some(Group::Others) => unlock.clone(),
+                    Some(g) => {
+                        let index = GROUPS.iter().position(|&x| x == g).unwrap();
+                        let key = commutative::inverse(&secrets[index]).unwrap();
+                        commutative::multiply(&unlock, &key).unwrap()
+                    },
+                };
+                commutative::encrypt(card, &exponent).unwrap()
+            })
+            .collect()
+    }
+
+    fn prove(decks: &[Vec<u8>], output: &[u8], secrets: &[Vec<u8>], nonce: u64) -> Proof {
+        let statement = statement(decks, PLAYERS, output).unwrap();
+        let publics = publics(secrets);
+        let keys: Vec<&[u8]> = publics.iter().map(|key| &key[..]).collect();
+
+        let unlock = commutative::inverse(&secrets[0]).unwrap();
+        let mut exponents = vec![unlock];
+        exponents.extend(secrets[1..].iter().cloned());
+        let nonces: Vec<Vec<u8>> = (0..5).map(|i| exponent(nonce * 10 + i)).collect();
+
+        let cards = statement.groups.iter()
+            .enumerate()
+            .map(|(g, (before, after))| {
+                let power = commutative::encrypt(before, &nonces[0]).unwrap();
+                if GROUPS[g] == Group::Others {
+                    return power;
+                }
+                let inverse = commutative::encrypt(after, &commutative::negate(&nonces[g]).unwrap()).unwrap();
+                commutative::combine(&[&power, &inverse], &[1, 1]).unwrap()
+            })
+            .collect();
+
+        let mut proof = Proof {
+            unlock: commutative::encrypt(&statement.shuffled.1, &nonces[0]).unwrap(),
+            keys: nonces[1..].iter().map(|k| commutative::public(k).unwrap()).collect(),
+            cards,
+            responses: vec![],
+        };
+        let challenge = challenge(&statement, &keys, &proof);
+        proof.responses = nonces.iter()
+            .zip(exponents.iter())
+            .map(|(k, x)| commutative::add(k, &commutative::multiply(&challenge, x).unwrap()).unwrap())
+            .collect();
+        proof
+    }
+
+    ///Both participants shuffle without permutations, the first one locks
+    fn decks() -> (Vec<Vec<u8>>, Vec<Vec<Vec<u8>>>) {
+        let secrets = vec![secrets(1), secrets(2)];
+        let mut decks = vec![initial()];
+        for s in secrets.iter() {
+            let next = commutative::encrypt(decks.last().unwrap(), &s[0]).unwrap();
+            decks.push(next);
+        }
+        (decks, secrets)
+    }
+
+    fn keys(publics: &[Vec<u8>]) -> Vec<&[u8]> {
+        publics.iter().map(|key| &key[..]).collect()
+    }
+
+    #[test]
+    fn groups_follow_dealing() {
+        let groups: Vec<Option<Group>> = (0..12).map(|i| group(i, PLAYERS, 1)).collect();
+        assert_eq!(groups[..4].to_vec(), vec![Some(Group::Others), Some(Group::Hand), Some(Group::Others), Some(Group::Hand)]);
+        assert_eq!(groups[4], None);
+        assert_eq!(groups[5..8].to_vec(), vec![Some(Group::Flop); 3]);
+        assert_eq!(groups[8..].to_vec(), vec![None, Some(Group::Turn), None, Some(Group::River)]);
+
+        assert_eq!(position(&vec![vec![]; 3], PLAYERS), Some(0));
+        assert_eq!(position(&vec![vec![]; 4], PLAYERS), Some(1));
+        assert_eq!(position(&vec![vec![]; 2], PLAYERS), None);
+        assert_eq!(position(&vec![vec![]; 5], PLAYERS), None);
+    }
+
+    #[test]
+    fn honest_lock_is_accepted() {
+        let (mut decks, secrets) = decks();
+        for (position, s) in secrets.iter().enumerate() {
+            let output = lock(decks.last().unwrap(), position, s);
+            let proof = prove(&decks, &output, s, 7 + position as u64);
+            let publics = publics(s);
+            assert!(verify(&decks, PLAYERS, &output, &keys(&publics), &proof));
+            decks.push(output);
+        }
+
+        //hand cards of the first participant are encrypted only with his hand key now
+        let size = commutative::ELEMENT_SIZE;
+        let locked = decks.last().unwrap();
+        let hand = commutative::decrypt(&locked[..size], &secrets[0][1]).unwrap();
+        assert_eq!(hand, initial()[..size].to_vec());
+    }
+
+    #[test]
+    fn unregistered_key_is_rejected() {
+        let (decks, secrets) = decks();
+        let mut other = secrets[0].clone();
+        other[2] = exponent(99);
+        let output = lock(decks.last().unwrap(), 0, &other);
+
+        let forged = prove(&decks, &output, &other, 7);
+        let publics = publics(&secrets[0]);
+        assert!(!verify(&decks, PLAYERS, &output, &keys(&publics), &forged));
+    }
+
+    #[test]
+    fn other_shuffle_key_is_rejected() {
+        let (decks, secrets) = decks();
+        let mut other = secrets[0].clone();
+        other[0] = secrets[1][0].clone();
+        let output = lock(decks.last().unwrap(), 0, &other);
+
+        let forged = prove(&decks, &output, &other, 7);
+        let publics = publics(&secrets[0]);
+        assert!(!verify(&decks, PLAYERS, &output, &keys(&publics), &forged));
+    }
+
+    #[test]
+    fn changed_cards_are_rejected() {
+        let (decks, secrets) = decks();
+        let output = lock(decks.last().unwrap(), 0, &secrets[0]);
+        let proof = prove(&decks, &output, &secrets[0], 7);
+        let publics = publics(&secrets[0]);
+        let size = commutative::ELEMENT_SIZE;
+
+        //the burnt card
+        let mut burnt = output.clone();
+        burnt[4 * size..5 * size].copy_from_slice(&output[5 * size..6 * size]);
+        assert!(!verify(&decks, PLAYERS, &burnt, &keys(&publics), &proof));
+
+        //swapped pocket cards of the locker
+        let mut swapped = output.clone();
+        swapped[..size].copy_from_slice(&output[2 * size..3 * size]);
+        swapped[2 * size..3 * size].copy_from_slice(&output[..size]);
+        assert!(!verify(&decks, PLAYERS, &swapped, &keys(&publics), &proof));
+
+        let mut truncated = proof.clone();
+        truncated.responses.pop();
+        assert!(!verify(&decks, PLAYERS, &output, &keys(&publics), &truncated));
+        assert!(!verify(&decks, PLAYERS, &output, &keys(&publics), &Proof::default()));
+    }
+}
@@ -1,11 +1,14 @@
-use crate::{naive_rsa, stage, cards, keys};
+use crate::{commutative, shuffle, lock, knowledge, stage, cards, keys, ranking, pots, table, betting, sit_and_go, api};
+use crate::history::{HandId, Action};
+use crate::error::PokerError;
 
 use support::{decl_module, decl_storage, decl_event, StorageValue, StorageMap};
-use support::traits::{Currency, WithdrawReason, ExistenceRequirement};
+use support::traits::{Currency, ReservableCurrency, Imbalance, OnUnbalanced};
 use support::dispatch::Result;
 use system::ensure_signed;
 
 use rstd::prelude::*;
+use rstd::cmp::Ordering;
 
 use core::debug_assert;
 
@@ -15,58 +18,186 @@ use parity_codec::Encode;
 
 pub trait Trait: system::Trait + balances::Trait {
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	///Maximal action timeout of a table: number of blocks a participant has to act
+	///or to submit his secret key, after that he is considered gone
+	const ACTION_TIMEOUT: u64;
+
+	///Module which manages tables of multi-table tournaments
+	type OnTableChange: OnTableChange<Self::AccountId>;
+
+	///Percentage of every pot taken by the house when the flop is seen
+	const RAKE_PERCENT: u32;
+	///Maximal rake from one hand, in big blinds of the table
+	const RAKE_CAP: u32;
+	///Receiver of the rake and of fees for proofs
+	type OnRake: OnUnbalanced<NegativeImbalanceOf<Self>>;
+	///Fee for verifying one card in one round of a shuffle proof; a shuffle proof
+	///is paid for all its rounds over the whole deck, a lock proof for as many checks
+	///as its verification takes
+	const CARD_PROOF_FEE: u64;
+	///Number of proofs accepted in one block: shuffles, locks and challenges;
+	///a shuffle proof takes about 640 KB of the block
+	const MAX_PROOFS_PER_BLOCK: u32;
+
+	///Number of blocks the log of a finished hand is kept for
+	const HAND_RETENTION: u64;
 }
 
-decl_storage! {
-	trait Store for Module<T: Trait> as Poker {
-		///Minimal amount to bet (small and big "blinds")
-		Blinds get(blinds): (T::Balance, T::Balance);
+type NegativeImbalanceOf<T> =
+	<balances::Module<T> as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
+
+///Notifications about tables which are managed by another module
+pub trait OnTableChange<AccountId> {
+	///Participant has lost all chips or left the table
+	fn on_eliminated(game: GameId, who: &AccountId);
+	///Hand is finished and the table is idle, so participants can be moved
+	fn on_hand_finished(game: GameId);
+}
+
+impl<AccountId> OnTableChange<AccountId> for () {
+	fn on_eliminated(_: GameId, _: &AccountId) {}
+	fn on_hand_finished(_: GameId) {}
+}
 
-		///Maximum 2 players currently
-		Dealer get(dealer): Option<T::AccountId>;
-		Player get(player): Option<T::AccountId>;
+///Identifier of a table, many games can be played simultaneously
+pub type GameId = u64;
 
-		///`Idle` when game is finished or not started,
-		///and `Preflop`,`Flop`,`Turn` or `River` when it is in progress
-		Stage get(stage): u32;
+///Verification work of proofs, in checks of one card in one round of a shuffle proof;
+///a lock proof takes about as long to verify as 6 rounds
+const SHUFFLE_CHECKS: usize = shuffle::ROUNDS * cards::DECK_SIZE;
+const LOCK_CHECKS: usize = 6 * cards::DECK_SIZE;
 
-		///Chips which are fixed after betting round and withdrawn from participants' stacks
-		Pot get(pot): T::Balance;
+decl_storage! {
+	trait Store for Module<T: Trait> as Poker {
+		///Identifier which will be assigned to the next created game
+		NextGameId get(next_game_id): GameId;
+
+		///Blinds, ante, limits of buy-in and betting, number of seats and the action timeout
+		Config get(config): map GameId => table::TableConfig<T::Balance>;
+
+		///Rules of the tournament, only for sit-and-go tables
+		SitAndGo get(sit_and_go): map GameId => Option<sit_and_go::SitAndGo<T::Balance>>;
+		///Block at which all seats of the tournament were taken
+		TournamentStart get(tournament_start): map GameId => Option<T::BlockNumber>;
+		HandsPlayed get(hands_played): map GameId => u32;
+		///Index of the current level of blinds
+		BlindLevel get(blind_level): map GameId => u32;
+		///Sum of buy-ins of all entrants, it stays reserved on their accounts until the end
+		PrizePool get(prize_pool): map GameId => T::Balance;
+		///Entrants who have lost all chips, in the order of elimination
+		Eliminated get(eliminated): map GameId => Vec<T::AccountId>;
+
+		///Tables opened by another module, e.g. for a multi-table tournament;
+		///chips at them are not money and seats are assigned by that module
+		Managed get(managed): map GameId => bool;
+
+		///Participants sitting at the table, in clockwise order
+		Seats get(seats): map GameId => Vec<T::AccountId>;
+		///Index of the seat with the dealer button,
+		///it moves clockwise after every round
+		Button get(button): map GameId => u32;
+		///Participants of tournament tables who haven't registered keys in time;
+		///they keep their seats, but are dealt out and pay blinds until they return
+		SittingOut get(sitting_out): map GameId => Vec<T::AccountId>;
+
+		///Participants who were dealt cards in current round,
+		///starting from the button and going clockwise
+		Participants get(participants): map GameId => Vec<T::AccountId>;
+		///Participants who have folded their cards in current round
+		Folded get(folded): map GameId => Vec<T::AccountId>;
+		///Participants who have acted since the last raise in current betting round
+		Acted get(acted): map GameId => Vec<T::AccountId>;
+
+		///`Idle` when game is finished or not started, `Shuffle` while
+		///participants prepare the deck and `Preflop`,`Flop`,`Turn` or `River`
+		///when it is in progress
+		Stage get(stage): map GameId => u32;
+
+		///Successive states of the deck: the initial one, then the deck after
+		///every submission of participants, the last one is dealt;
+		///it is empty until all participants reveal their nonces
+		Decks get(decks): map GameId => Vec<Vec<u8>>;
+		///Hashes of shuffle proofs in the order of the first pass; proofs are not verified
+		///by the runtime, they stay in blocks, where participants verify and challenge them
+		ShuffleProofs get(shuffle_proofs): map GameId => Vec<T::Hash>;
+		///Number of proofs accepted in the current block
+		ProofsInBlock get(proofs_in_block): u32;
+
+		///Chips which are fixed after betting round and withdrawn from participants' stacks;
+		///main pot goes first, then side pots, each with participants who can win it
+		Pots get(pots): map GameId => pots::Pots<T::AccountId, T::Balance>;
+		///Chips fixed in the pots by each participant during current round
+		Committed get(committed): map (GameId, T::AccountId) => T::Balance;
 		///Current bets of participants, can change until they are equal
-		Bets get(bets): map T::AccountId => T::Balance;
+		Bets get(bets): map (GameId, T::AccountId) => T::Balance;
 		///Game balances of participants
-		Stacks get(stacks): map T::AccountId => T::Balance;
+		Stacks get(stacks): map (GameId, T::AccountId) => T::Balance;
+		///Funds reserved on accounts of participants for the game; chips lost in current
+		///round stay reserved until the round is settled, even if the participant has left
+		Reserved get(reserved): map (GameId, T::AccountId) => T::Balance;
+		///Chips taken from pots of the current round by the house,
+		///they are removed from reserves when the round is settled
+		Rake get(rake): map GameId => T::Balance;
 
 		///Indicator of a participant who's turn to bet;
 		///if it is `None`, that means we are waiting for the keys for next stage
-		BetsNow get(bets_now): Option<T::AccountId>;
+		BetsNow get(bets_now): map GameId => Option<T::AccountId>;
 
 		///Current maximum bet, other players must "call" or "raise" it, or fold cards
-		BetLevel get(bet_level): Option<T::Balance>;
+		BetLevel get(bet_level): map GameId => Option<T::Balance>;
+		///Number of raises in current betting round, it is limited in fixed-limit games
+		Raises get(raises): map GameId => u32;
+		///Increment of the last full raise in current betting round,
+		///the next raise must be at least the same; big blind if there was no raise yet
+		LastRaiseSize get(last_raise_size): map GameId => Option<T::Balance>;
 
-		///This field is Some, when a game is over
-		Winner get(winner): Option<T::AccountId>;
+		///This field is Some, when a game is over;
+		///it stays None after a split pot
+		Winner get(winner): map GameId => Option<T::AccountId>;
 
 		///Key pairs generated by each participant,
 		///secret parts are revealed in certain moments,
 		///unlocking stages of the game or revealing cards
-		Keys get(keys): map T::AccountId => keys::PublicStorage;
-		Secrets get(secrets): map T::AccountId => keys::RevealedSecrets;
+		Keys get(keys): map (GameId, T::AccountId) => keys::PublicStorage;
+		Secrets get(secrets): map (GameId, T::AccountId) => keys::RevealedSecrets;
+
+		///Hashes of nonces committed by participants together with their keys;
+		///nonces are revealed when all keys are registered and seed the initial order of the deck
+		Commitments get(commitments): map (GameId, T::AccountId) => Option<T::Hash>;
+		Nonces get(nonces): map (GameId, T::AccountId) => Option<T::Hash>;
 
 		///Cards which are shared among participants
-		SharedCards get(shared_cards): Vec<u8>;
+		SharedCards get(shared_cards): map GameId => Vec<u8>;
 
 		///Cards "in the pocket", private and non-visible before showdown
-		PocketCards get(pocket_cards): map T::AccountId => Vec<u8>;
+		PocketCards get(pocket_cards): map (GameId, T::AccountId) => Vec<u8>;
 
 		///Cards "in the pocket" which have been revealed by their owner
-		OpenCards get(open_cards): map T::AccountId => Vec<u8>;
+		OpenCards get(open_cards): map (GameId, T::AccountId) => Vec<u8>;
 
 		///Shared cards are hidden and revealed by-stage when all
 		///players submit their secret keys for corresponding stages
-		FlopCards get(flop_cards): Vec<u8>;
-		TurnCards get(turn_cards): Vec<u8>;
-		RiverCards get(river_cards): Vec<u8>;
+		FlopCards get(flop_cards): map GameId => Vec<u8>;
+		TurnCards get(turn_cards): map GameId => Vec<u8>;
+		RiverCards get(river_cards): map GameId => Vec<u8>;
+
+		///Block at which waiting for the participant in `BetsNow`
+		///or for missing keys of other participants is over
+		Deadline get(deadline): map GameId => Option<T::BlockNumber>;
+		///Games which have their deadlines at the block
+		Timeouts get(timeouts): map T::BlockNumber => Vec<GameId>;
+
+		///Identifier which will be assigned to the next hand
+		NextHandId get(next_hand_id): HandId;
+		///Hand which is being played at the table
+		CurrentHand get(current_hand): map GameId => Option<HandId>;
+		///Table where the hand has been played
+		HandTable get(hand_table): map HandId => Option<GameId>;
+		///Everything which has happened during the hand, in the order of happening
+		HandActions get(hand_actions): map HandId => Vec<Action<T::AccountId, T::Balance>>;
+		///Hands which logs are removed at the block
+		ExpiringHands get(expiring_hands): map T::BlockNumber => Vec<HandId>;
 	}
 }
 
@@ -74,84 +205,197 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event<T>() = default;
 
-		fn create_game(origin, buy_in: T::Balance, big_blind: T::Balance) -> Result {
-			let who = ensure_signed(origin)?;
+		fn on_finalize(n: T::BlockNumber) {
+			<ProofsInBlock<T>>::kill();
+
+			for game in <Timeouts<T>>::take(n) {
+				//the deadline could be moved since then
+				if Self::deadline(game) == Some(n) {
+					Self::time_out(game);
+				}
+			}
+
+			for hand in <ExpiringHands<T>>::take(n) {
+				<HandActions<T>>::remove(hand);
+				<HandTable<T>>::remove(hand);
+			}
+		}
 
-			let dealer = Self::dealer();
-			let player = Self::player();
+		fn create_game(origin, config: table::TableConfig<T::Balance>, buy_in: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
 
-			if dealer.is_some() || player.is_some() {
-				return Self::error(who, "The game is already created, probably you can join");
+			if let Err(error) = config.validate(T::ACTION_TIMEOUT) {
+				return Self::error(who, error);
 			}
-			if buy_in < big_blind {
-				return Self::error(who, "Choose smaller blinds");
+			if !config.allows_buy_in(buy_in) {
+				return Self::error(who, PokerError::BuyInOutOfLimits);
 			}
 
-			Self::announce("Dealer joins the game, waiting for a player...");
-			<Dealer<T>>::put(&who);
+			//storage is not reverted on failure, so the buy-in is reserved first
+			let game = Self::next_game_id();
+			Self::refill_chips(game, who.clone(), buy_in)?;
+			<NextGameId<T>>::put(game + 1);
 
-			let small_blind = big_blind / T::Balance::sa(2);
-			<Blinds<T>>::put((small_blind, big_blind));
+			Self::deposit_event(RawEvent::GameCreated(game, who.clone()));
+			<Seats<T>>::insert(game, vec![who.clone()]);
+			<Button<T>>::insert(game, 0);
 
-			Self::refill_chips(who, buy_in)
+			<Config<T>>::insert(game, config);
+
+			Self::deposit_event(RawEvent::NewParticipant(game, who, buy_in));
+			Ok(())
 		}
 
-		fn join_game(origin, buy_in: T::Balance) -> Result {
+		fn create_sit_and_go(origin,
+				config: table::TableConfig<T::Balance>,
+				tournament: sit_and_go::SitAndGo<T::Balance>) -> Result {
 			let who = ensure_signed(origin)?;
 
-			let dealer = Self::dealer();
-			let player = Self::player();
+			if let Err(error) = tournament.validate(config.seats) {
+				return Self::error(who, error);
+			}
+
+			//blinds follow the schedule, chips can't be bought
+			let first = &tournament.levels[0];
+			let config = table::TableConfig {
+				small_blind: first.small_blind,
+				big_blind: first.big_blind,
+				ante: first.ante,
+				min_buy_in: tournament.starting_stack,
+				max_buy_in: tournament.starting_stack,
+				..config
+			};
+			if let Err(error) = config.validate(T::ACTION_TIMEOUT) {
+				return Self::error(who, error);
+			}
+
+			let game = Self::next_game_id();
+			<NextGameId<T>>::put(game + 1);
+
+			Self::deposit_event(RawEvent::GameCreated(game, who.clone()));
+			<Button<T>>::insert(game, 0);
+			<Config<T>>::insert(game, config);
+			<SitAndGo<T>>::insert(game, &tournament);
+
+			Self::register_entrant(game, who, &tournament)
+		}
+
+		fn join_game(origin, game: GameId, buy_in: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
 
-			if dealer.is_none() {
-				return Self::error(who, "There is nobody so far, you are free to set up the game.");
+			let seats = Self::seats(game);
+			if seats.is_empty() {
+				return Self::error(who, PokerError::NoSuchGame);
+			}
+			if seats.contains(&who) {
+				return Self::error(who, PokerError::AlreadySeated);
+			}
+			let config = Self::config(game);
+			if seats.len() >= config.seats as usize {
+				return Self::error(who, PokerError::GameFull);
 			}
-			if player.is_some() {
-				return Self::error(who, "Sorry man, no room.");
+			if Self::managed(game) {
+				return Self::error(who, PokerError::ManagedTable);
 			}
-			let (_, minimal_amount) = Self::blinds();
-			if buy_in < minimal_amount {
-				return Self::error(who, "Get some money first...");
+			if let Some(tournament) = Self::sit_and_go(game) {
+				if Self::tournament_start(game).is_some() {
+					return Self::error(who, PokerError::RegistrationClosed);
+				}
+				if buy_in != tournament.buy_in {
+					return Self::error(who, PokerError::FixedBuyIn);
+				}
+				return Self::register_entrant(game, who, &tournament);
 			}
+			if !config.allows_buy_in(buy_in) {
+				return Self::error(who, PokerError::BuyInOutOfLimits);
+			}
+
+			Self::refill_chips(game, who.clone(), buy_in)?;
 
-			<Player<T>>::put(&who);
-			Self::announce("Player joins the game! It's gonna be hot!");
+			//new participant is dealt in from the next round
+			<Seats<T>>::mutate(game, |seats| seats.push(who.clone()));
+			Self::deposit_event(RawEvent::NewParticipant(game, who, buy_in));
+			Ok(())
+		}
+
+		///Buying chips again after losing the whole stack
+		fn rebuy(origin, game: GameId, amount: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+			if Self::stacks((game, who.clone())) != Self::zero() {
+				return Self::error(who, PokerError::StackNotEmpty);
+			}
+			if amount < Self::config(game).min_buy_in {
+				return Self::error(who, PokerError::BuyInOutOfLimits);
+			}
+			Self::add_chips(game, who, amount)
+		}
 
-			Self::refill_chips(who, buy_in)
+		///Adding chips to the stack which is not empty yet
+		fn top_up(origin, game: GameId, amount: T::Balance) -> Result {
+			let who = ensure_signed(origin)?;
+			if Self::stacks((game, who.clone())) == Self::zero() {
+				return Self::error(who, PokerError::StackEmpty);
+			}
+			Self::add_chips(game, who, amount)
 		}
 
-		fn leave_game_anyway(origin) -> Result {
+		fn leave_game_anyway(origin, game: GameId) -> Result {
 			let who = ensure_signed(origin)?;
-			if Self::stage() != stage::IDLE && Self::stage() != stage::SHOWDOWN {
-				Self::perform_fold(who.clone())?;
+			if Self::stage(game) == stage::SHUFFLE && Self::participants(game).contains(&who) {
+				//the deck can't be dealt without his keys
+				Self::clear_round(game);
+			} else if Self::is_in_hand(game, &who) && Self::stage(game) != stage::SHOWDOWN {
+				Self::perform_fold(game, who.clone())?;
 			}
-			Self::remove_participant(who)
+			Self::remove_participant(game, who)
 		}
 
-		fn leave_game(origin) -> Result {
+		fn leave_game(origin, game: GameId) -> Result {
 			let who = ensure_signed(origin)?;
-			if Self::stage() != stage::IDLE {
-				return Self::error(who, "Can't quit while the game is in progress");
+			if Self::stage(game) != stage::IDLE {
+				return Self::error(who, PokerError::GameInProgress);
 			}
 
-			//for the case when we made a blind bet,
-			//but other player haven't yet
-			Self::reset_idle(&who);
+			//composition of the next round changes,
+			//so registered keys are not relevant anymore
+			Self::reset_idle(game);
 
-			Self::remove_participant(who)
+			Self::remove_participant(game, who)
 		}
 
 		fn preflop(origin,
+				game: GameId,
 				hand_key: Vec<u8>,
 				flop_key: Vec<u8>,
 				turn_key: Vec<u8>,
-				river_key: Vec<u8>) -> Result {
+				river_key: Vec<u8>,
+				commitment: T::Hash,
+				proofs: Vec<knowledge::Proof>) -> Result {
 			let who = ensure_signed(origin)?;
 
-			if Self::keys(&who).is_initialized() {
-				Self::error(who, "For current round, preflop stage is already initialized")
-			} else {
-				Self::info(who.clone(), "Registering participant's keys for preflop stage");
+			if !Self::seats(game).contains(&who) {
+				return Self::error(who, PokerError::NotParticipant);
+			}
+			if Self::stage(game) != stage::IDLE {
+				return Self::error(who, PokerError::WaitForNextRound);
+			}
+			if Self::stacks((game, who.clone())) == Self::zero() {
+				return Self::error(who, PokerError::NoChips);
+			}
+			if Self::sit_and_go(game).is_some() && Self::tournament_start(game).is_none() {
+				return Self::error(who, PokerError::TournamentNotStarted);
+			}
+
+			//registration of keys brings the participant back to the game
+			<SittingOut<T>>::mutate(game, |absent| absent.retain(|p| p != &who));
+			let players = Self::players_from_button(game);
+			if players.len() + Self::sitting_out(game).len() < 2 {
+				return Self::error(who, PokerError::WaitForPlayers);
+			}
 
+			if Self::keys((game, who.clone())).is_initialized() {
+				Self::error(who, PokerError::PreflopAlreadyStarted)
+			} else {
 				//All keys are received in big-endian format
 				let keys = keys::PublicStorage {
 					hand: hand_key,
@@ -160,246 +404,363 @@ decl_module! {
 					river: river_key
 				};
 
-				debug_assert!(keys.is_valid());
-				<Keys<T>>::insert(&who, &keys);
-
-				let dealer = Self::dealer().unwrap();
-				let player = Self::player().unwrap();
-
-				let dealer_keys = Self::keys(&dealer);
-				let player_keys = Self::keys(&player);
-
-				if dealer_keys.is_initialized() && player_keys.is_initialized() {
-					//Since we can't store the state of cards deck in (visible) blocks,
-					//we have to deal all cards in one atomic transaction;
-					//then we encrypt flop, turn and river with public keys of all participants
-					//and we encrypt hand cards by public keys of corresponding player
-					Self::info_all("Dealing cards for this round");
-
-					//This is fake random for my proof-of-concept;
-					//in future, it has to be replaced with off-chain random generation
-					//also it probably can be workarounded with sending a nonce from the player
-					let seed = (<system::Module<T>>::random_seed(), &who, &dealer_keys, &player_keys)
-						.using_encoded(<T as system::Trait>::Hashing::hash);
-
-					let mut deck = (0..1024)
-						.flat_map(|i| (seed, i).using_encoded(|x| x.to_vec())) //32 bytes
-						.map(cards::from_random);
-
-					let mut cards = vec![];
-					while cards.len() < 12 {
-						let card = deck.next().unwrap();
-						if !cards.contains(&card) {
-							cards.push(card);
-						}
-					}
-
-					let player_cards = cards::encode(vec![&cards[0], &cards[2]]);
-					let dealer_cards = cards::encode(vec![&cards[1], &cards[3]]);
-					let flop_cards   = cards::encode(vec![&cards[5], &cards[6], &cards[7]]);
-					let turn_cards   = cards::encode(vec![&cards[9]]);
-					let river_cards  = cards::encode(vec![&cards[11]]);
+				//keys are checked before cards are dealt,
+				//nobody can register a key which he can't open
+				if !keys.is_valid() {
+					return Self::error(who, PokerError::InvalidKey);
+				}
+				let context = (game, &who).encode();
+				let proven = proofs.len() == 4 && keys.all().into_iter()
+					.zip(proofs.iter())
+					.all(|(key, proof)| knowledge::verify(key, proof, &context[..]));
+				if !proven {
+					return Self::error(who, PokerError::InvalidKeyProof);
+				}
 
-					let player_cards = naive_rsa::encrypt(&player_cards[..], &player_keys.hand[..])?;
-					<PocketCards<T>>::insert(&player, player_cards);
+				<Keys<T>>::insert((game, who.clone()), &keys);
+				<Commitments<T>>::insert((game, who.clone()), commitment);
+				Self::deposit_event(RawEvent::KeysRegistered(game, who.clone()));
 
-					let dealer_cards = naive_rsa::encrypt(&dealer_cards[..], &dealer_keys.hand[..])?;
-					<PocketCards<T>>::insert(&dealer, dealer_cards);
+				let keys: Vec<keys::PublicStorage> = players.iter()
+					.map(|p| Self::keys((game, p.clone())))
+					.collect();
 
-					let flop_cards = naive_rsa::encrypt(&flop_cards[..], &player_keys.flop[..])?;
-					let flop_cards = naive_rsa::encrypt(&flop_cards[..], &dealer_keys.flop[..])?;
-					<FlopCards<T>>::put(flop_cards);
+				//a single player waits for the timeout, it blinds off absent participants
+				if players.len() >= 2 && keys.iter().all(|k| k.is_initialized()) {
+					Self::start_shuffle(game, players)
+				} else {
+					if Self::deadline(game).is_none() {
+						Self::start_timer(game);
+					}
+					Ok(())
+				}
+			}
+		}
 
-					let turn_cards = naive_rsa::encrypt(&turn_cards[..], &player_keys.turn[..])?;
-					let turn_cards = naive_rsa::encrypt(&turn_cards[..], &dealer_keys.turn[..])?;
-					<TurnCards<T>>::put(turn_cards);
+		fn reveal_nonce(origin, game: GameId, nonce: T::Hash) -> Result {
+			let who = ensure_signed(origin)?;
 
-					let river_cards = naive_rsa::encrypt(&river_cards[..], &player_keys.river[..])?;
-					let river_cards = naive_rsa::encrypt(&river_cards[..], &dealer_keys.river[..])?;
-					<RiverCards<T>>::put(river_cards);
+			if Self::stage(game) != stage::SHUFFLE || !Self::decks(game).is_empty() {
+				return Self::error(who, PokerError::NotRevealingNonces);
+			}
+			if !Self::participants(game).contains(&who) {
+				return Self::error(who, PokerError::NotInRound);
+			}
+			if Self::nonces((game, who.clone())).is_some() {
+				return Self::error(who, PokerError::NonceAlreadyRevealed);
+			}
 
-					<Winner<T>>::kill();
-					<Stage<T>>::put(stage::PREFLOP);
-					<BetsNow<T>>::put(&dealer);
-				} else {
-					Self::info(who.clone(), "Waiting for other participants to deal hand cards");
-				}
+			let hash = nonce.using_encoded(<T as system::Trait>::Hashing::hash);
+			if Self::commitments((game, who.clone())) != Some(hash) {
+				return Self::error(who, PokerError::NonceMismatch);
+			}
 
-				let (small_blind, big_blind) = Self::blinds();
-				<BetLevel<T>>::put(big_blind.clone());
+			<Nonces<T>>::insert((game, who.clone()), nonce);
+			Self::log(game, Action::Nonce(who.clone(), nonce.encode()));
+			Self::deposit_event(RawEvent::NonceRevealed(game, who));
 
-				let blind_bet = if &who == &dealer {
-					small_blind
-				} else {
-					big_blind
-				};
+			let all_revealed = Self::participants(game).into_iter()
+				.all(|p| Self::nonces((game, p)).is_some());
 
-				<Bets<T>>::insert(&who, blind_bet);
+			if all_revealed {
+				Self::prepare_deck(game)
+			} else {
 				Ok(())
 			}
 		}
 
-		fn check(origin) -> Result {
+		fn submit_shuffle(origin, game: GameId, deck: Vec<u8>, proof: shuffle::Proof) -> Result {
 			let who = ensure_signed(origin)?;
-			if !Self::makes_bet_now(&who) {
-				return Self::error(who, "Wait for your turn, please.");
+
+			if Self::stage(game) != stage::SHUFFLE {
+				return Self::error(who, PokerError::NotShuffling);
+			}
+			if Self::shuffler(game) != Some(who.clone()) {
+				return Self::error(who, PokerError::NotYourTurn);
 			}
 
-			let level = Self::bet_level();
-			if let Some(current) = level {
-				if current > Self::zero() && !Self::is_option_available(&who, current) {
-					return Self::error(who, "There is already a bet, you can't check.");
-				}
+			//Every participant submits the deck twice, in the order of dealing.
+			//First pass: encrypt every card with your own shuffle key and permute the deck.
+			//Second pass, see `lock_deck`.
+			let decks = Self::decks(game);
+			if decks.len() > Self::participants(game).len() {
+				return Self::error(who, PokerError::ShuffleFinished);
+			}
+			if !commutative::is_deck(&deck[..], cards::DECK_SIZE) {
+				return Self::error(who, PokerError::DeckNotDistinct);
+			}
+
+			//the proof is verified by the others, see `challenge_shuffle`
+			if !shuffle::is_well_formed(&proof, cards::DECK_SIZE) {
+				return Self::error(who, PokerError::InvalidShuffleProof);
+			}
+			if Self::proofs_in_block() >= T::MAX_PROOFS_PER_BLOCK {
+				return Self::error(who, PokerError::TooManyProofs);
 			}
 
-			Self::perform_check(who, level.is_none())
+			if !Self::charge_proof(&who, SHUFFLE_CHECKS) {
+				return Self::error(who, PokerError::InsufficientFunds);
+			}
+
+			let hash = proof.using_encoded(<T as system::Trait>::Hashing::hash);
+			<ShuffleProofs<T>>::mutate(game, |proofs| proofs.push(hash));
+			Self::accept_deck(game, deck)
 		}
 
-		fn call(origin) -> Result {
+		///Every participant verifies shuffle proofs before he locks the deck;
+		///if a round of the proof submitted in the pass fails, he challenges it with the proof
+		///from the block of the shuffle, and only this round is verified
+		fn challenge_shuffle(origin, game: GameId, pass: u32, proof: shuffle::Proof, round: u32) -> Result {
 			let who = ensure_signed(origin)?;
-			if !Self::makes_bet_now(&who) {
-				return Self::error(who, "Wait for your turn, please.");
+
+			if Self::stage(game) != stage::SHUFFLE {
+				return Self::error(who, PokerError::NotShuffling);
+			}
+			let order = Self::deal_order(game);
+			if !order.contains(&who) {
+				return Self::error(who, PokerError::NotInRound);
 			}
 
-			let level = Self::bet_level();
-			if level.is_none() || level == Some(Self::zero()) {
-				Self::perform_check(who, level.is_none())
-			} else {
-				let level = level.unwrap();
-				let stack = Self::stacks(&who);
-				if stack <= level {
-					<Bets<T>>::insert(&who, stack);
-					Self::deposit_event(RawEvent::AllIn(who));
-
-					//since there are only 2 participants, all-in means end of bets
-					<BetsNow<T>>::kill();
-				}  else {
-					<Bets<T>>::insert(&who, level);
-					Self::deposit_event(RawEvent::Call(who.clone()));
-
-					//previous move was either raise or big blind
-					if Self::is_option_available(&Self::opponent(&who), level) {
-						<BetsNow<T>>::put(Self::opponent(&who));
-					} else {
-						<BetsNow<T>>::kill();
-					}
-				}
+			let (pass, round) = (pass as usize, round as usize);
+			let hashes = Self::shuffle_proofs(game);
+			let decks = Self::decks(game);
+			if pass >= hashes.len() || pass + 1 >= decks.len() || round >= shuffle::ROUNDS {
+				return Self::error(who, PokerError::NoSuchShuffle);
+			}
+			if proof.using_encoded(<T as system::Trait>::Hashing::hash) != hashes[pass] {
+				return Self::error(who, PokerError::ProofMismatch);
+			}
+			if Self::proofs_in_block() >= T::MAX_PROOFS_PER_BLOCK {
+				return Self::error(who, PokerError::TooManyProofs);
+			}
 
-				Ok(())
+			//verification of the round is paid by the challenger in any case
+			if !Self::charge_proof(&who, cards::DECK_SIZE) {
+				return Self::error(who, PokerError::InsufficientFunds);
 			}
+			if shuffle::verify_round(&decks[pass], &decks[pass + 1], &proof, round) {
+				return Self::error(who, PokerError::ShuffleRoundValid);
+			}
+
+			//the cheater is punished the same way as for a timeout
+			let cheater = order[pass].clone();
+			Self::deposit_event(RawEvent::InvalidShuffle(game, cheater.clone()));
+			Self::log(game, Action::InvalidShuffle(cheater.clone()));
+			Self::clear_round(game);
+			let _ = Self::remove_participant(game, cheater);
+			Ok(())
 		}
 
-		fn raise(origin, total: T::Balance) -> Result {
+		fn lock_deck(origin, game: GameId, deck: Vec<u8>, proof: lock::Proof) -> Result {
 			let who = ensure_signed(origin)?;
-			if !Self::makes_bet_now(&who) {
-				return Self::error(who, "Wait for your turn, please.");
+
+			if Self::stage(game) != stage::SHUFFLE {
+				return Self::error(who, PokerError::NotShuffling);
+			}
+			if Self::shuffler(game) != Some(who.clone()) {
+				return Self::error(who, PokerError::NotYourTurn);
 			}
 
-			let stack = Self::stacks(&who);
-			if total > stack {
-				return Self::error(who, "You don't have enough chips for such a raise.");
+			//Second pass: keep the order, remove your shuffle encryption from the cards
+			//which are going to be dealt and encrypt them with the key of their group:
+			//`hand` key for your own pocket cards, `flop`, `turn` and `river` keys for
+			//shared cards; pocket cards of other participants are left without your
+			//encryption. Other cards stay encrypted with shuffle keys, which are never revealed.
+			//The proof binds the removed encryption to your first pass and the added one
+			//to your registered keys.
+			let players = Self::participants(game).len();
+			let decks = Self::decks(game);
+			if decks.len() <= players {
+				return Self::error(who, PokerError::ShuffleNotFinished);
+			}
+			if !commutative::is_deck(&deck[..], cards::DECK_SIZE) {
+				return Self::error(who, PokerError::DeckNotDistinct);
 			}
 
-			let level = Self::bet_level().unwrap_or(Self::zero());
-			if total <= level {
-				return Self::error(who, "Raise must be more than the current bet.");
+			if Self::proofs_in_block() >= T::MAX_PROOFS_PER_BLOCK {
+				return Self::error(who, PokerError::TooManyProofs);
 			}
 
-			if total == stack {
-				Self::deposit_event(RawEvent::AllIn(who.clone()));
-			} else {
-				if total < level * T::Balance::sa(2) {
-					return Self::error(who, "Raise must be at least doubling the current bet.");
-				}
+			if !Self::charge_proof(&who, LOCK_CHECKS) {
+				return Self::error(who, PokerError::InsufficientFunds);
+			}
 
-				let diff = total - level;
-				let (_, big_blind) = Self::blinds();
-				if diff < big_blind {
-					return Self::error(who, "Raise must be at least equal to big blind.");
-				}
+			let keys = Self::keys((game, who.clone()));
+			if !lock::verify(&decks[..], players, &deck[..], &keys.all()[..], &proof) {
+				return Self::error(who, PokerError::InvalidLockProof);
+			}
+
+			Self::accept_deck(game, deck)
+		}
+
+		fn check(origin, game: GameId) -> Result {
+			let who = ensure_signed(origin)?;
+			if !Self::makes_bet_now(game, &who) {
+				return Self::error(who, PokerError::NotYourTurn);
+			}
+
+			if !Self::actions(game, &who).check {
+				return Self::error(who, PokerError::CantCheck);
+			}
+
+			Self::deposit_event(RawEvent::Check(game, who.clone()));
+			Self::log(game, Action::Check(who.clone()));
+			Self::end_turn(game, who);
+			Ok(())
+		}
 
-				Self::deposit_event(RawEvent::Raise(who.clone(), diff));
+		fn call(origin, game: GameId) -> Result {
+			let who = ensure_signed(origin)?;
+			if !Self::makes_bet_now(game, &who) {
+				return Self::error(who, PokerError::NotYourTurn);
+			}
+
+			match Self::actions(game, &who).call {
+				None => {
+					Self::deposit_event(RawEvent::Check(game, who.clone()));
+					Self::log(game, Action::Check(who.clone()));
+				},
+				Some(call) => {
+					let key = (game, who.clone());
+					let total = Self::bets(&key) + call;
+					<Bets<T>>::insert(&key, total);
+					if total == Self::stacks(&key) {
+						Self::deposit_event(RawEvent::AllIn(game, who.clone()));
+						Self::log(game, Action::AllIn(who.clone(), total));
+					} else {
+						Self::deposit_event(RawEvent::Call(game, who.clone()));
+						Self::log(game, Action::Call(who.clone(), total));
+					}
+				},
 			}
 
-			<BetLevel<T>>::put(total);
-			<Bets<T>>::insert(&who, total);
-			<BetsNow<T>>::put(Self::opponent(&who));
+			Self::end_turn(game, who);
 			Ok(())
 		}
 
-		fn next_stage(origin, stage_secret: Vec<u8>) -> Result {
+		fn raise(origin, game: GameId, total: T::Balance) -> Result {
 			let who = ensure_signed(origin)?;
+			if !Self::makes_bet_now(game, &who) {
+				return Self::error(who, PokerError::NotYourTurn);
+			}
+
+			let stack = Self::stacks((game, who.clone()));
+			if total > stack {
+				return Self::error(who, PokerError::InsufficientChips);
+			}
+
+			let level = Self::bet_level(game).unwrap_or(Self::zero());
+			if total <= level {
+				return Self::error(who, PokerError::RaiseNotAboveBet);
+			}
 
-			let stage = Self::stage() + 1;
+			let (lowest, highest) = match Self::actions(game, &who).raise {
+				Some(range) => range,
+				None => return Self::error(who, PokerError::RaisingClosed),
+			};
+			if total > highest {
+				return Self::error(who, PokerError::RaiseAboveLimit);
+			}
 
-			if Self::secrets(&who).retrieve(stage).is_some() {
-				Self::error(who, "The next stage is already initialized for this player")
+			if total == stack {
+				Self::deposit_event(RawEvent::AllIn(game, who.clone()));
+				Self::log(game, Action::AllIn(who.clone(), total));
 			} else {
-				Self::info(who.clone(), "Registering participant's keys for the next stage");
+				if total < lowest {
+					return Self::error(who, PokerError::RaiseTooSmall);
+				}
 
-				<Secrets<T>>::mutate(&who, |secrets| {
-					(*secrets).submit(stage, stage_secret);
-					debug_assert!(secrets.is_valid());
-				});
+				Self::deposit_event(RawEvent::Raise(game, who.clone(), total - level));
+				Self::log(game, Action::Raise(who.clone(), total));
+			}
 
-				let dealer = Self::dealer().unwrap();
-				let player = Self::player().unwrap();
+			let increment = total - level;
+			let situation = Self::situation(game, &who);
+			if increment >= betting::min_increment(&Self::config(game).betting, &situation) {
+				<LastRaiseSize<T>>::insert(game, increment);
+				<Raises<T>>::mutate(game, |raises| *raises += 1);
 
-				let dealer_secret = Self::secrets(&dealer).retrieve(stage);
-				let player_secret = Self::secrets(&player).retrieve(stage);
+				//everybody else has to respond to the raise
+				<Acted<T>>::remove(game);
+			}
+			//an incomplete all-in raise doesn't reopen betting,
+			//participants who have acted can only call it or fold
 
-				if dealer_secret.is_some() && player_secret.is_some() {
-					let dealer_secret = dealer_secret.unwrap();
-					let player_secret = player_secret.unwrap();
+			<BetLevel<T>>::insert(game, total);
+			<Bets<T>>::insert((game, who.clone()), total);
+			Self::end_turn(game, who);
+			Ok(())
+		}
 
-					if stage == stage::SHOWDOWN {
-						Self::reveal_hand(dealer, dealer_secret)?;
-						Self::reveal_hand(player, player_secret)?;
-						<Stage<T>>::put(stage);
-						//todo: determine winner
-						return Ok(());
-					}
+		fn next_stage(origin, game: GameId, stage_secret: Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
 
-					Self::info_all("Revealing cards of the next stage");
+			let participants = Self::participants(game);
+			if !participants.contains(&who) {
+				return Self::error(who, PokerError::NotInRound);
+			}
+			if Self::stage(game) == stage::SHUFFLE {
+				return Self::error(who, PokerError::CardsNotDealt);
+			}
+			if Self::bets_now(game).is_some() {
+				return Self::error(who, PokerError::BettingNotFinished);
+			}
 
-					let dealer_key = Self::keys(&dealer).retrieve(stage);
-					let player_key = Self::keys(&player).retrieve(stage);
+			let stage = Self::stage(game) + 1;
+			if stage == stage::SHOWDOWN && !Self::is_in_hand(game, &who) {
+				return Self::error(who, PokerError::FoldedCardsHidden);
+			}
 
-					let hidden = match stage {
-						stage::FLOP  => Self::flop_cards(),
-						stage::TURN  => Self::turn_cards(),
-						stage::RIVER => Self::river_cards(),
+			//a secret of another size can't match any key,
+			//and it mustn't get into the log
+			if stage_secret.len() != keys::KEY_SIZE {
+				return Self::error(who, PokerError::InvalidSecretSize);
+			}
 
-						_ => unreachable!()
-					};
+			if Self::secrets((game, who.clone())).retrieve(stage).is_some() {
+				Self::error(who, PokerError::StageAlreadySubmitted)
+			} else {
+				let key = Self::keys((game, who.clone())).retrieve(stage);
+				if !commutative::keypair_is_valid(&key[..], &stage_secret[..]) {
+					//honest clients never submit such a secret, so the participant
+					//is at fault; the secret is logged as the evidence
+					Self::deposit_event(RawEvent::WrongSecret(game, who.clone(), stage));
+					Self::log(game, Action::WrongSecret(who.clone(), stage, stage_secret));
+					Self::punish(game, stage, vec![who.clone()]);
+					return Self::error(who, PokerError::SecretMismatch);
+				}
 
-					let revealed = naive_rsa::decrypt(&hidden, &dealer_key[..], &dealer_secret[..])?;
-					let mut revealed = naive_rsa::decrypt(&revealed, &player_key[..], &player_secret[..])?;
+				//layers of encryption can be removed in any order,
+				//so shared cards are decrypted as soon as a secret arrives;
+				//pocket cards are encrypted only by their owner
+				if stage != stage::SHOWDOWN {
+					Self::peel(game, stage, &stage_secret[..])?;
+				}
 
-					if !cards::decode(&revealed[..]).into_iter().all(|card| card.is_valid()) {
-						return Self::error_all("Critical error: decrypted cards are invalid!");
-					}
+				Self::log(game, Action::Secret(who.clone(), stage, stage_secret.clone()));
+				Self::deposit_event(RawEvent::SecretSubmitted(game, who.clone(), stage));
+				<Secrets<T>>::mutate((game, who.clone()), |secrets| {
+					(*secrets).submit(stage, stage_secret);
+					debug_assert!(secrets.is_valid());
+				});
 
-					<SharedCards<T>>::mutate(|v| v.append(&mut revealed));
+				let all_submitted = Self::required_secrets(game, stage).iter()
+					.all(|p| Self::secrets((game, p.clone())).retrieve(stage).is_some());
 
-					<BetsNow<T>>::put(&player);
-					<Stage<T>>::put(stage);
-					Ok(())
-				} else {
-					//Technically, if we use commutative encryption, then we can
-					//remove one layer of encryption after each player submits his secret
-					//for current stage. Also we can do it in current implementation
-					//after receiving dealer's secret (because his secret is last of applied),
-					//but for simplicity we wait for all in PoC
-					Self::info(who, "Waiting for other participants to deal next stage");
-					Ok(())
+				if !all_submitted {
+					return Ok(());
 				}
+
+				Self::reveal_stage(game, stage)
 			}
 		}
 
-		fn fold(origin) -> Result {
+		fn fold(origin, game: GameId) -> Result {
 			let who = ensure_signed(origin)?;
-			Self::perform_fold(who)
+			if Self::stage(game) == stage::SHUFFLE {
+				return Self::error(who, PokerError::CardsNotDealt);
+			}
+			Self::perform_fold(game, who)
 		}
 	}
 }
@@ -408,219 +769,2246 @@ decl_module! {
 decl_event!(
 	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
 							Balance = <T as balances::Trait>::Balance {
-		///Auxiliary events; they are redundant and not necessary,
-		///but provide better user experience
-		Announcement(Vec<u8>),
-		InfoMessage(Option<AccountId>, Vec<u8>),
-		ErrorMessage(Option<AccountId>, Vec<u8>),
-
-		NewParticipant(AccountId, Balance),
-		NewDealer(AccountId),
-		ParticipantLeft(AccountId),
-
-		Call(AccountId),
-		Check(AccountId),
-		Raise(AccountId, Balance),
-		AllIn(AccountId),
-		Fold(AccountId),
+		///Transaction of the account has failed; `None` if the failure concerns everybody
+		Error(Option<AccountId>, PokerError),
+
+		GameCreated(GameId, AccountId),
+		NewParticipant(GameId, AccountId, Balance),
+		///Participant has bought more chips
+		Rebuy(GameId, AccountId, Balance),
+
+		///All seats of the tournament are taken, the prize pool is fixed
+		TournamentStarted(GameId, Balance),
+		///New small blind, big blind and ante
+		BlindsRaised(GameId, Balance, Balance, Balance),
+		///Entrant has lost all chips and finished at the place
+		Eliminated(GameId, AccountId, u32),
+		PrizeAwarded(GameId, AccountId, u32, Balance),
+		///The button has moved to the participant, blinds follow it
+		RolesSwapped(GameId, AccountId),
+		ParticipantLeft(GameId, AccountId),
+
+		Call(GameId, AccountId),
+		Check(GameId, AccountId),
+		Raise(GameId, AccountId, Balance),
+		AllIn(GameId, AccountId),
+		Fold(GameId, AccountId),
+
+		///Participant has registered his public keys for the hand
+		KeysRegistered(GameId, AccountId),
+		NonceRevealed(GameId, AccountId),
+		///Participant has to submit the deck now
+		ShuffleTurn(GameId, AccountId),
+		AntePosted(GameId, AccountId, Balance),
+		BlindPosted(GameId, AccountId, Balance),
+		///The deck is locked, pocket cards are dealt
+		CardsDealt(GameId),
+		SecretSubmitted(GameId, AccountId, stage::StageId),
+		///Shared cards opened at the stage, two bytes per card: nominal and suit
+		StageRevealed(GameId, stage::StageId, Vec<u8>),
+		///Late participants have lost their chips, the others got them back
+		RoundCancelled(GameId),
+
+		///Best five cards of a participant, revealed at showdown
+		Showdown(GameId, AccountId, ranking::Rank, Vec<u8>),
+		///Rank of the winning hand, if the pot is won at showdown
+		PotAwarded(GameId, AccountId, Balance, Option<ranking::Rank>),
+		///Index of the seat with the button, small and big blinds
+		HandStarted(GameId, HandId, u32, Balance, Balance),
+		///The log of the hand is complete
+		HandFinished(GameId, HandId),
+		///Funds of the rake have been sent to the house
+		RakeCollected(GameId, Balance),
+
+		///Participant hasn't acted or submitted his key in time
+		TimedOut(GameId, AccountId),
+		///Participant has submitted a secret which doesn't match his key,
+		///he is punished the same way as for a timeout
+		WrongSecret(GameId, AccountId, stage::StageId),
+		///Participant sitting out has paid the ante and the big blind of a hand
+		BlindedOff(GameId, AccountId, Balance),
+		///Shuffle proof of the participant has failed a challenge,
+		///he is punished the same way as for a timeout
+		InvalidShuffle(GameId, AccountId),
 	}
 );
 
 impl<T: Trait> Module<T> {
 
-	fn refill_chips(who: T::AccountId, buy_in: T::Balance) -> Result {
-		let _ = <balances::Module<T> as Currency<_>>::withdraw(
-			&who, buy_in, WithdrawReason::Transfer,
-			ExistenceRequirement::KeepAlive)?;
+	///Opens a table without a host, seats are assigned by the caller
+	pub fn open_table(config: table::TableConfig<T::Balance>) -> GameId {
+		let game = Self::next_game_id();
+		<NextGameId<T>>::put(game + 1);
+
+		<Config<T>>::insert(game, config);
+		<Button<T>>::insert(game, 0);
+		<Managed<T>>::insert(game, true);
+		game
+	}
+
+	///Seats the participant at a managed table with the given chips
+	pub fn seat(game: GameId, who: T::AccountId, chips: T::Balance) -> Result {
+		let seats = Self::seats(game);
+		if !Self::managed(game) || seats.contains(&who) || seats.len() >= Self::config(game).seats as usize {
+			return Err(PokerError::CantSeat.into());
+		}
 
-		<Stacks<T>>::insert(&who, &buy_in);
-		Self::deposit_event(RawEvent::NewParticipant(who, buy_in));
+		<Seats<T>>::mutate(game, |seats| seats.push(who.clone()));
+		<Stacks<T>>::insert((game, who.clone()), chips);
+		Self::deposit_event(RawEvent::NewParticipant(game, who, chips));
 		Ok(())
 	}
 
-	fn reveal_hand(who: T::AccountId, hand_secret: Vec<u8>) -> Result {
-		Self::announce("Revealing pocket cards");
+	///Takes the participant away from an idle managed table, returns his chips
+	pub fn unseat(game: GameId, who: &T::AccountId) -> rstd::result::Result<T::Balance, &'static str> {
+		if !Self::managed(game) || Self::stage(game) != stage::IDLE {
+			return Err(PokerError::CantLeave.into());
+		}
+		let index = match Self::seats(game).iter().position(|p| p == who) {
+			Some(index) => index,
+			None => return Err(PokerError::NotParticipant.into()),
+		};
+
+		//composition of the next round changes
+		Self::reset_idle(game);
+		let chips = <Stacks<T>>::take((game, who.clone()));
+		Self::vacate(game, index);
+		Ok(chips)
+	}
 
-		let hand_key  = Self::keys(&who).hand;
-		let encrypted = Self::pocket_cards(&who);
-		let decrypted = naive_rsa::decrypt(&encrypted, &hand_key[..], &hand_secret[..])?;
+	pub fn set_blinds(game: GameId, level: &sit_and_go::Level<T::Balance>) {
+		<Config<T>>::mutate(game, |config| {
+			config.small_blind = level.small_blind;
+			config.big_blind = level.big_blind;
+			config.ante = level.ante;
+		});
+		Self::deposit_event(RawEvent::BlindsRaised(game, level.small_blind, level.big_blind, level.ante));
+	}
 
-		<OpenCards<T>>::insert(&who, decrypted);
-		Ok(())
+	///Removes a managed table with all chips on it
+	pub fn close_table(game: GameId) {
+		Self::clear_round(game);
+		for who in Self::seats(game).into_iter() {
+			<Stacks<T>>::remove((game, who));
+		}
+		Self::remove_game(game);
 	}
 
-	fn perform_check(who: T::AccountId, first_check: bool) -> Result {
-		if first_check {
-			<BetsNow<T>>::put(Self::opponent(&who));
+	///Participant who will post big blind in the next hand,
+	///he is the one to be moved when tables are balanced
+	pub fn next_big_blind(game: GameId) -> Option<T::AccountId> {
+		let players = Self::players_from_button(game);
+		match players.len() {
+			0 | 1 => None,
+			2 => Some(players[1].clone()),
+			_ => Some(players[2].clone()),
+		}
+	}
+
+	///Participant who has to bet now, with minimal and maximal
+	///total bets he can raise to; used by the runtime API
+	pub fn raise_range(game: GameId) -> Option<(T::AccountId, T::Balance, T::Balance)> {
+		let who = Self::bets_now(game)?;
+		let (lowest, highest) = Self::actions(game, &who).raise?;
+		Some((who, lowest, highest))
+	}
+
+	///Actions the participant can take, if it is his turn to bet;
+	///the same rules are applied by `check`, `call` and `raise`
+	pub fn legal_actions(game: GameId, who: &T::AccountId) -> Option<betting::LegalActions<T::Balance>> {
+		if Self::makes_bet_now(game, who) {
+			Some(Self::actions(game, who))
 		} else {
-			//since there are only 2 participants, we are last who checks
-			<BetsNow<T>>::kill();
+			None
+		}
+	}
+
+	///Snapshot of the table for clients; used by the runtime API
+	pub fn table(game: GameId) -> Option<api::Table<T::AccountId, T::Balance>> {
+		let seats = Self::seats(game);
+		if seats.is_empty() {
+			return None;
 		}
 
-		Self::deposit_event(RawEvent::Check(who));
+		let folded = Self::folded(game);
+		let in_hand = Self::in_hand(game);
+		let config = Self::config(game);
+		let to_act = Self::bets_now(game);
+
+		let seats = seats.into_iter()
+			.map(|who| {
+				let key = (game, who.clone());
+				let (stack, bet) = (Self::stacks(&key), Self::bets(&key));
+				//the bet of a folded participant is already taken from his stack
+				let chips = if folded.contains(&who) { stack } else { stack - bet };
+				api::Seat { in_hand: in_hand.contains(&who), who, chips, bet }
+			})
+			.collect();
+		let pot = Self::participants(game).into_iter()
+			.fold(Self::zero(), |total, p| total + Self::committed((game, p)));
+
+		Some(api::Table {
+			seats,
+			button: Self::button(game),
+			stage: Self::stage(game),
+			small_blind: config.small_blind,
+			big_blind: config.big_blind,
+			ante: config.ante,
+			pot,
+			actions: to_act.as_ref().map(|who| Self::actions(game, who)),
+			to_act,
+			shared_cards: Self::shared_cards(game),
+		})
+	}
+
+	fn actions(game: GameId, who: &T::AccountId) -> betting::LegalActions<T::Balance> {
+		//the participant has acted and only an incomplete raise followed
+		let reopened = !Self::acted(game).contains(who);
+		betting::legal_actions(&Self::config(game).betting, &Self::situation(game, who), reopened)
+	}
+
+	fn situation(game: GameId, who: &T::AccountId) -> betting::Situation<T::Balance> {
+		let config = Self::config(game);
+		let key = (game, who.clone());
+
+		//committed chips include antes, which are not in the pots yet
+		let pot = Self::participants(game).into_iter()
+			.map(|p| (game, p))
+			.fold(Self::zero(), |total, k| total + Self::committed(&k) + Self::bets(&k));
+
+		betting::Situation {
+			stage: Self::stage(game),
+			level: Self::bet_level(game).unwrap_or(Self::zero()),
+			bet: Self::bets(&key),
+			stack: Self::stacks(&key),
+			pot,
+			big_blind: config.big_blind,
+			last_raise: Self::last_raise_size(game).unwrap_or(config.big_blind),
+			raises: Self::raises(game),
+		}
+	}
+
+	fn refill_chips(game: GameId, who: T::AccountId, amount: T::Balance) -> Result {
+		if <balances::Module<T> as ReservableCurrency<_>>::reserve(&who, amount).is_err() {
+			return Self::error(who, PokerError::InsufficientFunds);
+		}
+
+		<Stacks<T>>::mutate((game, who.clone()), |v| *v += amount);
+		<Reserved<T>>::mutate((game, who), |v| *v += amount);
 		Ok(())
 	}
 
-	fn perform_fold(who: T::AccountId) -> Result {
-		let dealer = Self::dealer().unwrap();
-		let player = Self::player().unwrap();
+	fn add_chips(game: GameId, who: T::AccountId, amount: T::Balance) -> Result {
+		if !Self::seats(game).contains(&who) {
+			return Self::error(who, PokerError::NotParticipant);
+		}
+		if Self::plays_for_chips(game) {
+			return Self::error(who, PokerError::ChipsNotForSale);
+		}
+		//chips can't be added during a hand
+		if Self::stage(game) != stage::IDLE {
+			return Self::error(who, PokerError::WaitForEndOfRound);
+		}
+		if amount == Self::zero() {
+			return Self::error(who, PokerError::ZeroAmount);
+		}
+		if Self::stacks((game, who.clone())) + amount > Self::config(game).max_buy_in {
+			return Self::error(who, PokerError::StackAboveMaximum);
+		}
 
-		let prize = Self::calculate_pot();
+		Self::refill_chips(game, who.clone(), amount)?;
+		Self::deposit_event(RawEvent::Rebuy(game, who, amount));
+		Ok(())
+	}
 
-		let winner = if who == dealer { player } else { dealer };
-		{
-			let prize = prize.clone();
-			<Stacks<T>>::mutate(&winner, move |v| *v += prize);
+	///Buy-in goes to the prize pool, chips are given for free;
+	///the tournament starts when all seats are taken
+	fn register_entrant(game: GameId, who: T::AccountId,
+			tournament: &sit_and_go::SitAndGo<T::Balance>) -> Result {
+		if <balances::Module<T> as ReservableCurrency<_>>::reserve(&who, tournament.buy_in).is_err() {
+			return Self::error(who, PokerError::InsufficientFunds);
 		}
 
-		Self::deposit_event(RawEvent::Fold(who));
-		Self::reset_round();
+		<Seats<T>>::mutate(game, |seats| seats.push(who.clone()));
+		<Reserved<T>>::insert((game, who.clone()), tournament.buy_in);
+		<Stacks<T>>::insert((game, who.clone()), tournament.starting_stack);
+		<PrizePool<T>>::mutate(game, |pool| *pool += tournament.buy_in);
+		Self::deposit_event(RawEvent::NewParticipant(game, who, tournament.starting_stack));
+
+		if Self::seats(game).len() == Self::config(game).seats as usize {
+			<TournamentStart<T>>::insert(game, <system::Module<T>>::block_number());
+			Self::deposit_event(RawEvent::TournamentStarted(game, Self::prize_pool(game)));
+		}
 		Ok(())
 	}
 
-	fn remove_participant(who: T::AccountId) -> Result {
-		//This version is for 2 participants maximum;
-		//and no game can contain only a player without a dealer,
-		//so either we remove the player, or replace the dealer
-		let player = Self::player();
-		let target = Some(who.clone());
+	///Blinds go up according to the schedule of the tournament
+	fn update_blinds(game: GameId) {
+		let (tournament, start) = match (Self::sit_and_go(game), Self::tournament_start(game)) {
+			(Some(tournament), Some(start)) => (tournament, start),
+			_ => return,
+		};
+
+		let blocks = (<system::Module<T>>::block_number() - start).as_();
+		let level = tournament.level_at(blocks, Self::hands_played(game));
+		if level as u32 == Self::blind_level(game) {
+			return;
+		}
 
-		let stack = <Stacks<T>>::take(&who);
-		let _ = <balances::Module<T> as Currency<_>>::deposit_into_existing(&who, stack)?;
+		<BlindLevel<T>>::insert(game, level as u32);
+		Self::set_blinds(game, &tournament.levels[level]);
+	}
 
-		if player != target {
-			if Self::dealer() != target {
-				return Self::error(who, "The account is not a participant of this game");
+	///Pays out the prize pool when only one entrant is left
+	fn finish_tournament(game: GameId) {
+		let tournament = match Self::sit_and_go(game) {
+			Some(tournament) => tournament,
+			None => return,
+		};
+
+		let mut places = Self::seats(game);
+		places.extend(Self::eliminated(game).into_iter().rev());
+
+		let prizes = tournament.prizes(Self::prize_pool(game));
+		let targets: Vec<(T::AccountId, T::Balance)> = places.iter()
+			.enumerate()
+			.map(|(i, who)| (who.clone(), prizes.get(i).cloned().unwrap_or(Self::zero())))
+			.collect();
+		Self::redistribute(game, targets.clone());
+
+		for (place, (who, prize)) in targets.into_iter().enumerate() {
+			Self::release(game, &who, Self::reserved((game, who.clone())));
+			if prize > Self::zero() {
+				Self::deposit_event(RawEvent::PrizeAwarded(game, who, place as u32 + 1, prize));
 			}
-			if let Some(player) = player {
-				Self::deposit_event(RawEvent::NewDealer(player.clone()));
-				<Dealer<T>>::put(player);
-			} else {
-				<Dealer<T>>::kill();
-			};
 		}
-		<Player<T>>::kill();
 
-		Self::deposit_event(RawEvent::ParticipantLeft(who));
+		for who in Self::seats(game).into_iter() {
+			<Stacks<T>>::remove((game, who));
+		}
+		Self::remove_game(game);
+	}
 
+	fn start_shuffle(game: GameId, players: Vec<T::AccountId>) -> Result {
+		//The node only puts cards into the initial order, then every participant
+		//encrypts and permutes the deck, so nobody, including validators,
+		//knows the order of cards which are going to be dealt
+		Self::update_blinds(game);
+		Self::blind_off(game, &players);
+
+		<Decks<T>>::remove(game);
+		<Participants<T>>::insert(game, &players);
+		<Folded<T>>::remove(game);
+		<Acted<T>>::remove(game);
+		<Winner<T>>::remove(game);
+		<Stage<T>>::insert(game, stage::SHUFFLE);
+
+		Self::start_hand(game, &players);
+		Self::start_timer(game);
 		Ok(())
 	}
 
-	fn error(who: T::AccountId, message: &'static str) -> Result {
-		let bytes = message.as_bytes().to_vec();
-		Self::deposit_event(RawEvent::ErrorMessage(Some(who), bytes));
-		Err(message)
+	fn start_hand(game: GameId, players: &[T::AccountId]) {
+		let hand = Self::next_hand_id();
+		<NextHandId<T>>::put(hand + 1);
+		<CurrentHand<T>>::insert(game, hand);
+		<HandTable<T>>::insert(hand, game);
+		let config = Self::config(game);
+		Self::deposit_event(RawEvent::HandStarted(game, hand,
+			Self::button(game), config.small_blind, config.big_blind));
+
+		let stacks = players.iter()
+			.map(|p| (p.clone(), Self::stacks((game, p.clone()))))
+			.collect();
+		Self::log(game, Action::Started(stacks));
+		Self::log(game, Action::Blinds(config.small_blind, config.big_blind));
+		for p in players.iter() {
+			let keys = Self::keys((game, p.clone()));
+			Self::log(game, Action::Keys(p.clone(), keys.hand, keys.flop, keys.turn, keys.river));
+		}
 	}
 
-	fn info(who: T::AccountId, message: &'static str) {
-		let bytes = message.as_bytes().to_vec();
-		Self::deposit_event(RawEvent::InfoMessage(Some(who), bytes));
+	///The log of the hand is kept for the retention period
+	fn finish_hand(game: GameId) {
+		if let Some(hand) = <CurrentHand<T>>::take(game) {
+			let expiry = <system::Module<T>>::block_number() + T::BlockNumber::sa(T::HAND_RETENTION);
+			<ExpiringHands<T>>::mutate(expiry, |hands| hands.push(hand));
+			Self::deposit_event(RawEvent::HandFinished(game, hand));
+		}
 	}
 
-	fn error_all(message: &'static str) -> Result {
-		let bytes = message.as_bytes().to_vec();
-		Self::deposit_event(RawEvent::ErrorMessage(None, bytes));
-		Err(message)
+	fn log(game: GameId, action: Action<T::AccountId, T::Balance>) {
+		if let Some(hand) = Self::current_hand(game) {
+			<HandActions<T>>::mutate(hand, |actions| actions.push(action));
+		}
 	}
 
-	fn info_all(message: &'static str) {
-		let bytes = message.as_bytes().to_vec();
-		Self::deposit_event(RawEvent::InfoMessage(None, bytes));
-	}
+	fn prepare_deck(game: GameId) -> Result {
+		//Nonces were committed before anybody could see the others,
+		//so the seed is random if at least one participant is honest,
+		//and everybody can recompute the initial order from revealed nonces
+		let nonces: Vec<T::Hash> = Self::deal_order(game).into_iter()
+			.filter_map(|p| Self::nonces((game, p)))
+			.collect();
+		let seed = (game, nonces).using_encoded(<T as system::Trait>::Hashing::hash);
 
-	fn announce(message: &'static str) {
-		let bytes = message.as_bytes().to_vec();
-		Self::deposit_event(RawEvent::Announcement(bytes));
+		let mut random = (0u32..)
+			.flat_map(|i| (seed, i).using_encoded(<T as system::Trait>::Hashing::hash).as_ref().to_vec());
+
+		let cards = match cards::shuffle(&mut random) {
+			Some(cards) => cards,
+			None => return Self::error_all(PokerError::NotEnoughRandomness),
+		};
+
+		<Decks<T>>::insert(game, vec![commutative::encode(cards.iter().collect())]);
+
+		if let Some(first) = Self::shuffler(game) {
+			Self::deposit_event(RawEvent::ShuffleTurn(game, first));
+		}
+		Self::start_timer(game);
+		Ok(())
 	}
 
-	///Auxiliary functions
+	///The fee is taken before verification, so invalid proofs are paid for too
+	fn charge_proof(who: &T::AccountId, checks: usize) -> bool {
+		let fee = T::Balance::sa(T::CARD_PROOF_FEE * checks as u64);
+		if <balances::Module<T>>::free_balance(who) < fee {
+			return false;
+		}
 
-	fn zero() -> T::Balance {
-		T::Balance::sa(0)
+		let (fee, _) = <balances::Module<T> as Currency<_>>::slash(who, fee);
+		T::OnRake::on_unbalanced(fee);
+		<ProofsInBlock<T>>::mutate(|proofs| *proofs += 1);
+		true
 	}
 
-	fn opponent(who: &T::AccountId) -> T::AccountId {
-		let dealer = Self::dealer().unwrap();
+	fn accept_deck(game: GameId, deck: Vec<u8>) -> Result {
+		<Decks<T>>::mutate(game, |decks| decks.push(deck));
 
-		if &dealer == who {
-			Self::player().unwrap()
-		} else {
-			dealer
+		match Self::shuffler(game) {
+			Some(next) => {
+				Self::deposit_event(RawEvent::ShuffleTurn(game, next));
+				Self::start_timer(game);
+				Ok(())
+			},
+			None => Self::deal(game),
 		}
 	}
 
-	fn calculate_pot() -> T::Balance {
-		let dealer = Self::dealer().unwrap();
-		let player = Self::player().unwrap();
+	fn deal(game: GameId) -> Result {
+		//Cards are dealt from the top of the locked deck: two cards for each
+		//participant one by one starting from the left of the button,
+		//then burn and flop, burn and turn, burn and river;
+		//pocket cards are encrypted only by their owner now,
+		//shared cards are encrypted by everybody
+		let players = Self::participants(game);
+		let n = players.len();
+
+		let deck = Self::decks(game).pop().unwrap_or_default();
+		Self::log(game, Action::Dealt(deck.clone()));
+		let card = |i: usize| &deck[i * commutative::ELEMENT_SIZE..(i + 1) * commutative::ELEMENT_SIZE];
+
+		for (position, p) in Self::deal_order(game).into_iter().enumerate() {
+			let mut hand_cards = card(position).to_vec();
+			hand_cards.extend_from_slice(card(position + n));
+			<PocketCards<T>>::insert((game, p), hand_cards);
+		}
+
+		let shared = 2 * n;
+		let mut flop_cards = vec![];
+		for i in shared + 1..shared + 4 {
+			flop_cards.extend_from_slice(card(i));
+		}
 
-		let d_bet = <Bets<T>>::take(&dealer);
-		let p_bet = <Bets<T>>::take(&player);
+		<FlopCards<T>>::insert(game, flop_cards);
+		<TurnCards<T>>::insert(game, card(shared + 5).to_vec());
+		<RiverCards<T>>::insert(game, card(shared + 7).to_vec());
+		Self::deposit_event(RawEvent::CardsDealt(game));
 
-		<Stacks<T>>::mutate(&dealer, |v| *v -= d_bet);
-		<Stacks<T>>::mutate(&player, |v| *v -= p_bet);
+		let config = Self::config(game);
+		if config.ante > Self::zero() {
+			for p in players.iter() {
+				Self::post_ante(game, p, config.ante);
+			}
+		}
 
-		<Pot<T>>::take() + d_bet + p_bet
+		//heads-up, the button posts small blind
+		let (sb, bb) = if n == 2 { (0, 1) } else { (1, 2) };
+		Self::post_blind(game, &players[sb], config.small_blind);
+		Self::post_blind(game, &players[bb], config.big_blind);
+		<BetLevel<T>>::insert(game, config.big_blind);
+		<Stage<T>>::insert(game, stage::PREFLOP);
+
+		//big blind has an option to raise when the others just call,
+		//since he hasn't acted yet
+		match Self::next_to_act(game, &players[bb]) {
+			Some(next) => <BetsNow<T>>::insert(game, next),
+			None => <BetsNow<T>>::remove(game),
+		}
+		Self::start_timer(game);
+		Ok(())
 	}
 
-	fn makes_bet_now(who: &T::AccountId) -> bool {
-		let expected = Self::bets_now();
-		expected.is_some() && who == &expected.unwrap()
+	///Ante goes to the pot directly, so it isn't matched by bets
+	fn post_ante(game: GameId, who: &T::AccountId, ante: T::Balance) {
+		let key = (game, who.clone());
+		let stack = Self::stacks(&key);
+		let ante = if stack < ante { stack } else { ante };
+		<Stacks<T>>::insert(&key, stack - ante);
+		<Committed<T>>::insert(&key, ante);
+		Self::log(game, Action::Ante(who.clone(), ante));
+		Self::deposit_event(RawEvent::AntePosted(game, who.clone(), ante));
 	}
 
-	///Special rule in Poker
-	fn is_option_available(who: &T::AccountId, level: T::Balance) -> bool {
-		if Self::stage() != stage::PREFLOP {
-			return false;
-		}
-		let (_, big_blind) = Self::blinds();
-		if level != big_blind {
-			return false;
+	fn post_blind(game: GameId, who: &T::AccountId, blind: T::Balance) {
+		let stack = Self::stacks((game, who.clone()));
+		let bet = if stack < blind { stack } else { blind };
+		<Bets<T>>::insert((game, who.clone()), bet);
+		Self::log(game, Action::Blind(who.clone(), bet));
+		Self::deposit_event(RawEvent::BlindPosted(game, who.clone(), bet));
+	}
+
+	fn reveal_hand(game: GameId, who: T::AccountId, hand_secret: Vec<u8>) -> Result {
+
+		let encrypted = Self::pocket_cards((game, who.clone()));
+		let decrypted = commutative::decrypt(&encrypted, &hand_secret[..])?;
+
+		let revealed = match commutative::decode(&decrypted[..]) {
+			Some(ref hand) if hand.len() == 2 => cards::encode(hand.iter().collect()),
+			_ => return Self::cancel(game, PokerError::InvalidPocketCards),
+		};
+		if !Self::opened_cards_are_distinct(game, &revealed[..]) {
+			return Self::cancel(game, PokerError::InvalidPocketCards);
 		}
 
-		who == &Self::player().unwrap()
+		Self::log(game, Action::Showdown(who.clone(), revealed.clone()));
+		<OpenCards<T>>::insert((game, who), revealed);
+		Ok(())
+	}
+
+	///Newly opened cards must differ from each other and from all cards opened before;
+	///it holds for the proven decks, so anything else is a critical error
+	fn opened_cards_are_distinct(game: GameId, revealed: &[u8]) -> bool {
+		let mut opened = Self::shared_cards(game);
+		for p in Self::participants(game).into_iter() {
+			opened.extend(Self::open_cards((game, p)));
+		}
+		opened.extend_from_slice(revealed);
+		cards::are_distinct(&cards::decode(&opened[..]))
 	}
 
-	fn reset_idle(who_waits: &T::AccountId) {
-		<Bets<T>>::remove(who_waits);
+	///Removes one layer of encryption from shared cards of the stage
+	fn peel(game: GameId, stage: u32, secret: &[u8]) -> Result {
+		match stage {
+			stage::FLOP  => <FlopCards<T>>::insert(game,  commutative::decrypt(&Self::flop_cards(game),  secret)?),
+			stage::TURN  => <TurnCards<T>>::insert(game,  commutative::decrypt(&Self::turn_cards(game),  secret)?),
+			stage::RIVER => <RiverCards<T>>::insert(game, commutative::decrypt(&Self::river_cards(game), secret)?),
 
-		Self::dealer().into_iter().for_each(<Keys<T>>::remove);
-		Self::player().into_iter().for_each(<Keys<T>>::remove);
+			_ => unreachable!()
+		}
+		Ok(())
 	}
 
-	fn reset_round() {
-		let dealer = Self::dealer().unwrap();
-		let player = Self::player().unwrap();
-
-		vec![&dealer, &player]
-			.iter().for_each(|k| {
-			<Keys<T>>::remove(*k);
-			<Secrets<T>>::remove(*k);
-			<PocketCards<T>>::remove(*k);
-			<OpenCards<T>>::remove(*k);
-		});
+	///Opens the stage when all required secrets are submitted
+	fn reveal_stage(game: GameId, stage: u32) -> Result {
+		if stage == stage::SHOWDOWN {
+			for p in Self::required_secrets(game, stage).into_iter() {
+				let secret = Self::secrets((game, p.clone())).retrieve(stage).unwrap();
+				Self::reveal_hand(game, p, secret)?;
+			}
+			<Stage<T>>::insert(game, stage);
+			return Self::showdown(game);
+		}
 
-		<FlopCards<T>>::kill();
-		<TurnCards<T>>::kill();
-		<RiverCards<T>>::kill();
-		<SharedCards<T>>::kill();
-		<Stage<T>>::kill();
-		<BetsNow<T>>::kill();
+		//all layers of encryption are already removed
+		let decrypted = match stage {
+			stage::FLOP  => Self::flop_cards(game),
+			stage::TURN  => Self::turn_cards(game),
+			stage::RIVER => Self::river_cards(game),
+
+			_ => unreachable!()
+		};
+
+		let mut revealed = match commutative::decode(&decrypted[..]) {
+			Some(ref opened) => cards::encode(opened.iter().collect()),
+			None => return Self::cancel(game, PokerError::InvalidSharedCards),
+		};
+		if !Self::opened_cards_are_distinct(game, &revealed[..]) {
+			return Self::cancel(game, PokerError::InvalidSharedCards);
+		}
 
-		//Swapping roles
-		<Dealer<T>>::put(player);
-		<Player<T>>::put(dealer);
+		Self::log(game, Action::Cards(stage, revealed.clone()));
+		Self::deposit_event(RawEvent::StageRevealed(game, stage, revealed.clone()));
+		<SharedCards<T>>::mutate(game, |v| v.append(&mut revealed));
+
+		//bets of the finished betting round are fixed in the pots
+		Self::collect_bets(game);
+		<BetLevel<T>>::remove(game);
+		<Raises<T>>::remove(game);
+		<LastRaiseSize<T>>::remove(game);
+		<Acted<T>>::remove(game);
+
+		//first active participant after the button starts betting
+		let button = Self::participants(game)[0].clone();
+		match Self::next_to_act(game, &button) {
+			Some(next) => <BetsNow<T>>::insert(game, next),
+			None => <BetsNow<T>>::remove(game),
+		}
+		<Stage<T>>::insert(game, stage);
+		Self::start_timer(game);
+		Ok(())
 	}
 
-}
+	fn showdown(game: GameId) -> Result {
+		Self::collect_bets(game);
+		Self::take_rake(game);
+
+		let shared = cards::decode(&Self::shared_cards(game)[..]);
+
+		//odd chips of a split pot go to the first winners
+		//to the left of the button
+		let contenders: Vec<T::AccountId> = Self::deal_order(game).into_iter()
+			.filter(|p| Self::is_in_hand(game, p))
+			.collect();
+
+		let hands: Vec<ranking::RankedHand> = contenders.iter()
+			.map(|who| {
+				let mut seven = cards::decode(&Self::open_cards((game, who.clone()))[..]);
+				seven.extend(shared.iter().cloned());
+				ranking::choose_strongest_five(&seven[..])
+			})
+			.collect();
+
+		for (who, hand) in contenders.iter().zip(hands.iter()) {
+			Self::deposit_event(RawEvent::Showdown(game, who.clone(),
+				hand.rank(), cards::encode(hand.cards())));
+		}
 
-//todo: optimize some places using `exists`
+		//every pot is played independently among participants eligible for it
+		<Winner<T>>::remove(game);
+		for (i, (amount, eligible)) in Self::pots(game).into_iter().enumerate() {
+			let ranked: Vec<(&T::AccountId, &ranking::RankedHand)> = contenders.iter()
+				.zip(hands.iter())
+				.filter(|(who, _)| eligible.contains(*who))
+				.collect();
 
-//todo: optimize some origin/who places
+			if ranked.is_empty() {
+				continue;
+			}
 
-//todo: invent something to remove duplicated code for dealer/player
+			let best = ranked.iter()
+				.map(|(_, hand)| *hand)
+				.fold(ranked[0].1, |x, y| if y.cmp(x) == Ordering::Greater { y } else { x });
+			let winners: Vec<T::AccountId> = ranked.iter()
+				.filter(|(_, hand)| *hand == best)
+				.map(|(who, _)| (*who).clone())
+				.collect();
+
+			//the main pot determines the winner of the round
+			if i == 0 && winners.len() == 1 {
+				<Winner<T>>::insert(game, &winners[0]);
+			}
 
-//todo: reduce usage of `unwrap()`
+			for (who, prize) in pots::share(amount, &winners[..]) {
+				Self::award(game, who, prize, Some(best.rank()));
+			}
+		}
+
+		Self::reset_round(game);
+		Ok(())
+	}
 
-//todo: proper input-output of money to the game:
-//1. reserve balance instead of withdrawal at the moment of joining
-//2. unreserve balance + deposit/withdraw difference when leaving
\ No newline at end of file
+	///The house takes its fee from pots only when the flop is seen ("no flop, no drop")
+	fn take_rake(game: GameId) {
+		if Self::plays_for_chips(game) || Self::shared_cards(game).is_empty() {
+			return;
+		}
+
+		let mut pots = Self::pots(game);
+		let cap = Self::config(game).big_blind * T::Balance::sa(T::RAKE_CAP as u64);
+		let rake = pots::rake(&mut pots, T::RAKE_PERCENT, cap);
+		if rake > Self::zero() {
+			<Pots<T>>::insert(game, pots);
+			<Rake<T>>::mutate(game, |v| *v += rake);
+			Self::log(game, Action::Rake(rake));
+		}
+	}
+
+	///`rank` of the winning hand is known only at showdown
+	fn award(game: GameId, who: T::AccountId, prize: T::Balance, rank: Option<ranking::Rank>) {
+		<Stacks<T>>::mutate((game, who.clone()), |v| *v += prize);
+		Self::log(game, Action::PotAwarded(who.clone(), prize));
+		Self::deposit_event(RawEvent::PotAwarded(game, who, prize, rank));
+	}
+
+	fn end_turn(game: GameId, who: T::AccountId) {
+		<Acted<T>>::mutate(game, |acted| acted.push(who.clone()));
+
+		match Self::next_to_act(game, &who) {
+			Some(next) => <BetsNow<T>>::insert(game, next),
+			None => <BetsNow<T>>::remove(game),
+		}
+		Self::start_timer(game);
+	}
+
+	///Gives the awaited participants the action timeout of the table to respond
+	fn start_timer(game: GameId) {
+		let timeout = Self::config(game).action_timeout;
+		let deadline = <system::Module<T>>::block_number() + T::BlockNumber::sa(timeout);
+		<Deadline<T>>::insert(game, deadline);
+		<Timeouts<T>>::mutate(deadline, |games| if !games.contains(&game) {
+			games.push(game);
+		});
+	}
+
+	///Called when the awaited participants haven't responded in time
+	fn time_out(game: GameId) {
+		<Deadline<T>>::remove(game);
+
+		let stage = Self::stage(game);
+		if stage == stage::SHUFFLE && Self::decks(game).is_empty() {
+			//the deck can't be shuffled without their nonces
+			let late: Vec<T::AccountId> = Self::participants(game).into_iter()
+				.filter(|p| Self::nonces((game, p.clone())).is_none())
+				.collect();
+
+			for who in late.iter() {
+				Self::log(game, Action::TimedOut(who.clone()));
+			}
+			Self::clear_round(game);
+			for who in late.into_iter() {
+				Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+				let _ = Self::remove_participant(game, who);
+			}
+			return;
+		}
+
+		if stage == stage::SHUFFLE {
+			//the deck can't be dealt without his keys
+			if let Some(who) = Self::shuffler(game) {
+				Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+				Self::log(game, Action::TimedOut(who.clone()));
+				Self::clear_round(game);
+				let _ = Self::remove_participant(game, who);
+			}
+			return;
+		}
+
+		if stage == stage::IDLE {
+			//players who haven't registered their keys leave cash tables,
+			//the others have to register keys again for the new composition
+			let (ready, late): (Vec<T::AccountId>, Vec<T::AccountId>) = Self::players_from_button(game)
+				.into_iter()
+				.partition(|p| Self::keys((game, p.clone())).is_initialized());
+
+			Self::reset_idle(game);
+			if !Self::plays_for_chips(game) {
+				for who in late.into_iter() {
+					Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+					let _ = Self::remove_participant(game, who);
+				}
+				return;
+			}
+
+			//chips of a tournament can't leave the table, so late players keep their seats
+			//and sit out; when nobody can be dealt in without them, they pay blinds anyway
+			if ready.len() < 2 {
+				Self::blind_off(game, &ready);
+			}
+			for who in late.into_iter() {
+				Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+				<SittingOut<T>>::mutate(game, |absent| absent.push(who));
+			}
+			let busted: Vec<T::AccountId> = Self::sitting_out(game).into_iter()
+				.filter(|p| Self::stacks((game, p.clone())) == Self::zero())
+				.collect();
+			for who in busted.into_iter() {
+				if Self::seats(game).is_empty() {
+					break;
+				}
+				let _ = Self::remove_participant(game, who);
+			}
+			return;
+		}
+
+		if let Some(who) = Self::bets_now(game) {
+			Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+			Self::log(game, Action::TimedOut(who.clone()));
+
+			let level = Self::bet_level(game).unwrap_or(Self::zero());
+			if Self::bets((game, who.clone())) >= level {
+				Self::deposit_event(RawEvent::Check(game, who.clone()));
+				Self::log(game, Action::Check(who.clone()));
+				Self::end_turn(game, who);
+			} else {
+				let _ = Self::perform_fold(game, who);
+			}
+			return;
+		}
+
+		let next = stage + 1;
+		let late: Vec<T::AccountId> = Self::required_secrets(game, next).into_iter()
+			.filter(|p| Self::secrets((game, p.clone())).retrieve(next).is_none())
+			.collect();
+
+		for who in late.iter() {
+			Self::deposit_event(RawEvent::TimedOut(game, who.clone()));
+			Self::log(game, Action::TimedOut(who.clone()));
+		}
+
+		Self::punish(game, next, late);
+	}
+
+	///Participants who haven't submitted valid secrets of the stage, in time or at all:
+	///at showdown their cards are mucked, otherwise the round is cancelled
+	///and they lose their chips to the others
+	fn punish(game: GameId, stage: u32, guilty: Vec<T::AccountId>) {
+		if stage == stage::SHOWDOWN {
+			for who in guilty.into_iter() {
+				if Self::in_hand(game).len() > 1 {
+					let _ = Self::perform_fold(game, who);
+				}
+			}
+
+			let all_submitted = Self::required_secrets(game, stage).iter()
+				.all(|p| Self::secrets((game, p.clone())).retrieve(stage).is_some());
+			if Self::stage(game) != stage::IDLE && all_submitted {
+				let _ = Self::reveal_stage(game, stage);
+			}
+		} else if guilty.is_empty() {
+			let _ = Self::reveal_stage(game, stage);
+		} else {
+			Self::forfeit(game, guilty);
+		}
+	}
+
+	///Shared cards can't be opened without secrets of all participants,
+	///so the round is cancelled: participants get their chips back
+	///and chips committed by the late participants are shared among the others
+	fn forfeit(game: GameId, late: Vec<T::AccountId>) {
+		Self::collect_bets(game);
+
+		let seats = Self::seats(game);
+		let (fined, others): (Vec<T::AccountId>, Vec<T::AccountId>) = Self::participants(game).into_iter()
+			.filter(|p| seats.contains(p))
+			.partition(|p| late.contains(p));
+
+		for who in others.iter() {
+			Self::refund(game, who);
+		}
+
+		if others.is_empty() {
+			//nobody can be compensated
+			for who in fined.iter() {
+				Self::refund(game, who);
+			}
+		} else {
+			let mut penalty = Self::zero();
+			for who in late.iter() {
+				let lost = Self::committed((game, who.clone()));
+				penalty += lost;
+				Self::log(game, Action::Forfeited(who.clone(), lost));
+			}
+
+			for (who, share) in pots::share(penalty, &others[..]) {
+				Self::award(game, who, share, None);
+			}
+		}
+
+		Self::log(game, Action::Cancelled);
+		Self::deposit_event(RawEvent::RoundCancelled(game));
+		Self::reset_round(game);
+	}
+
+	///Opened cards are invalid although every deck and every secret was verified,
+	///so nobody can be blamed: the round is cancelled and all participants get
+	///their chips back; the table must not wait for a stage which can't be opened
+	fn cancel(game: GameId, error: PokerError) -> Result {
+		Self::collect_bets(game);
+
+		let seats = Self::seats(game);
+		for who in Self::participants(game).iter().filter(|p| seats.contains(p)) {
+			Self::refund(game, who);
+		}
+
+		Self::log(game, Action::Cancelled);
+		Self::deposit_event(RawEvent::RoundCancelled(game));
+		Self::reset_round(game);
+		Self::error_all(error)
+	}
+
+	///Chips committed in the round go back to the stack
+	fn refund(game: GameId, who: &T::AccountId) {
+		let refund = Self::committed((game, who.clone()));
+		<Stacks<T>>::mutate((game, who.clone()), |v| *v += refund);
+		Self::log(game, Action::Refunded(who.clone(), refund));
+	}
+
+	fn perform_fold(game: GameId, who: T::AccountId) -> Result {
+		if !Self::is_in_hand(game, &who) {
+			return Self::error(who, PokerError::NoCardsToFold);
+		}
+
+		//chips of the folded participant are lost immediately,
+		//but his bet still counts until the end of the betting round
+		let key = (game, who.clone());
+		let bet = Self::bets(&key);
+		<Stacks<T>>::mutate(&key, |v| *v -= bet);
+
+		<Folded<T>>::mutate(game, |folded| folded.push(who.clone()));
+		Self::deposit_event(RawEvent::Fold(game, who.clone()));
+		Self::log(game, Action::Fold(who.clone()));
+
+		let in_hand = Self::in_hand(game);
+		if in_hand.len() == 1 {
+			let winner = in_hand[0].clone();
+
+			Self::collect_bets(game);
+			Self::take_rake(game);
+			let prize = Self::pots(game).into_iter()
+				.fold(Self::zero(), |total, (amount, _)| total + amount);
+
+			<Winner<T>>::insert(game, &winner);
+			Self::award(game, winner, prize, None);
+			Self::reset_round(game);
+		} else if Self::makes_bet_now(game, &who) {
+			match Self::next_to_act(game, &who) {
+				Some(next) => <BetsNow<T>>::insert(game, next),
+				None => <BetsNow<T>>::remove(game),
+			}
+			Self::start_timer(game);
+		}
+		Ok(())
+	}
+
+	fn remove_participant(game: GameId, who: T::AccountId) -> Result {
+		let seats = Self::seats(game);
+		let index = match seats.iter().position(|p| p == &who) {
+			Some(index) => index,
+			None => return Self::error(who, PokerError::NotParticipant),
+		};
+
+		//chips which are still in the pots are released when the round is settled
+		let stack = <Stacks<T>>::take((game, who.clone()));
+		let tournament = Self::sit_and_go(game);
+		let started = Self::tournament_start(game).is_some();
+		let managed = Self::managed(game);
+		match tournament {
+			//chips of a managed table belong to its tournament
+			_ if managed => {},
+			None => Self::release(game, &who, stack),
+			Some(ref tournament) if !started => {
+				Self::release(game, &who, tournament.buy_in);
+				<PrizePool<T>>::mutate(game, |pool| *pool -= tournament.buy_in);
+			},
+			//the buy-in stays in the prize pool
+			Some(_) => {
+				<Eliminated<T>>::mutate(game, |eliminated| eliminated.push(who.clone()));
+				Self::deposit_event(RawEvent::Eliminated(game, who.clone(), seats.len() as u32));
+			},
+		}
+
+		Self::vacate(game, index);
+
+		if managed {
+			T::OnTableChange::on_eliminated(game, &who);
+		} else if tournament.is_some() && started && seats.len() == 2 {
+			Self::finish_tournament(game);
+		}
+		Ok(())
+	}
+
+	///Frees the seat; the button stays with the same participant,
+	///or goes to the next one if it was his seat
+	fn vacate(game: GameId, index: usize) {
+		let mut seats = Self::seats(game);
+		let who = seats.remove(index);
+		<SittingOut<T>>::mutate(game, |absent| absent.retain(|p| p != &who));
+		Self::deposit_event(RawEvent::ParticipantLeft(game, who));
+
+		if seats.is_empty() {
+			Self::remove_game(game);
+			return;
+		}
+
+		let mut button = Self::button(game) as usize;
+		let was_dealer = index == button;
+		if index < button {
+			button -= 1;
+		}
+		if button >= seats.len() {
+			button = 0;
+		}
+		if was_dealer {
+			Self::deposit_event(RawEvent::RolesSwapped(game, seats[button].clone()));
+		}
+
+		<Button<T>>::insert(game, button as u32);
+		<Seats<T>>::insert(game, &seats);
+	}
+
+	///Moves reserved funds from participants who have lost chips in the round
+	///to participants who have won them, so total issuance is never changed
+	fn settle(game: GameId) {
+		//chips of a tournament are not money, the prize pool is paid out in the end
+		if Self::plays_for_chips(game) {
+			return;
+		}
+
+		let mut accounts = Self::participants(game);
+		for who in Self::seats(game).into_iter() {
+			if !accounts.contains(&who) {
+				accounts.push(who);
+			}
+		}
+
+		let targets: Vec<(T::AccountId, T::Balance)> = accounts.into_iter()
+			.map(|who| {
+				let stack = Self::stacks((game, who.clone()));
+				(who, stack)
+			})
+			.collect();
+		Self::redistribute(game, targets.clone());
+		Self::collect_rake(game, &targets);
+
+		//participants who have left the table get the rest back
+		let seats = Self::seats(game);
+		for who in Self::participants(game).into_iter().filter(|who| !seats.contains(who)) {
+			Self::release(game, &who, Self::reserved((game, who.clone())));
+		}
+	}
+
+	///Chips of the rake are not in anybody's stack, so after redistribution
+	///they are left in reserves of those who have lost; the house gets them from there
+	fn collect_rake(game: GameId, targets: &[(T::AccountId, T::Balance)]) {
+		let mut rake = <Rake<T>>::take(game);
+		if rake == Self::zero() {
+			return;
+		}
+
+		let mut collected = NegativeImbalanceOf::<T>::zero();
+		for (who, target) in targets.iter() {
+			let reserved = Self::reserved((game, who.clone()));
+			if reserved <= *target || rake == Self::zero() {
+				continue;
+			}
+
+			let amount = rstd::cmp::min(reserved - *target, rake);
+			let (imbalance, left) = <balances::Module<T> as ReservableCurrency<_>>::slash_reserved(who, amount);
+			<Reserved<T>>::mutate((game, who.clone()), |v| *v -= amount - left);
+			collected = collected.merge(imbalance);
+			rake -= amount;
+		}
+
+		Self::deposit_event(RawEvent::RakeCollected(game, collected.peek()));
+		T::OnRake::on_unbalanced(collected);
+	}
+
+	///Repatriates reserved funds, so every account has its target amount reserved for the game
+	fn redistribute(game: GameId, targets: Vec<(T::AccountId, T::Balance)>) {
+		let mut losers: Vec<(T::AccountId, T::Balance)> = targets.iter()
+			.map(|(who, target)| (who.clone(), Self::reserved((game, who.clone())), *target))
+			.filter(|(_, reserved, target)| reserved > target)
+			.map(|(who, reserved, target)| (who, reserved - target))
+			.collect();
+		let winners: Vec<(T::AccountId, T::Balance)> = targets.iter()
+			.map(|(who, target)| (who.clone(), Self::reserved((game, who.clone())), *target))
+			.filter(|(_, reserved, target)| target > reserved)
+			.map(|(who, reserved, target)| (who, target - reserved))
+			.collect();
+
+		for (winner, mut gain) in winners.into_iter() {
+			for (loser, loss) in losers.iter_mut() {
+				let amount = rstd::cmp::min(gain, *loss);
+				if amount == Self::zero() {
+					continue;
+				}
+
+				let left = <balances::Module<T> as ReservableCurrency<_>>::repatriate_reserved(
+					loser, &winner, amount).unwrap_or(amount);
+				let moved = amount - left;
+
+				*loss -= amount;
+				gain -= amount;
+				<Reserved<T>>::mutate((game, loser.clone()), |v| *v -= moved);
+
+				//repatriated funds arrive to the free balance
+				if <balances::Module<T> as ReservableCurrency<_>>::reserve(&winner, moved).is_ok() {
+					<Reserved<T>>::mutate((game, winner.clone()), |v| *v += moved);
+				} else if Self::sit_and_go(game).is_none() {
+					<Stacks<T>>::mutate((game, winner.clone()), |v| *v -= moved);
+				}
+			}
+		}
+	}
+
+	fn release(game: GameId, who: &T::AccountId, amount: T::Balance) {
+		let key = (game, who.clone());
+		let amount = rstd::cmp::min(amount, Self::reserved(&key));
+		let _ = <balances::Module<T> as ReservableCurrency<_>>::unreserve(who, amount);
+
+		<Reserved<T>>::mutate(&key, |v| *v -= amount);
+		if Self::reserved(&key) == Self::zero() {
+			<Reserved<T>>::remove(&key);
+		}
+	}
+
+	fn remove_game(game: GameId) {
+		<Seats<T>>::remove(game);
+		<Button<T>>::remove(game);
+		<SittingOut<T>>::remove(game);
+		<Config<T>>::remove(game);
+		<SitAndGo<T>>::remove(game);
+		<TournamentStart<T>>::remove(game);
+		<HandsPlayed<T>>::remove(game);
+		<BlindLevel<T>>::remove(game);
+		<PrizePool<T>>::remove(game);
+		<Eliminated<T>>::remove(game);
+		<Managed<T>>::remove(game);
+		<Rake<T>>::remove(game);
+		<Pots<T>>::remove(game);
+		<BetLevel<T>>::remove(game);
+		<Raises<T>>::remove(game);
+		<LastRaiseSize<T>>::remove(game);
+		<Winner<T>>::remove(game);
+		<Decks<T>>::remove(game);
+		<ShuffleProofs<T>>::remove(game);
+		<Deadline<T>>::remove(game);
+		Self::finish_hand(game);
+	}
+
+	fn error(who: T::AccountId, error: PokerError) -> Result {
+		Self::deposit_event(RawEvent::Error(Some(who), error));
+		Err(error.message())
+	}
+
+	fn error_all(error: PokerError) -> Result {
+		Self::deposit_event(RawEvent::Error(None, error));
+		Err(error.message())
+	}
+
+	///Auxiliary functions
+
+	fn zero() -> T::Balance {
+		T::Balance::sa(0)
+	}
+
+	///Seated participants having chips, starting from the button
+	fn players_from_button(game: GameId) -> Vec<T::AccountId> {
+		let seats = Self::seats(game);
+		let button = Self::button(game) as usize;
+		let n = seats.len();
+		let absent = Self::sitting_out(game);
+
+		(0..n).map(|i| seats[(button + i) % n].clone())
+			.filter(|p| Self::stacks((game, p.clone())) > Self::zero() && !absent.contains(p))
+			.collect()
+	}
+
+	///Participants sitting out pay the ante and the big blind of every hand they miss,
+	///the chips are shared among the players of the hand
+	fn blind_off(game: GameId, players: &[T::AccountId]) {
+		if players.is_empty() {
+			return;
+		}
+
+		let config = Self::config(game);
+		let due = config.ante + config.big_blind;
+		for who in Self::sitting_out(game).into_iter() {
+			let key = (game, who.clone());
+			let stack = Self::stacks(&key);
+			let paid = if stack < due { stack } else { due };
+			<Stacks<T>>::insert(&key, stack - paid);
+			Self::deposit_event(RawEvent::BlindedOff(game, who, paid));
+
+			for (p, share) in pots::share(paid, players) {
+				<Stacks<T>>::mutate((game, p), |v| *v += share);
+			}
+		}
+	}
+
+	///Participants of current round in the order of dealing,
+	///starting from the left of the button
+	fn deal_order(game: GameId) -> Vec<T::AccountId> {
+		let mut participants = Self::participants(game);
+		if !participants.is_empty() {
+			let button = participants.remove(0);
+			participants.push(button);
+		}
+		participants
+	}
+
+	///Participants of current round who haven't folded yet
+	fn in_hand(game: GameId) -> Vec<T::AccountId> {
+		let folded = Self::folded(game);
+		Self::participants(game).into_iter()
+			.filter(|p| !folded.contains(p))
+			.collect()
+	}
+
+	fn is_in_hand(game: GameId, who: &T::AccountId) -> bool {
+		Self::participants(game).contains(who) && !Self::folded(game).contains(who)
+	}
+
+	///Participants whose secrets are needed to open the stage:
+	///shared cards are encrypted by everybody who was dealt in,
+	///but only those who still hold cards reveal them at showdown
+	fn required_secrets(game: GameId, stage: u32) -> Vec<T::AccountId> {
+		if stage == stage::SHOWDOWN {
+			Self::in_hand(game)
+		} else {
+			Self::participants(game)
+		}
+	}
+
+	fn is_all_in(game: GameId, who: &T::AccountId) -> bool {
+		let key = (game, who.clone());
+		Self::stacks(&key) == Self::bets(&key)
+	}
+
+	///Participant who has to act after `after`, going clockwise;
+	///the betting round is over when it is `None`
+	fn next_to_act(game: GameId, after: &T::AccountId) -> Option<T::AccountId> {
+		let participants = Self::participants(game);
+		let acted = Self::acted(game);
+		let level = Self::bet_level(game).unwrap_or(Self::zero());
+
+		let active: Vec<T::AccountId> = Self::in_hand(game).into_iter()
+			.filter(|p| !Self::is_all_in(game, p))
+			.collect();
+
+		//there is nobody to bet against
+		if active.len() < 2 && active.iter().all(|p| Self::bets((game, p.clone())) >= level) {
+			return None;
+		}
+
+		let n = participants.len();
+		let start = participants.iter().position(|p| p == after).unwrap_or(0);
+		(1..n + 1)
+			.map(|i| participants[(start + i) % n].clone())
+			.filter(|p| active.contains(p))
+			.find(|p| !acted.contains(p) || Self::bets((game, p.clone())) < level)
+	}
+
+	///Moves bets of the finished betting round into the pots;
+	///the part of a bet which nobody has matched is returned to its owner
+	fn collect_bets(game: GameId) {
+		let participants = Self::participants(game);
+		let folded = Self::folded(game);
+
+		let mut bets: Vec<(T::AccountId, T::Balance)> = participants.iter()
+			.map(|p| (p.clone(), <Bets<T>>::take((game, p.clone()))))
+			.collect();
+
+		if let Some((who, excess)) = pots::uncalled(&bets[..]) {
+			if !folded.contains(&who) {
+				for bet in bets.iter_mut() {
+					if bet.0 == who {
+						bet.1 -= excess;
+					}
+				}
+			}
+		}
+
+		for (p, bet) in bets.into_iter() {
+			if bet > Self::zero() {
+				let key = (game, p.clone());
+				//folded participants have already lost their chips
+				if !folded.contains(&p) {
+					<Stacks<T>>::mutate(&key, |v| *v -= bet);
+				}
+				<Committed<T>>::mutate(&key, |v| *v += bet);
+			}
+		}
+
+		let committed: Vec<(T::AccountId, T::Balance, bool)> = participants.into_iter()
+			.map(|p| {
+				let amount = Self::committed((game, p.clone()));
+				let in_hand = !folded.contains(&p);
+				(p, amount, in_hand)
+			})
+			.collect();
+
+		<Pots<T>>::insert(game, pots::split(&committed[..]));
+	}
+
+	///Participant who has to submit the deck now: everybody shuffles it
+	///in the order of dealing, then everybody locks it in the same order
+	fn shuffler(game: GameId) -> Option<T::AccountId> {
+		let order = Self::deal_order(game);
+		let decks = Self::decks(game);
+		let submitted = decks.len().saturating_sub(1);
+		if order.is_empty() || decks.is_empty() || submitted >= 2 * order.len() {
+			None
+		} else {
+			Some(order[submitted % order.len()].clone())
+		}
+	}
+
+	fn makes_bet_now(game: GameId, who: &T::AccountId) -> bool {
+		let expected = Self::bets_now(game);
+		expected.is_some() && who == &expected.unwrap()
+	}
+
+	fn reset_idle(game: GameId) {
+		Self::seats(game).into_iter()
+			.for_each(|k| {
+				let key = (game, k);
+				<Keys<T>>::remove(&key);
+				<Commitments<T>>::remove(&key);
+			});
+		<Deadline<T>>::remove(game);
+	}
+
+	fn reset_round(game: GameId) {
+		//participants who start the hand with more chips finish higher
+		let mut busted: Vec<(T::AccountId, T::Balance)> = Self::participants(game).into_iter()
+			.chain(Self::sitting_out(game))
+			.filter(|p| Self::stacks((game, p.clone())) == Self::zero())
+			.map(|p| (p.clone(), Self::committed((game, p))))
+			.collect();
+		busted.sort_by(|a, b| a.1.cmp(&b.1));
+
+		Self::settle(game);
+		Self::clear_round(game);
+
+		if Self::plays_for_chips(game) {
+			<HandsPlayed<T>>::mutate(game, |hands| *hands += 1);
+			for (who, _) in busted.into_iter() {
+				//the elimination may finish the tournament and close the table
+				if Self::seats(game).is_empty() {
+					break;
+				}
+				let _ = Self::remove_participant(game, who);
+			}
+		}
+
+		//Moving the button
+		let seats = Self::seats(game);
+		if !seats.is_empty() {
+			let button = (Self::button(game) as usize + 1) % seats.len();
+			<Button<T>>::insert(game, button as u32);
+			Self::deposit_event(RawEvent::RolesSwapped(game, seats[button].clone()));
+		}
+
+		if Self::managed(game) {
+			T::OnTableChange::on_hand_finished(game);
+		}
+	}
+
+	fn plays_for_chips(game: GameId) -> bool {
+		Self::sit_and_go(game).is_some() || Self::managed(game)
+	}
+
+	fn clear_round(game: GameId) {
+		Self::participants(game)
+			.into_iter().for_each(|k| {
+			let key = (game, k);
+			<Bets<T>>::remove(&key);
+			<Committed<T>>::remove(&key);
+			<Keys<T>>::remove(&key);
+			<Secrets<T>>::remove(&key);
+			<Commitments<T>>::remove(&key);
+			<Nonces<T>>::remove(&key);
+			<PocketCards<T>>::remove(&key);
+			<OpenCards<T>>::remove(&key);
+		});
+
+		<Participants<T>>::remove(game);
+		<Pots<T>>::remove(game);
+		<Folded<T>>::remove(game);
+		<Acted<T>>::remove(game);
+		<FlopCards<T>>::remove(game);
+		<TurnCards<T>>::remove(game);
+		<RiverCards<T>>::remove(game);
+		<SharedCards<T>>::remove(game);
+		<Stage<T>>::remove(game);
+		<BetsNow<T>>::remove(game);
+		<BetLevel<T>>::remove(game);
+		<Raises<T>>::remove(game);
+		<LastRaiseSize<T>>::remove(game);
+		<Decks<T>>::remove(game);
+		<ShuffleProofs<T>>::remove(game);
+		<Deadline<T>>::remove(game);
+		Self::finish_hand(game);
+	}
+
+}
+
+//todo: optimize some places using `exists`
+
+//todo: optimize some origin/who places
+
+//todo: reduce usage of `unwrap()`
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cards::{spades, hearts, clubs, diamonds, A, K, Q, J};
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok};
+	use runtime_primitives::BuildStorage;
+	use runtime_primitives::traits::{BlakeTwo256, IdentityLookup, OnFinalize};
+	use runtime_primitives::testing::{Digest, DigestItem, Header};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type DustRemoval = ();
+		type TransferPayment = ();
+	}
+
+	impl Trait for Test {
+		type Event = ();
+		const ACTION_TIMEOUT: u64 = 100;
+		type OnTableChange = ();
+		const RAKE_PERCENT: u32 = 5;
+		const RAKE_CAP: u32 = 3;
+		type OnRake = ();
+		const CARD_PROOF_FEE: u64 = 1;
+		const MAX_PROOFS_PER_BLOCK: u32 = 4;
+		const HAND_RETENTION: u64 = 1000;
+	}
+
+	type Poker = Module<Test>;
+	type Balances = balances::Module<Test>;
+
+	const BALANCE: u64 = 1_000_000;
+	const BUY_IN: u64 = 1000;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test> {
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit: 0,
+			transfer_fee: 0,
+			creation_fee: 0,
+			balances: (1..10).map(|who| (who, BALANCE)).collect(),
+			vesting: vec![],
+		}.build_storage().unwrap().0);
+		t.into()
+	}
+
+	fn config() -> table::TableConfig<u64> {
+		table::TableConfig {
+			small_blind: 5,
+			big_blind: 10,
+			min_buy_in: 100,
+			max_buy_in: BUY_IN,
+			seats: 9,
+			action_timeout: 10,
+			..Default::default()
+		}
+	}
+
+	///Seats everybody with the maximal buy-in at a new table
+	fn open(players: &[u64]) -> GameId {
+		let buy_ins: Vec<(u64, u64)> = players.iter().map(|&p| (p, BUY_IN)).collect();
+		open_with(&buy_ins)
+	}
+
+	fn open_with(buy_ins: &[(u64, u64)]) -> GameId {
+		let game = Poker::next_game_id();
+		let (host, buy_in) = buy_ins[0];
+		assert_ok!(Poker::create_game(Origin::signed(host), config(), buy_in));
+		for &(p, buy_in) in buy_ins[1..].iter() {
+			assert_ok!(Poker::join_game(Origin::signed(p), game, buy_in));
+		}
+		game
+	}
+
+	fn open_betting(betting: betting::BettingStructure<u64>, players: &[u64]) -> GameId {
+		let game = Poker::next_game_id();
+		let config = table::TableConfig { betting, ..config() };
+		assert_ok!(Poker::create_game(Origin::signed(players[0]), config, BUY_IN));
+		for &p in players[1..].iter() {
+			assert_ok!(Poker::join_game(Origin::signed(p), game, BUY_IN));
+		}
+		game
+	}
+
+	///Every seat is taken for the buy-in of 100, so the tournament starts
+	fn open_sit_and_go(entrants: &[u64], starting_stack: u64, ante: u64) -> GameId {
+		let game = Poker::next_game_id();
+		let tournament = sit_and_go::SitAndGo {
+			buy_in: 100,
+			starting_stack,
+			levels: vec![sit_and_go::Level { small_blind: 10, big_blind: 20, ante }],
+			escalation: sit_and_go::Escalation::Hands(10),
+			payouts: vec![70, 30],
+		};
+		let config = table::TableConfig { seats: entrants.len() as u32, ..config() };
+		assert_ok!(Poker::create_sit_and_go(Origin::signed(entrants[0]), config, tournament));
+		for &p in entrants[1..].iter() {
+			assert_ok!(Poker::join_game(Origin::signed(p), game, 100));
+		}
+		game
+	}
+
+	fn exponent(seed: u64) -> Vec<u8> {
+		let seed = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+		let mut bytes: Vec<u8> = (0..commutative::ELEMENT_SIZE as u64)
+			.map(|i| (seed.wrapping_mul(i + 1).wrapping_add(i) >> 16) as u8)
+			.collect();
+		bytes[0] = 0x3f;
+		bytes
+	}
+
+	fn secret(who: u64, stage: stage::StageId) -> Vec<u8> {
+		exponent(who * 10 + stage as u64)
+	}
+
+	///Encrypts cards for the secret of the participant
+	fn encrypt(cards: Vec<&cards::Card>, who: u64, stage: stage::StageId) -> Vec<u8> {
+		let key = commutative::inverse(&secret(who, stage)).unwrap();
+		commutative::encrypt(&commutative::encode(cards), &key).unwrap()
+	}
+
+	///Cards are dealt, everybody has bet the same and registered keys of `secret`
+	fn deal(game: GameId, players: &[u64], bet: u64) {
+		for &p in players.iter() {
+			let public = |stage| commutative::public(&secret(p, stage)).unwrap();
+			<Keys<Test>>::insert((game, p), keys::PublicStorage {
+				hand: public(stage::SHOWDOWN),
+				flop: public(stage::FLOP),
+				turn: public(stage::TURN),
+				river: public(stage::RIVER),
+			});
+			<Bets<Test>>::insert((game, p), bet);
+		}
+		<Participants<Test>>::insert(game, players.to_vec());
+		<Stage<Test>>::insert(game, stage::PREFLOP);
+	}
+
+	fn assert_refunded(game: GameId, players: &[u64]) {
+		for &p in players.iter() {
+			assert_eq!(Poker::stacks((game, p)), BUY_IN);
+			assert_eq!(Balances::reserved_balance(&p), BUY_IN);
+			assert_eq!(Balances::free_balance(&p), BALANCE - BUY_IN);
+		}
+	}
+
+	///Betting on the river is finished, everybody opens his pocket cards
+	fn show_down(game: GameId, pockets: &[(u64, [cards::Card; 2])], shared: Vec<&cards::Card>) {
+		<Stage<Test>>::insert(game, stage::RIVER);
+		<SharedCards<Test>>::insert(game, cards::encode(shared));
+		for (p, pocket) in pockets.iter() {
+			<PocketCards<Test>>::insert((game, *p), encrypt(pocket.iter().collect(), *p, stage::SHOWDOWN));
+		}
+		for (p, _) in pockets.iter() {
+			assert_ok!(Poker::next_stage(Origin::signed(*p), game, secret(*p, stage::SHOWDOWN)));
+		}
+	}
+
+	///Ends the block at which the action timer of the table expires
+	fn expire(game: GameId) {
+		let deadline = Poker::deadline(game).expect("the action timer must run");
+		<Poker as OnFinalize<u64>>::on_finalize(deadline);
+	}
+
+	fn nonce(who: u64) -> H256 {
+		H256::from([who as u8; 32])
+	}
+
+	///Proves knowledge of the secret key, the same way as clients do
+	fn prove(secret: &[u8], context: &[u8]) -> knowledge::Proof {
+		let nonce = exponent(7);
+		let public = commutative::public(secret).unwrap();
+		let commitment = commutative::public(&nonce).unwrap();
+		let challenge = knowledge::challenge(&public, &commitment, context);
+		let response = commutative::add(&nonce, &commutative::multiply(&challenge, secret).unwrap()).unwrap();
+		knowledge::Proof { commitment, response }
+	}
+
+	///Registers public keys of `secret` and commits to the nonce of the participant
+	fn register(game: GameId, who: u64) -> Result {
+		let stages = [stage::SHOWDOWN, stage::FLOP, stage::TURN, stage::RIVER];
+		let context = (game, who).encode();
+		let keys: Vec<Vec<u8>> = stages.iter()
+			.map(|&stage| commutative::public(&secret(who, stage)).unwrap())
+			.collect();
+		let proofs = stages.iter()
+			.map(|&stage| prove(&secret(who, stage), &context))
+			.collect();
+		let commitment = nonce(who).using_encoded(BlakeTwo256::hash);
+		Poker::preflop(Origin::signed(who), game,
+			keys[0].clone(), keys[1].clone(), keys[2].clone(), keys[3].clone(), commitment, proofs)
+	}
+
+	///Keys and nonces are registered, the initial deck is prepared
+	fn start_shuffle(game: GameId, players: &[u64]) {
+		<Participants<Test>>::insert(game, players.to_vec());
+		<Stage<Test>>::insert(game, stage::SHUFFLE);
+		<Decks<Test>>::insert(game, vec![commutative::encode(cards::deck().iter().collect())]);
+	}
+
+	///Encryption with the key 1 keeps cards, so the deck is only reversed;
+	///a forged proof opens every round as the link with the input
+	fn shuffled(input: &[u8], honest: bool) -> (Vec<u8>, shuffle::Proof) {
+		let size = commutative::ELEMENT_SIZE;
+		let mut one = vec![0; size];
+		one[size - 1] = 1;
+		let output: Vec<u8> = input.chunks(size).rev().flat_map(|card| card.to_vec()).collect();
+		let identity: Vec<u8> = (0..cards::DECK_SIZE as u8).collect();
+		let reversed: Vec<u8> = identity.iter().rev().cloned().collect();
+
+		let decks = vec![input.to_vec(); shuffle::ROUNDS];
+		let openings = shuffle::challenge(input, &output, &decks).into_iter()
+			.map(|bit| if bit && honest {
+				shuffle::Opening::Output { exponent: one.clone(), permutation: reversed.clone() }
+			} else {
+				shuffle::Opening::Input { exponent: one.clone(), permutation: identity.clone() }
+			})
+			.collect();
+		(output, shuffle::Proof { decks, openings })
+	}
+
+	///Everybody registers keys and reveals nonces, the initial deck is dealt
+	///as if it was shuffled and locked, blinds are posted
+	fn dealt(game: GameId, players: &[u64]) {
+		for &p in players.iter() {
+			assert_ok!(register(game, p));
+		}
+		for &p in players.iter() {
+			assert_ok!(Poker::reveal_nonce(Origin::signed(p), game, nonce(p)));
+		}
+		assert_ok!(Poker::deal(game));
+	}
+
+	#[test]
+	fn invalid_shared_cards_cancel_the_round() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			deal(game, &players, 10);
+
+			//the flop is encrypted with the key of somebody else
+			<FlopCards<Test>>::insert(game, encrypt(vec![&spades(A), &hearts(K), &clubs(7)], 3, stage::FLOP));
+
+			assert_ok!(Poker::next_stage(Origin::signed(1), game, secret(1, stage::FLOP)));
+			assert_eq!(Poker::next_stage(Origin::signed(2), game, secret(2, stage::FLOP)),
+				Err(PokerError::InvalidSharedCards.message()));
+
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			assert!(Poker::participants(game).is_empty());
+			assert!(Poker::deadline(game).is_none());
+			assert_refunded(game, &players);
+		});
+	}
+
+	#[test]
+	fn cancelled_round_keeps_everybody_seated() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			deal(game, &players, 10);
+			<FlopCards<Test>>::insert(game, encrypt(vec![&spades(A), &hearts(K), &clubs(7)], 4, stage::FLOP));
+
+			assert_ok!(Poker::next_stage(Origin::signed(1), game, secret(1, stage::FLOP)));
+			assert_ok!(Poker::next_stage(Origin::signed(2), game, secret(2, stage::FLOP)));
+			assert_eq!(Poker::next_stage(Origin::signed(3), game, secret(3, stage::FLOP)),
+				Err(PokerError::InvalidSharedCards.message()));
+
+			//nobody is late for the next hand until somebody registers his keys
+			for n in 1..=2 * Test::ACTION_TIMEOUT {
+				<Poker as OnFinalize<u64>>::on_finalize(n);
+			}
+			assert_eq!(Poker::seats(game), players.to_vec());
+			assert_refunded(game, &players);
+		});
+	}
+
+	#[test]
+	fn secrets_of_wrong_size_are_not_logged() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			deal(game, &players, 10);
+			<CurrentHand<Test>>::insert(game, 0);
+
+			assert_eq!(Poker::next_stage(Origin::signed(1), game, vec![7; 100_000]),
+				Err(PokerError::InvalidSecretSize.message()));
+			assert!(Poker::hand_actions(0).is_empty());
+			assert_eq!(Poker::stage(game), stage::PREFLOP);
+			assert_eq!(Poker::bets((game, 1)), 10);
+
+			//a secret of the right size which doesn't match is the evidence
+			let mut wrong = secret(1, stage::FLOP);
+			wrong[1] ^= 1;
+			assert_eq!(Poker::next_stage(Origin::signed(1), game, wrong.clone()),
+				Err(PokerError::SecretMismatch.message()));
+			assert_eq!(Poker::hand_actions(0).first(), Some(&Action::WrongSecret(1, stage::FLOP, wrong)));
+
+			//the replay checks what everybody got back and what the late participant has lost
+			let actions = Poker::hand_actions(0);
+			assert!(actions.contains(&Action::Refunded(2, 10)));
+			assert!(actions.contains(&Action::Forfeited(1, 10)));
+			assert!(actions.contains(&Action::PotAwarded(2, 10)));
+		});
+	}
+
+	#[test]
+	fn repeated_pocket_cards_cancel_the_round() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			deal(game, &players, 10);
+			<Stage<Test>>::insert(game, stage::RIVER);
+
+			for &p in players.iter() {
+				<PocketCards<Test>>::insert((game, p), encrypt(vec![&spades(A), &spades(K)], p, stage::SHOWDOWN));
+			}
+
+			assert_ok!(Poker::next_stage(Origin::signed(1), game, secret(1, stage::SHOWDOWN)));
+			assert_ok!(Poker::next_stage(Origin::signed(2), game, secret(2, stage::SHOWDOWN)));
+			assert_eq!(Poker::next_stage(Origin::signed(3), game, secret(3, stage::SHOWDOWN)),
+				Err(PokerError::InvalidPocketCards.message()));
+
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			assert!(Poker::deadline(game).is_none());
+			assert_refunded(game, &players);
+
+			//the table isn't stuck, the next round can be started
+			assert_eq!(Poker::button(game), 1);
+			assert_eq!(Poker::seats(game).len(), players.len());
+		});
+	}
+
+	#[test]
+	fn late_bettor_folds_and_loses_the_bet() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			deal(game, &players, 0);
+			<Bets<Test>>::insert((game, 1), 10);
+			<Bets<Test>>::insert((game, 2), 5);
+			<BetLevel<Test>>::insert(game, 10);
+			<BetsNow<Test>>::insert(game, 2);
+			Poker::start_timer(game);
+
+			expire(game);
+
+			//the uncalled part of the bet goes back, the blind of the late one is lost
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			assert_eq!(Poker::stacks((game, 1)), BUY_IN + 5);
+			assert_eq!(Poker::stacks((game, 2)), BUY_IN - 5);
+			assert_eq!(Balances::reserved_balance(&1), BUY_IN + 5);
+			assert_eq!(Balances::reserved_balance(&2), BUY_IN - 5);
+			for p in players.iter() {
+				assert_eq!(Balances::free_balance(p), BALANCE - BUY_IN);
+			}
+		});
+	}
+
+	#[test]
+	fn late_secrets_forfeit_chips_to_the_others() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			deal(game, &players, 10);
+			<CurrentHand<Test>>::insert(game, 0);
+			<FlopCards<Test>>::insert(game, encrypt(vec![&spades(A), &hearts(K), &clubs(7)], 1, stage::FLOP));
+
+			assert_ok!(Poker::next_stage(Origin::signed(1), game, secret(1, stage::FLOP)));
+			Poker::start_timer(game);
+			expire(game);
+
+			//the only one who has submitted the secret gets the chips of the others
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			assert_eq!(Poker::stacks((game, 1)), BUY_IN + 20);
+			assert_eq!(Balances::reserved_balance(&1), BUY_IN + 20);
+			for &p in [2, 3].iter() {
+				assert_eq!(Poker::stacks((game, p)), BUY_IN - 10);
+				assert_eq!(Balances::reserved_balance(&p), BUY_IN - 10);
+			}
+			for p in players.iter() {
+				assert_eq!(Balances::free_balance(p), BALANCE - BUY_IN);
+			}
+
+			let actions = Poker::hand_actions(0);
+			assert!(actions.contains(&Action::Refunded(1, 10)));
+			assert!(actions.contains(&Action::Forfeited(2, 10)));
+			assert!(actions.contains(&Action::Forfeited(3, 10)));
+		});
+	}
+
+	#[test]
+	fn side_pots_are_paid_from_reserves_of_losers() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open_with(&[(1, 100), (2, 300), (3, BUY_IN)]);
+			deal(game, &players, 0);
+			//both short stacks are all-in
+			<Bets<Test>>::insert((game, 1), 100);
+			<Bets<Test>>::insert((game, 2), 300);
+			<Bets<Test>>::insert((game, 3), 300);
+
+			show_down(game, &[
+				(1, [spades(A), clubs(A)]),
+				(2, [spades(K), clubs(K)]),
+				(3, [spades(Q), clubs(3)]),
+			], vec![&hearts(2), &clubs(7), &diamonds(9), &spades(J), &hearts(4)]);
+
+			//the main pot of 300 goes to aces, the side pot of 400 to kings;
+			//the rake of 3 big blinds takes 15 chips from each of them
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			for &(p, buy_in, stack) in [(1, 100, 285), (2, 300, 385), (3, BUY_IN, 700)].iter() {
+				assert_eq!(Poker::stacks((game, p)), stack);
+				assert_eq!(Poker::reserved((game, p)), stack);
+				assert_eq!(Balances::reserved_balance(&p), stack);
+				assert_eq!(Balances::free_balance(&p), BALANCE - buy_in);
+			}
+			assert_eq!(Balances::total_issuance(), 9 * BALANCE - 30);
+		});
+	}
+
+	#[test]
+	fn rake_is_capped_and_sent_from_reserves_of_losers() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			deal(game, &players, BUY_IN);
+
+			show_down(game, &[
+				(1, [spades(A), clubs(A)]),
+				(2, [spades(K), clubs(K)]),
+			], vec![&hearts(2), &clubs(7), &diamonds(9), &spades(J), &hearts(4)]);
+
+			//5% of the pot of 2000 is more than the cap of 3 big blinds
+			assert_eq!(Poker::stacks((game, 1)), 2 * BUY_IN - 30);
+			assert_eq!(Balances::reserved_balance(&1), 2 * BUY_IN - 30);
+			assert_eq!(Poker::stacks((game, 2)), 0);
+			assert_eq!(Balances::reserved_balance(&2), 0);
+			for p in players.iter() {
+				assert_eq!(Balances::free_balance(p), BALANCE - BUY_IN);
+			}
+			assert_eq!(Poker::rake(game), 0);
+			assert_eq!(Balances::total_issuance(), 9 * BALANCE - 30);
+		});
+	}
+
+	#[test]
+	fn invalid_shuffle_is_challenged() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			start_shuffle(game, &players);
+
+			//the order of dealing starts left of the button
+			let (deck, honest) = shuffled(&Poker::decks(game)[0], true);
+			assert_ok!(Poker::submit_shuffle(Origin::signed(2), game, deck.clone(), honest.clone()));
+			let (deck, forged) = shuffled(&deck, false);
+			assert_ok!(Poker::submit_shuffle(Origin::signed(3), game, deck, forged.clone()));
+			assert_eq!(Balances::free_balance(&3), BALANCE - BUY_IN - SHUFFLE_CHECKS as u64);
+
+			//the challenger pays for verification of a valid round
+			assert_eq!(Poker::challenge_shuffle(Origin::signed(1), game, 1, honest.clone(), 0),
+				Err(PokerError::ProofMismatch.message()));
+			assert_eq!(Poker::challenge_shuffle(Origin::signed(1), game, 0, honest, 0),
+				Err(PokerError::ShuffleRoundValid.message()));
+			assert_eq!(Balances::free_balance(&1), BALANCE - BUY_IN - cards::DECK_SIZE as u64);
+			assert_eq!(Poker::seats(game), players.to_vec());
+
+			let decks = Poker::decks(game);
+			let failed = shuffle::challenge(&decks[1], &decks[2], &forged.decks).into_iter()
+				.position(|bit| bit)
+				.unwrap();
+			assert_ok!(Poker::challenge_shuffle(Origin::signed(1), game, 1, forged, failed as u32));
+			assert_eq!(Poker::seats(game), vec![1, 2]);
+			assert_eq!(Poker::stage(game), stage::IDLE);
+			assert!(Poker::decks(game).is_empty());
+			assert!(Poker::shuffle_proofs(game).is_empty());
+		});
+	}
+
+	#[test]
+	fn proofs_are_limited_per_block() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			start_shuffle(game, &players);
+
+			let (deck, proof) = shuffled(&Poker::decks(game)[0], true);
+			assert_ok!(Poker::submit_shuffle(Origin::signed(2), game, deck, proof.clone()));
+			for _ in 1..4 {
+				assert_eq!(Poker::challenge_shuffle(Origin::signed(1), game, 0, proof.clone(), 0),
+					Err(PokerError::ShuffleRoundValid.message()));
+			}
+			assert_eq!(Poker::challenge_shuffle(Origin::signed(1), game, 0, proof.clone(), 0),
+				Err(PokerError::TooManyProofs.message()));
+			assert_eq!(Balances::free_balance(&1), BALANCE - BUY_IN - 3 * cards::DECK_SIZE as u64);
+
+			<Poker as OnFinalize<u64>>::on_finalize(1);
+			assert_eq!(Poker::challenge_shuffle(Origin::signed(1), game, 0, proof, 0),
+				Err(PokerError::ShuffleRoundValid.message()));
+		});
+	}
+
+	#[test]
+	fn absent_entrant_keeps_the_seat_and_is_blinded_off() {
+		with_externalities(&mut new_test_ext(), || {
+			let game = open_sit_and_go(&[1, 2, 3], 1500, 5);
+
+			assert_ok!(register(game, 1));
+			assert_ok!(register(game, 2));
+			expire(game);
+
+			//3 keeps his seat and chips, the next hand is dealt without him
+			assert_eq!(Poker::seats(game), vec![1, 2, 3]);
+			assert_eq!(Poker::sitting_out(game), vec![3]);
+			assert!(Poker::eliminated(game).is_empty());
+			assert_eq!(Poker::stacks((game, 3)), 1500);
+
+			assert_ok!(register(game, 1));
+			assert_ok!(register(game, 2));
+			assert_eq!(Poker::stage(game), stage::SHUFFLE);
+			assert_eq!(Poker::participants(game), vec![1, 2]);
+			assert_eq!(Poker::stacks((game, 3)), 1500 - 25);
+			assert_eq!(Poker::stacks((game, 1)), 1500 + 13);
+			assert_eq!(Poker::stacks((game, 2)), 1500 + 12);
+			assert_eq!(Poker::prize_pool(game), 300);
+		});
+	}
+
+	#[test]
+	fn button_and_blinds_move_clockwise() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3, 4];
+			let game = open(&players);
+			dealt(game, &players);
+
+			//blinds are left of the button, the action starts left of big blind
+			assert_eq!(Poker::participants(game), vec![1, 2, 3, 4]);
+			assert_eq!(Poker::bets((game, 2)), 5);
+			assert_eq!(Poker::bets((game, 3)), 10);
+			assert_eq!(Poker::call(Origin::signed(1), game), Err(PokerError::NotYourTurn.message()));
+
+			assert_ok!(Poker::call(Origin::signed(4), game));
+			assert_ok!(Poker::raise(Origin::signed(1), game, 30));
+			assert_ok!(Poker::fold(Origin::signed(2), game));
+			assert_ok!(Poker::call(Origin::signed(3), game));
+			//4 has acted before the raise, so he has to respond to it
+			assert_eq!(Poker::bets_now(game), Some(4));
+			assert_ok!(Poker::call(Origin::signed(4), game));
+			assert_eq!(Poker::bets_now(game), None);
+
+			assert_ok!(Poker::fold(Origin::signed(3), game));
+			assert_ok!(Poker::fold(Origin::signed(4), game));
+			assert_eq!(Poker::stacks((game, 1)), BUY_IN + 65);
+			assert_eq!(Balances::reserved_balance(&1), BUY_IN + 65);
+
+			//the button goes to the next seat, so do the blinds
+			assert_eq!(Poker::button(game), 1);
+			dealt(game, &players);
+			assert_eq!(Poker::participants(game), vec![2, 3, 4, 1]);
+			assert_eq!(Poker::bets((game, 3)), 5);
+			assert_eq!(Poker::bets((game, 4)), 10);
+			assert_eq!(Poker::bets_now(game), Some(1));
+		});
+	}
+
+	#[test]
+	fn button_posts_small_blind_heads_up() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			dealt(game, &players);
+
+			assert_eq!(Poker::bets((game, 1)), 5);
+			assert_eq!(Poker::bets((game, 2)), 10);
+			assert_eq!(Poker::bets_now(game), Some(1));
+			assert_ok!(Poker::call(Origin::signed(1), game));
+			assert_ok!(Poker::check(Origin::signed(2), game));
+			assert_eq!(Poker::bets_now(game), None);
+		});
+	}
+
+	#[test]
+	fn chips_are_added_between_hands_up_to_the_maximum() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open_with(&[(1, 100), (2, BUY_IN)]);
+
+			assert_eq!(Poker::top_up(Origin::signed(1), game, BUY_IN),
+				Err(PokerError::StackAboveMaximum.message()));
+			assert_eq!(Poker::top_up(Origin::signed(1), game, 0),
+				Err(PokerError::ZeroAmount.message()));
+			assert_eq!(Poker::rebuy(Origin::signed(1), game, 100),
+				Err(PokerError::StackNotEmpty.message()));
+			assert_eq!(Poker::rebuy(Origin::signed(3), game, 100),
+				Err(PokerError::NotParticipant.message()));
+			assert_ok!(Poker::top_up(Origin::signed(1), game, 50));
+			assert_eq!(Poker::stacks((game, 1)), 150);
+
+			//1 goes all-in and loses everything
+			dealt(game, &players);
+			assert_eq!(Poker::top_up(Origin::signed(1), game, 50),
+				Err(PokerError::WaitForEndOfRound.message()));
+			assert_ok!(Poker::raise(Origin::signed(1), game, 150));
+			assert_ok!(Poker::call(Origin::signed(2), game));
+			show_down(game, &[
+				(1, [spades(K), clubs(K)]),
+				(2, [spades(A), clubs(A)]),
+			], vec![&hearts(2), &clubs(7), &diamonds(9), &spades(J), &hearts(4)]);
+			assert_eq!(Poker::stacks((game, 1)), 0);
+			assert_eq!(Poker::seats(game), players.to_vec());
+
+			assert_eq!(Poker::top_up(Origin::signed(1), game, 100),
+				Err(PokerError::StackEmpty.message()));
+			assert_eq!(Poker::rebuy(Origin::signed(1), game, 99),
+				Err(PokerError::BuyInOutOfLimits.message()));
+			assert_eq!(Poker::rebuy(Origin::signed(1), game, BUY_IN + 1),
+				Err(PokerError::StackAboveMaximum.message()));
+			assert_ok!(Poker::rebuy(Origin::signed(1), game, 300));
+			assert_eq!(Poker::stacks((game, 1)), 300);
+			assert_eq!(Poker::reserved((game, 1)), 300);
+			assert_eq!(Balances::reserved_balance(&1), 300);
+			assert_eq!(Balances::free_balance(&1), BALANCE - 450);
+		});
+	}
+
+	#[test]
+	fn invalid_table_config_is_rejected() {
+		with_externalities(&mut new_test_ext(), || {
+			let fixed = |small_bet, big_bet, max_raises| table::TableConfig {
+				betting: betting::BettingStructure::FixedLimit { small_bet, big_bet, max_raises },
+				..config()
+			};
+			let rejected = vec![
+				(table::TableConfig { small_blind: 0, ..config() }, PokerError::InvalidBlinds),
+				(table::TableConfig { small_blind: 20, ..config() }, PokerError::InvalidBlinds),
+				(table::TableConfig { ante: 11, ..config() }, PokerError::AnteAboveBigBlind),
+				(table::TableConfig { min_buy_in: 5, ..config() }, PokerError::BuyInBelowBigBlind),
+				(table::TableConfig { max_buy_in: 50, ..config() }, PokerError::InvalidBuyInRange),
+				(table::TableConfig { seats: 1, ..config() }, PokerError::InvalidSeats),
+				(table::TableConfig { seats: table::MAX_SEATS + 1, ..config() }, PokerError::InvalidSeats),
+				(table::TableConfig { action_timeout: 0, ..config() }, PokerError::InvalidTimeout),
+				(table::TableConfig { action_timeout: Test::ACTION_TIMEOUT + 1, ..config() }, PokerError::InvalidTimeout),
+				(fixed(0, 20, 4), PokerError::InvalidFixedLimit),
+				(fixed(20, 10, 4), PokerError::InvalidFixedLimit),
+				(fixed(10, 20, 0), PokerError::InvalidFixedLimit),
+			];
+			for (config, error) in rejected.into_iter() {
+				assert_eq!(Poker::create_game(Origin::signed(1), config, BUY_IN), Err(error.message()));
+			}
+			assert_eq!(Poker::create_game(Origin::signed(1), config(), BUY_IN + 1),
+				Err(PokerError::BuyInOutOfLimits.message()));
+			assert_eq!(Poker::create_game(Origin::signed(1), config(), 99),
+				Err(PokerError::BuyInOutOfLimits.message()));
+
+			//nothing is reserved or stored for a rejected table
+			assert_eq!(Poker::next_game_id(), 0);
+			assert_eq!(Balances::free_balance(&1), BALANCE);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+
+			let config = fixed(10, 20, 4);
+			assert_ok!(Poker::create_game(Origin::signed(1), config.clone(), BUY_IN));
+			assert_eq!(Poker::config(0), config);
+			assert_eq!(Poker::seats(0), vec![1]);
+		});
+	}
+
+	#[test]
+	fn pot_limit_raise_is_bounded_by_the_pot() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open_betting(betting::BettingStructure::PotLimit, &players);
+			dealt(game, &players);
+
+			//the call of 10 makes the pot 25, so the raise is up to 35
+			assert_eq!(Poker::raise_range(game), Some((1, 20, 35)));
+			assert_eq!(Poker::raise(Origin::signed(1), game, 36), Err(PokerError::RaiseAboveLimit.message()));
+			assert_ok!(Poker::raise(Origin::signed(1), game, 35));
+
+			//the pot of 50 after the call of 30, on top of the bet of 35
+			assert_eq!(Poker::raise_range(game), Some((2, 60, 115)));
+			assert_eq!(Poker::raise(Origin::signed(2), game, 59), Err(PokerError::RaiseTooSmall.message()));
+			assert_eq!(Poker::raise(Origin::signed(2), game, 116), Err(PokerError::RaiseAboveLimit.message()));
+			assert_ok!(Poker::raise(Origin::signed(2), game, 115));
+			assert_eq!(Poker::bet_level(game), Some(115));
+		});
+	}
+
+	#[test]
+	fn fixed_limit_raises_by_the_bet_and_are_capped() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let limit = betting::BettingStructure::FixedLimit { small_bet: 10, big_bet: 20, max_raises: 2 };
+			let game = open_betting(limit, &players);
+			dealt(game, &players);
+
+			assert_eq!(Poker::raise_range(game), Some((1, 20, 20)));
+			assert_eq!(Poker::raise(Origin::signed(1), game, 30), Err(PokerError::RaiseAboveLimit.message()));
+			assert_eq!(Poker::raise(Origin::signed(1), game, 15), Err(PokerError::RaiseTooSmall.message()));
+			assert_ok!(Poker::raise(Origin::signed(1), game, 20));
+			assert_ok!(Poker::raise(Origin::signed(2), game, 30));
+
+			//the cap of two raises is reached, the big blind can only call or fold
+			assert_eq!(Poker::raise_range(game), None);
+			assert_eq!(Poker::raise(Origin::signed(3), game, 40), Err(PokerError::RaisingClosed.message()));
+			assert_ok!(Poker::call(Origin::signed(3), game));
+			assert_eq!(Poker::bets((game, 3)), 30);
+		});
+	}
+
+	#[test]
+	fn short_all_in_does_not_reopen_raising() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3, 4];
+			let game = open_with(&[(1, BUY_IN), (2, 100), (3, BUY_IN), (4, BUY_IN)]);
+			dealt(game, &players);
+
+			//a re-raise is at least as large as the raise of 70
+			assert_ok!(Poker::raise(Origin::signed(4), game, 80));
+			assert_eq!(Poker::raise_range(game), Some((1, 150, BUY_IN)));
+			assert_eq!(Poker::raise(Origin::signed(1), game, 149), Err(PokerError::RaiseTooSmall.message()));
+			assert_ok!(Poker::call(Origin::signed(1), game));
+
+			//all-in for 20 more is not a full raise, so the minimum stays the same
+			assert_ok!(Poker::raise(Origin::signed(2), game, 100));
+			assert_eq!(Poker::raise_range(game), Some((3, 170, BUY_IN)));
+			assert_ok!(Poker::call(Origin::signed(3), game));
+
+			//those who have acted before can only call the all-in or fold
+			let actions = Poker::legal_actions(game, &4).unwrap();
+			assert_eq!(actions.call, Some(20));
+			assert_eq!(actions.raise, None);
+			assert_eq!(Poker::raise(Origin::signed(4), game, 200), Err(PokerError::RaisingClosed.message()));
+			assert_ok!(Poker::call(Origin::signed(4), game));
+			assert_ok!(Poker::call(Origin::signed(1), game));
+			assert_eq!(Poker::bets_now(game), None);
+			for p in players.iter() {
+				assert_eq!(Poker::bets((game, *p)), 100);
+			}
+		});
+	}
+
+	#[test]
+	fn sit_and_go_pays_out_in_order_of_elimination() {
+		with_externalities(&mut new_test_ext(), || {
+			let game = open_sit_and_go(&[1, 2, 3], 500, 0);
+			let board = [hearts(2), clubs(7), diamonds(9), spades(J), hearts(4)];
+
+			dealt(game, &[1, 2, 3]);
+			assert_ok!(Poker::raise(Origin::signed(1), game, 500));
+			assert_ok!(Poker::fold(Origin::signed(2), game));
+			assert_ok!(Poker::call(Origin::signed(3), game));
+			show_down(game, &[(1, [spades(A), clubs(A)]), (3, [spades(K), clubs(K)])], board.iter().collect());
+
+			//3 is out at the third place, the prize pool is untouched
+			assert_eq!(Poker::seats(game), vec![1, 2]);
+			assert_eq!(Poker::eliminated(game), vec![3]);
+			assert_eq!(Poker::stacks((game, 1)), 1010);
+			assert_eq!(Poker::stacks((game, 2)), 490);
+			assert_eq!(Poker::prize_pool(game), 300);
+			assert_eq!(Balances::reserved_balance(&3), 100);
+
+			//heads-up the button is 2, he posts small blind and acts first
+			dealt(game, &[2, 1]);
+			assert_eq!(Poker::participants(game), vec![2, 1]);
+			assert_ok!(Poker::raise(Origin::signed(2), game, 490));
+			assert_ok!(Poker::call(Origin::signed(1), game));
+			show_down(game, &[(2, [spades(K), clubs(K)]), (1, [spades(A), clubs(A)])], board.iter().collect());
+
+			//70% of the pool goes to the winner, 30% to the second place
+			assert!(Poker::seats(game).is_empty());
+			assert_eq!(Poker::prize_pool(game), 0);
+			for &(p, balance) in [(1, BALANCE + 110), (2, BALANCE - 10), (3, BALANCE - 100)].iter() {
+				assert_eq!(Balances::free_balance(&p), balance);
+				assert_eq!(Balances::reserved_balance(&p), 0);
+			}
+			assert_eq!(Balances::total_issuance(), 9 * BALANCE);
+		});
+	}
+
+	#[test]
+	fn hand_log_is_pruned_after_retention() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2];
+			let game = open(&players);
+			<system::Module<Test>>::set_block_number(5);
+
+			dealt(game, &players);
+			let hand = Poker::current_hand(game).unwrap();
+			assert_ok!(Poker::fold(Origin::signed(1), game));
+			assert_eq!(Poker::current_hand(game), None);
+
+			let expiry = 5 + Test::HAND_RETENTION;
+			assert_eq!(Poker::expiring_hands(expiry), vec![hand]);
+			assert_eq!(Poker::hand_table(hand), Some(game));
+			let actions = Poker::hand_actions(hand);
+			assert!(actions.contains(&Action::Blind(2, 10)));
+			assert!(actions.contains(&Action::Fold(1)));
+			assert!(actions.contains(&Action::PotAwarded(2, 10)));
+
+			//the log is kept until the end of the retention period
+			<Poker as OnFinalize<u64>>::on_finalize(expiry - 1);
+			assert_eq!(Poker::hand_actions(hand), actions);
+			<Poker as OnFinalize<u64>>::on_finalize(expiry);
+			assert!(Poker::hand_actions(hand).is_empty());
+			assert_eq!(Poker::hand_table(hand), None);
+			assert!(Poker::expiring_hands(expiry).is_empty());
+		});
+	}
+
+	#[test]
+	fn legal_actions_agree_with_dispatchables() {
+		with_externalities(&mut new_test_ext(), || {
+			let players = [1, 2, 3];
+			let game = open(&players);
+			dealt(game, &players);
+
+			//only the one who has to bet now has actions
+			assert!(Poker::legal_actions(game, &2).is_none());
+			let actions = Poker::legal_actions(game, &1).unwrap();
+			assert!(!actions.check && !actions.option);
+			assert_eq!(actions.call, Some(10));
+			assert_eq!(actions.raise, Some((20, BUY_IN)));
+			assert_eq!(Poker::check(Origin::signed(1), game), Err(PokerError::CantCheck.message()));
+			assert_eq!(Poker::raise(Origin::signed(1), game, 19), Err(PokerError::RaiseTooSmall.message()));
+			assert_ok!(Poker::call(Origin::signed(1), game));
+			assert_eq!(Poker::bets((game, 1)), 10);
+
+			let actions = Poker::legal_actions(game, &2).unwrap();
+			assert_eq!(actions.call, Some(5));
+			assert_ok!(Poker::call(Origin::signed(2), game));
+
+			//everybody has called, big blind can check or raise
+			let actions = Poker::legal_actions(game, &3).unwrap();
+			assert!(actions.check && actions.option);
+			assert_eq!(actions.call, None);
+			assert_eq!(actions.raise, Some((20, BUY_IN)));
+			assert_eq!(Poker::raise_range(game), Some((3, 20, BUY_IN)));
+			assert_ok!(Poker::check(Origin::signed(3), game));
+			assert_eq!(Poker::bets_now(game), None);
+			assert!(Poker::legal_actions(game, &3).is_none());
+		});
+	}
+}
@@ -0,0 +1,218 @@
+use rstd::prelude::*;
+use rstd::cmp::min;
+use runtime_primitives::traits::{SimpleArithmetic, Zero, One};
+
+///Main pot goes first, then side pots; each pot can be won
+///only by participants who have put enough chips to it
+pub type Pots<AccountId, Balance> = Vec<(Balance, Vec<AccountId>)>;
+
+///Finds the participant whose bet is not matched by anybody else,
+///returns him together with the excess which must be returned to him
+pub fn uncalled<AccountId, Balance>(bets: &[(AccountId, Balance)]) -> Option<(AccountId, Balance)>
+    where AccountId: Clone, Balance: SimpleArithmetic + Copy {
+    let highest = bets.iter()
+        .enumerate()
+        .fold(None, |max: Option<(usize, Balance)>, (i, (_, bet))| match max {
+            Some((_, value)) if value >= *bet => max,
+            _ => Some((i, *bet))
+        });
+
+    highest.and_then(|(i, value)| {
+        let second = bets.iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, (_, bet))| *bet)
+            .max()
+            .unwrap_or(Zero::zero());
+
+        if value > second {
+            Some((bets[i].0.clone(), value - second))
+        } else {
+            None
+        }
+    })
+}
+
+///Splits chips committed by participants during the round into the main pot and side pots;
+///`committed` contains every participant of the round, the flag tells
+///if he is still holding cards (folded participants can't win anything)
+pub fn split<AccountId, Balance>(committed: &[(AccountId, Balance, bool)]) -> Pots<AccountId, Balance>
+    where AccountId: Clone, Balance: SimpleArithmetic + Copy {
+    let mut levels: Vec<Balance> = committed.iter()
+        .filter(|(_, _, in_hand)| *in_hand)
+        .map(|(_, amount, _)| *amount)
+        .collect();
+    levels.sort();
+    levels.dedup();
+
+    let mut pots = vec![];
+    let mut previous: Balance = Zero::zero();
+    for level in levels.into_iter() {
+        let amount = committed.iter()
+            .fold(Zero::zero(), |sum: Balance, (_, c, _)| sum + min(*c, level) - min(*c, previous));
+
+        let eligible: Vec<AccountId> = committed.iter()
+            .filter(|(_, c, in_hand)| *in_hand && *c >= level)
+            .map(|(who, _, _)| who.clone())
+            .collect();
+
+        if amount > Zero::zero() {
+            pots.push((amount, eligible));
+        }
+        previous = level;
+    }
+
+    //folded participants could put more than anybody who is still in the game
+    let rest = committed.iter()
+        .filter(|(_, c, _)| *c > previous)
+        .fold(Zero::zero(), |sum: Balance, (_, c, _)| sum + *c - previous);
+
+    if rest > Zero::zero() {
+        if let Some(last) = pots.last_mut() {
+            last.0 = last.0 + rest;
+        }
+    }
+
+    pots
+}
+
+///Divides a pot among the winners evenly,
+///odd chips go to the first winners in the given order
+pub fn share<AccountId, Balance>(amount: Balance, winners: &[AccountId]) -> Vec<(AccountId, Balance)>
+    where AccountId: Clone, Balance: SimpleArithmetic + Copy {
+    if winners.is_empty() {
+        return vec![];
+    }
+
+    let count = Balance::sa(winners.len() as u64);
+    let part = amount / count;
+    let mut odd = amount - part * count;
+
+    winners.iter()
+        .map(|who| {
+            if odd > Zero::zero() {
+                odd = odd - One::one();
+                (who.clone(), part + One::one())
+            } else {
+                (who.clone(), part)
+            }
+        })
+        .collect()
+}
+
+///Takes `percent` of every pot starting from the main one, but not more than `cap`
+///in total; returns the whole amount taken
+pub fn rake<AccountId, Balance>(pots: &mut Pots<AccountId, Balance>, percent: u32, cap: Balance) -> Balance
+    where Balance: SimpleArithmetic + Copy {
+    let hundred = Balance::sa(100);
+    let mut taken: Balance = Zero::zero();
+
+    for (amount, _) in pots.iter_mut() {
+        let part = min(*amount * Balance::sa(percent as u64) / hundred, cap - taken);
+        *amount = *amount - part;
+        taken = taken + part;
+    }
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(pots: &Pots<char, u64>) -> u64 {
+        pots.iter().map(|(amount, _)| amount).sum()
+    }
+
+    #[test]
+    fn uncalled_bet_is_detected() {
+        assert_eq!(uncalled(&[('a', 100u64), ('b', 40)]), Some(('a', 60)));
+        assert_eq!(uncalled(&[('a', 40u64), ('b', 100), ('c', 70)]), Some(('b', 30)));
+        assert_eq!(uncalled(&[('a', 100u64), ('b', 100)]), None);
+        assert_eq!(uncalled(&[('a', 0u64), ('b', 0), ('c', 0)]), None);
+        assert_eq!(uncalled::<char, u64>(&[]), None);
+    }
+
+    #[test]
+    fn heads_up_all_in() {
+        let pots: Pots<char, u64> = split(&[('a', 50, true), ('b', 50, true)]);
+        assert_eq!(pots, vec![(100, vec!['a', 'b'])]);
+    }
+
+    #[test]
+    fn three_way_all_in_of_different_sizes() {
+        let pots: Pots<char, u64> = split(&[('a', 30, true), ('b', 100, true), ('c', 70, true)]);
+        assert_eq!(pots, vec![
+            (90, vec!['a', 'b', 'c']),
+            (80, vec!['b', 'c']),
+            (30, vec!['b']),
+        ]);
+        assert_eq!(total(&pots), 200);
+    }
+
+    #[test]
+    fn four_way_all_in_of_different_sizes() {
+        let pots: Pots<char, u64> = split(&[('a', 25, true), ('b', 200, true), ('c', 60, true), ('d', 200, true)]);
+        assert_eq!(pots, vec![
+            (100, vec!['a', 'b', 'c', 'd']),
+            (105, vec!['b', 'c', 'd']),
+            (280, vec!['b', 'd']),
+        ]);
+        assert_eq!(total(&pots), 485);
+    }
+
+    #[test]
+    fn four_way_with_equal_short_stacks() {
+        let pots: Pots<char, u64> = split(&[('a', 40, true), ('b', 40, true), ('c', 90, true), ('d', 150, true)]);
+        assert_eq!(pots, vec![
+            (160, vec!['a', 'b', 'c', 'd']),
+            (100, vec!['c', 'd']),
+            (60, vec!['d']),
+        ]);
+        assert_eq!(total(&pots), 320);
+    }
+
+    #[test]
+    fn folded_chips_stay_in_pots() {
+        let pots: Pots<char, u64> = split(&[('a', 30, true), ('b', 50, false), ('c', 100, true)]);
+        assert_eq!(pots, vec![
+            (90, vec!['a', 'c']),
+            (90, vec!['c']),
+        ]);
+
+        let pots: Pots<char, u64> = split(&[('a', 20, true), ('b', 80, false), ('c', 40, true)]);
+        assert_eq!(pots, vec![
+            (60, vec!['a', 'c']),
+            (80, vec!['c']),
+        ]);
+        assert_eq!(total(&pots), 140);
+    }
+
+    #[test]
+    fn pot_is_shared_with_odd_chips() {
+        assert_eq!(share(100u64, &['a', 'b']), vec![('a', 50), ('b', 50)]);
+        assert_eq!(share(101u64, &['a', 'b']), vec![('a', 51), ('b', 50)]);
+        assert_eq!(share(11u64, &['a', 'b', 'c']), vec![('a', 4), ('b', 4), ('c', 3)]);
+        assert_eq!(share(7u64, &['a']), vec![('a', 7)]);
+        assert_eq!(share(7u64, &[] as &[char]), vec![]);
+    }
+
+    #[test]
+    fn rake_is_taken_from_pots_up_to_the_cap() {
+        let mut pots: Pots<char, u64> = vec![(200, vec!['a', 'b'])];
+        assert_eq!(rake(&mut pots, 5, 30), 10);
+        assert_eq!(pots, vec![(190, vec!['a', 'b'])]);
+
+        let mut pots: Pots<char, u64> = vec![(1000, vec!['a', 'b', 'c']), (400, vec!['b', 'c'])];
+        assert_eq!(rake(&mut pots, 5, 60), 60);
+        assert_eq!(pots, vec![(950, vec!['a', 'b', 'c']), (390, vec!['b', 'c'])]);
+
+        //nothing is taken from tiny pots
+        let mut pots: Pots<char, u64> = vec![(19, vec!['a', 'b'])];
+        assert_eq!(rake(&mut pots, 5, 30), 0);
+        assert_eq!(total(&pots), 19);
+
+        let mut pots: Pots<char, u64> = vec![(200, vec!['a', 'b'])];
+        assert_eq!(rake(&mut pots, 0, 30), 0);
+        assert_eq!(total(&pots), 200);
+    }
+}
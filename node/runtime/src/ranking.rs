@@ -31,6 +31,16 @@ pub struct Combination {
     rank: Rank,
 }
 
+impl RankedHand {
+    pub fn rank(&self) -> Rank {
+        self.combination.rank
+    }
+
+    pub fn cards(&self) -> Vec<&Card> {
+        self.cards.iter().collect()
+    }
+}
+
 pub fn classify(hand: &[Card]) -> Combination {
     debug_assert!(hand.len() == 5);
     let is_flush = hand.iter().all(|c| c.suit == hand[0].suit);
@@ -0,0 +1,326 @@
+///Zero-knowledge proof of a correct shuffle of the encrypted deck
+
+///A participant proves that the deck he submits is the previous deck,
+///encrypted with the same secret exponent and permuted: `output[π(i)] = input[i]^e`,
+///without revealing neither `e` nor `π`. This is the cut-and-choose protocol,
+///made non-interactive with the Fiat–Shamir heuristic. For every round the prover
+///publishes an intermediate deck `deck[ρ(i)] = input[i]^r`, then the challenge bit of the round
+///requires to open either the link between the input and the intermediate deck (`r` and `ρ`),
+///or the link between the intermediate deck and the output (`e / r` and `π ∘ ρ^(-1)`).
+///A cheater can't answer both questions, so he is caught with probability `1 - 2^(-ROUNDS)`;
+///since the challenge is derived from the input, he has only the action timeout to search
+///for a suitable challenge.
+
+///Every link is checked with only one exponentiation by random small weights,
+///which are derived from the whole transcript too. Such check holds only up to the sign
+///of elements, but all decks consist of quadratic residues, and `-1` is not one of them.
+
+///Verification of all rounds takes about a second, so the runtime only checks the sizes
+///of a proof; clients verify it, and a round which fails can be challenged on chain,
+///then only this round is verified.
+
+use crate::commutative;
+
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+use rstd::ops::Range;
+use runtime_io::blake2_256;
+
+pub const ROUNDS: usize = 48;
+
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Proof {
+    ///Intermediate decks, one for every round
+    pub decks: Vec<Vec<u8>>,
+    ///Answers to the challenge, one for every round
+    pub openings: Vec<Opening>,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Opening {
+    ///`deck[permutation[i]] = input[i]^exponent`
+    Input { exponent: Vec<u8>, permutation: Vec<u8> },
+    ///`output[permutation[i]] = deck[i]^exponent`
+    Output { exponent: Vec<u8>, permutation: Vec<u8> },
+}
+
+///Challenge bits for every round, `true` requires to open the link with the output;
+///they depend on everything the prover has committed to
+pub fn challenge(input: &[u8], output: &[u8], decks: &[Vec<u8>]) -> Vec<bool> {
+    let hash = (input, output, decks).using_encoded(blake2_256);
+    (0..decks.len())
+        .map(|i| (hash[i / 8 % 32] >> (i % 8)) & 1 == 1)
+        .collect()
+}
+
+///Verifies all rounds of the proof, clients do it before they lock the deck
+pub fn verify(input: &[u8], output: &[u8], proof: &Proof) -> bool {
+    verify_rounds(input, output, proof, ROUNDS)
+}
+
+///Verifies the proof with another number of rounds, for tests of provers
+pub fn verify_rounds(input: &[u8], output: &[u8], proof: &Proof, rounds: usize) -> bool {
+    proof.decks.len() == rounds && check(input, output, proof)
+}
+
+///Verifies one round of the proof, the one which is challenged
+pub fn verify_round(input: &[u8], output: &[u8], proof: &Proof, round: usize) -> bool {
+    round < proof.decks.len() && check_rounds(input, output, proof, round..round + 1)
+}
+
+///The proof has `ROUNDS` rounds, and every round is of the size of a deck of `size` cards;
+///it bounds the size of the proof, which is checked before anything is stored
+pub fn is_well_formed(proof: &Proof, size: usize) -> bool {
+    proof.decks.len() == ROUNDS && proof.openings.len() == ROUNDS
+        && proof.decks.iter().all(|deck| deck.len() == size * commutative::ELEMENT_SIZE)
+        && proof.openings.iter().all(|opening| match opening {
+            Opening::Input { exponent, permutation } | Opening::Output { exponent, permutation } =>
+                exponent.len() == commutative::ELEMENT_SIZE && permutation.len() == size,
+        })
+}
+
+///Verifies the proof with any number of rounds
+fn check(input: &[u8], output: &[u8], proof: &Proof) -> bool {
+    check_rounds(input, output, proof, 0..proof.decks.len())
+}
+
+fn check_rounds(input: &[u8], output: &[u8], proof: &Proof, rounds: Range<usize>) -> bool {
+    let size = input.len() / commutative::ELEMENT_SIZE;
+    if input.len() % commutative::ELEMENT_SIZE != 0 || !commutative::is_deck(output, size) {
+        return false;
+    }
+    if proof.openings.len() != proof.decks.len() || proof.decks.len() > 256 {
+        return false;
+    }
+
+    let bits = challenge(input, output, &proof.decks[..]);
+    let weights = weights(input, output, proof, size);
+
+    let input: Vec<&[u8]> = input.chunks(commutative::ELEMENT_SIZE).collect();
+    let output: Vec<&[u8]> = output.chunks(commutative::ELEMENT_SIZE).collect();
+
+    rounds.into_iter()
+        .all(|round| {
+            let (deck, opening, bit) = (&proof.decks[round], &proof.openings[round], bits[round]);
+            if deck.len() != size * commutative::ELEMENT_SIZE {
+                return false;
+            }
+            let deck: Vec<&[u8]> = deck.chunks(commutative::ELEMENT_SIZE).collect();
+
+            match (opening, bit) {
+                (Opening::Input { exponent, permutation }, false) =>
+                    link(&input[..], &deck[..], &exponent[..], &permutation[..], &weights[..]),
+                (Opening::Output { exponent, permutation }, true) =>
+                    link(&deck[..], &output[..], &exponent[..], &permutation[..], &weights[..]),
+
+                _ => false
+            }
+        })
+}
+
+///Checks that `to[permutation[i]] = ±from[i]^exponent` for every `i`
+fn link(from: &[&[u8]], to: &[&[u8]], exponent: &[u8], permutation: &[u8], weights: &[u64]) -> bool {
+    if !is_permutation(permutation, from.len()) {
+        return false;
+    }
+
+    let permuted: Vec<&[u8]> = permutation.iter()
+        .map(|&j| to[j as usize])
+        .collect();
+
+    let source = match commutative::combine(from, weights) {
+        Ok(source) => source,
+        Err(_) => return false
+    };
+    let target = match commutative::combine(&permuted[..], weights) {
+        Ok(target) => target,
+        Err(_) => return false
+    };
+
+    match commutative::encrypt(&source[..], exponent) {
+        Ok(expected) => commutative::equal_up_to_sign(&target[..], &expected[..]),
+        Err(_) => false
+    }
+}
+
+fn is_permutation(permutation: &[u8], size: usize) -> bool {
+    let mut seen = vec![false; size];
+    permutation.len() == size && permutation.iter().all(|&j| {
+        let j = j as usize;
+        if j >= size || seen[j] {
+            false
+        } else {
+            seen[j] = true;
+            true
+        }
+    })
+}
+
+///Weights depend on the whole transcript: the input, the output, intermediate decks
+///and openings, so the prover can't choose any of them after the weights
+fn weights(input: &[u8], output: &[u8], proof: &Proof, size: usize) -> Vec<u64> {
+    let seed = (input, output, proof).using_encoded(blake2_256);
+    (0..size as u32)
+        .map(|i| {
+            let hash = (seed, i).using_encoded(blake2_256);
+            hash[..8].iter().fold(0, |weight, &byte| (weight << 8) | byte as u64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{spades, hearts, clubs, diamonds, A, K};
+
+    const SIZE: usize = 4;
+
+    fn exponent(seed: u64) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..commutative::ELEMENT_SIZE as u64)
+            .map(|i| (seed.wrapping_mul(i + 1).wrapping_add(i) >> 16) as u8)
+            .collect();
+        bytes[0] = 0x3f;
+        bytes
+    }
+
+    fn permute(data: &[u8], permutation: &[u8]) -> Vec<u8> {
+        let size = commutative::ELEMENT_SIZE;
+        let mut result = vec![0; data.len()];
+        for (i, chunk) in data.chunks(size).enumerate() {
+            let j = permutation[i] as usize;
+            result[j * size..(j + 1) * size].copy_from_slice(chunk);
+        }
+        result
+    }
+
+    fn input() -> Vec<u8> {
+        commutative::encode(vec![&spades(A), &hearts(7), &clubs(K), &diamonds(2)])
+    }
+
+    fn prove(input: &[u8], key: &[u8], permutation: &[u8], rounds: usize) -> (Vec<u8>, Proof) {
+        let output = permute(&commutative::encrypt(input, key).unwrap(), permutation);
+
+        let masks: Vec<(Vec<u8>, Vec<u8>)> = (0..rounds as u64)
+            .map(|j| {
+                let mask = exponent(j * 7919 + 3);
+                let order: Vec<u8> = (0..SIZE as u64).map(|i| ((i + j) % SIZE as u64) as u8).collect();
+                (mask, order)
+            })
+            .collect();
+
+        let decks: Vec<Vec<u8>> = masks.iter()
+            .map(|(mask, order)| permute(&commutative::encrypt(input, mask).unwrap(), order))
+            .collect();
+
+        let bits = challenge(input, &output, &decks);
+        let openings = masks.into_iter()
+            .zip(bits.into_iter())
+            .map(|((mask, order), bit)| if bit {
+                let mut rest = vec![0; SIZE];
+                for i in 0..SIZE {
+                    rest[order[i] as usize] = permutation[i];
+                }
+                let exponent = commutative::multiply(key, &commutative::inverse(&mask).unwrap()).unwrap();
+                Opening::Output { exponent, permutation: rest }
+            } else {
+                Opening::Input { exponent: mask, permutation: order }
+            })
+            .collect();
+
+        (output, Proof { decks, openings })
+    }
+
+    #[test]
+    fn honest_shuffle_is_accepted() {
+        let input = input();
+        let (output, proof) = prove(&input, &exponent(42), &[2, 0, 3, 1], 8);
+
+        let bits = challenge(&input, &output, &proof.decks);
+        assert!(bits.iter().any(|&bit| bit) && bits.iter().any(|&bit| !bit));
+
+        assert!(check(&input, &output, &proof));
+        assert!(!verify(&input, &output, &proof));
+    }
+
+    #[test]
+    fn replaced_card_is_rejected() {
+        let input = input();
+        let (output, proof) = prove(&input, &exponent(42), &[2, 0, 3, 1], 8);
+
+        let size = commutative::ELEMENT_SIZE;
+        let other = commutative::encrypt(&input[..size], &exponent(43)).unwrap();
+        let mut forged = output.clone();
+        forged[..size].copy_from_slice(&other);
+        assert!(!check(&input, &forged, &proof));
+
+        let mut duplicated = output.clone();
+        duplicated[..size].copy_from_slice(&output[size..2 * size]);
+        assert!(!check(&input, &duplicated, &proof));
+    }
+
+    #[test]
+    fn wrong_openings_are_rejected() {
+        let input = input();
+        let (output, proof) = prove(&input, &exponent(42), &[2, 0, 3, 1], 8);
+
+        let mut swapped = proof.clone();
+        swapped.openings.swap(0, 1);
+        assert!(!check(&input, &output, &swapped));
+
+        let mut truncated = proof.clone();
+        truncated.openings.pop();
+        assert!(!check(&input, &output, &truncated));
+
+        let mut broken = proof.clone();
+        broken.openings[0] = match broken.openings[0].clone() {
+            Opening::Input { exponent, .. } => Opening::Input { exponent, permutation: vec![0, 0, 1, 2] },
+            Opening::Output { exponent, .. } => Opening::Output { exponent, permutation: vec![0, 0, 1, 2] },
+        };
+        assert!(!check(&input, &output, &broken));
+    }
+
+    #[test]
+    fn failed_round_is_found() {
+        let input = input();
+        let (output, proof) = prove(&input, &exponent(42), &[2, 0, 3, 1], 8);
+        assert!((0..8).all(|round| verify_round(&input, &output, &proof, round)));
+        assert!(!verify_round(&input, &output, &proof, 8));
+
+        let mut broken = proof.clone();
+        broken.openings[3] = match broken.openings[3].clone() {
+            Opening::Input { permutation, .. } => Opening::Input { exponent: exponent(43), permutation },
+            Opening::Output { permutation, .. } => Opening::Output { exponent: exponent(43), permutation },
+        };
+        assert!(!verify_round(&input, &output, &broken, 3));
+        assert!(verify_round(&input, &output, &broken, 2));
+    }
+
+    #[test]
+    fn sizes_of_proofs_are_bounded() {
+        let opening = Opening::Input { exponent: exponent(42), permutation: vec![0, 1, 2, 3] };
+        let proof = Proof {
+            decks: vec![input(); ROUNDS],
+            openings: vec![opening; ROUNDS],
+        };
+        assert!(is_well_formed(&proof, SIZE));
+        assert!(!is_well_formed(&proof, SIZE + 1));
+
+        let mut short = proof.clone();
+        short.decks.pop();
+        assert!(!is_well_formed(&short, SIZE));
+
+        let mut long = proof.clone();
+        long.openings[0] = Opening::Output { exponent: exponent(42), permutation: vec![0, 1, 2, 3, 4] };
+        assert!(!is_well_formed(&long, SIZE));
+    }
+
+    #[test]
+    fn permutations_are_validated() {
+        assert!(is_permutation(&[2, 0, 3, 1], 4));
+        assert!(!is_permutation(&[2, 0, 3], 4));
+        assert!(!is_permutation(&[2, 0, 3, 3], 4));
+        assert!(!is_permutation(&[2, 0, 3, 4], 4));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::error::PokerError;
+
+use parity_codec::{Encode, Decode};
+use rstd::prelude::*;
+use runtime_primitives::traits::{SimpleArithmetic, Zero};
+
+///Blinds and ante of one level of a tournament
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Level<Balance> {
+    pub small_blind: Balance,
+    pub big_blind: Balance,
+    pub ante: Balance,
+}
+
+///When blinds go to the next level
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Escalation {
+    ///Every number of blocks since the start
+    Blocks(u64),
+    ///Every number of played hands
+    Hands(u32),
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Escalation::Hands(10)
+    }
+}
+
+///Single-table tournament: it starts when all seats are taken
+///and lasts until one participant has all chips
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SitAndGo<Balance> {
+    ///Amount every entrant pays into the prize pool
+    pub buy_in: Balance,
+    ///Chips every entrant starts with, they don't depend on the buy-in
+    pub starting_stack: Balance,
+    ///Levels of blinds, the last one stays until the end
+    pub levels: Vec<Level<Balance>>,
+    pub escalation: Escalation,
+    ///Percentages of the prize pool for the first place, the second one and so on
+    pub payouts: Vec<u8>,
+}
+
+impl<Balance> SitAndGo<Balance> where Balance: SimpleArithmetic + Copy {
+    pub fn validate(&self, seats: u32) -> Result<(), PokerError> {
+        if self.buy_in == Zero::zero() || self.starting_stack == Zero::zero() {
+            return Err(PokerError::InvalidBuyIn);
+        }
+        if self.levels.is_empty() {
+            return Err(PokerError::NoLevels);
+        }
+        let valid_levels = self.levels.iter()
+            .all(|level| level.small_blind > Zero::zero()
+                && level.small_blind <= level.big_blind
+                && level.ante <= level.big_blind);
+        if !valid_levels {
+            return Err(PokerError::InvalidLevel);
+        }
+        if self.levels[0].big_blind > self.starting_stack {
+            return Err(PokerError::StackBelowBigBlind);
+        }
+        match self.escalation {
+            Escalation::Blocks(0) | Escalation::Hands(0) => return Err(PokerError::InvalidEscalation),
+            _ => {}
+        }
+        if self.payouts.is_empty() || self.payouts.len() > seats as usize {
+            return Err(PokerError::InvalidPlaces);
+        }
+        if self.payouts.iter().map(|&p| p as u32).sum::<u32>() != 100 {
+            return Err(PokerError::InvalidPayouts);
+        }
+        Ok(())
+    }
+
+    ///Index of the current level of blinds
+    pub fn level_at(&self, blocks: u64, hands: u32) -> usize {
+        let passed = match self.escalation {
+            Escalation::Blocks(period) => (blocks / period) as usize,
+            Escalation::Hands(period) => (hands / period) as usize,
+        };
+        rstd::cmp::min(passed, self.levels.len() - 1)
+    }
+
+    ///Prizes for finishing places; chips left after rounding go to the winner
+    pub fn prizes(&self, pool: Balance) -> Vec<Balance> {
+        let hundred = Balance::sa(100);
+        let mut prizes: Vec<Balance> = self.payouts.iter()
+            .map(|&percent| pool * Balance::sa(percent as u64) / hundred)
+            .collect();
+
+        let paid = prizes.iter().fold(Zero::zero(), |sum: Balance, &prize| sum + prize);
+        prizes[0] = prizes[0] + (pool - paid);
+        prizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(small_blind: u64, big_blind: u64, ante: u64) -> Level<u64> {
+        Level { small_blind, big_blind, ante }
+    }
+
+    fn sit_and_go() -> SitAndGo<u64> {
+        SitAndGo {
+            buy_in: 100,
+            starting_stack: 1500,
+            levels: vec![level(10, 20, 0), level(15, 30, 0), level(25, 50, 5)],
+            escalation: Escalation::Hands(10),
+            payouts: vec![50, 30, 20],
+        }
+    }
+
+    #[test]
+    fn valid_tournament_is_accepted() {
+        assert_eq!(sit_and_go().validate(6), Ok(()));
+        assert_eq!(SitAndGo { payouts: vec![100], ..sit_and_go() }.validate(2), Ok(()));
+        assert_eq!(SitAndGo { escalation: Escalation::Blocks(60), ..sit_and_go() }.validate(3), Ok(()));
+    }
+
+    #[test]
+    fn invalid_tournament_is_rejected() {
+        assert!(SitAndGo { buy_in: 0, ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { starting_stack: 0, ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { starting_stack: 10, ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { levels: vec![], ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { levels: vec![level(0, 20, 0)], ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { levels: vec![level(30, 20, 0)], ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { levels: vec![level(10, 20, 30)], ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { escalation: Escalation::Hands(0), ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { escalation: Escalation::Blocks(0), ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { payouts: vec![], ..sit_and_go() }.validate(6).is_err());
+        assert!(SitAndGo { payouts: vec![50, 40], ..sit_and_go() }.validate(6).is_err());
+        assert!(sit_and_go().validate(2).is_err());
+    }
+
+    #[test]
+    fn blinds_escalate() {
+        let by_hands = sit_and_go();
+        assert_eq!(by_hands.level_at(1000, 0), 0);
+        assert_eq!(by_hands.level_at(0, 9), 0);
+        assert_eq!(by_hands.level_at(0, 10), 1);
+        assert_eq!(by_hands.level_at(0, 25), 2);
+        assert_eq!(by_hands.level_at(0, 1000), 2);
+
+        let by_blocks = SitAndGo { escalation: Escalation::Blocks(60), ..sit_and_go() };
+        assert_eq!(by_blocks.level_at(59, 100), 0);
+        assert_eq!(by_blocks.level_at(60, 0), 1);
+        assert_eq!(by_blocks.level_at(6000, 0), 2);
+    }
+
+    #[test]
+    fn prizes_sum_up_to_the_pool() {
+        assert_eq!(sit_and_go().prizes(600), vec![300, 180, 120]);
+        assert_eq!(sit_and_go().prizes(301), vec![151, 90, 60]);
+        assert_eq!(SitAndGo { payouts: vec![100], ..sit_and_go() }.prizes(7), vec![7]);
+        assert_eq!(SitAndGo { payouts: vec![65, 35], ..sit_and_go() }.prizes(3), vec![2, 1]);
+    }
+}
@@ -7,4 +7,7 @@ pub const TURN:     StageId = 3;
 pub const RIVER:    StageId = 4;
 
 ///This stage is a fake one, it doesn't go to UI
-pub const SHOWDOWN: StageId = 5;
\ No newline at end of file
+pub const SHOWDOWN: StageId = 5;
+///This stage is a fake one too, participants shuffle
+///and lock the deck before cards are dealt
+pub const SHUFFLE: StageId = 6;
\ No newline at end of file
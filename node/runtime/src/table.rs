@@ -0,0 +1,115 @@
+use crate::betting::BettingStructure;
+use crate::error::PokerError;
+
+use parity_codec::{Encode, Decode};
+use runtime_primitives::traits::{SimpleArithmetic, Zero};
+
+pub const MAX_SEATS: u32 = 9;
+
+///Rules of a table chosen by its host
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct TableConfig<Balance> {
+    pub small_blind: Balance,
+    pub big_blind: Balance,
+    ///Forced contribution of every participant to the pot,
+    ///it doesn't count as a bet
+    pub ante: Balance,
+    pub min_buy_in: Balance,
+    pub max_buy_in: Balance,
+    ///Maximal number of participants at the table
+    pub seats: u32,
+    ///Number of blocks a participant has to act or to submit his keys
+    pub action_timeout: u64,
+    pub betting: BettingStructure<Balance>,
+}
+
+impl<Balance> TableConfig<Balance> where Balance: SimpleArithmetic + Copy {
+    pub fn validate(&self, max_timeout: u64) -> Result<(), PokerError> {
+        if self.small_blind == Zero::zero() || self.small_blind > self.big_blind {
+            return Err(PokerError::InvalidBlinds);
+        }
+        if self.ante > self.big_blind {
+            return Err(PokerError::AnteAboveBigBlind);
+        }
+        if self.min_buy_in < self.big_blind {
+            return Err(PokerError::BuyInBelowBigBlind);
+        }
+        if self.max_buy_in < self.min_buy_in {
+            return Err(PokerError::InvalidBuyInRange);
+        }
+        if self.seats < 2 || self.seats > MAX_SEATS {
+            return Err(PokerError::InvalidSeats);
+        }
+        if self.action_timeout == 0 || self.action_timeout > max_timeout {
+            return Err(PokerError::InvalidTimeout);
+        }
+        if let BettingStructure::FixedLimit { small_bet, big_bet, max_raises } = self.betting {
+            if small_bet == Zero::zero() || big_bet < small_bet || max_raises == 0 {
+                return Err(PokerError::InvalidFixedLimit);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn allows_buy_in(&self, amount: Balance) -> bool {
+        amount >= self.min_buy_in && amount <= self.max_buy_in
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TableConfig<u64> {
+        TableConfig {
+            small_blind: 5,
+            big_blind: 10,
+            ante: 1,
+            min_buy_in: 200,
+            max_buy_in: 1000,
+            seats: 6,
+            action_timeout: 18,
+            betting: BettingStructure::NoLimit,
+        }
+    }
+
+    #[test]
+    fn valid_config_is_accepted() {
+        assert_eq!(config().validate(18), Ok(()));
+        assert_eq!(TableConfig { small_blind: 10, ante: 0, ..config() }.validate(18), Ok(()));
+        assert_eq!(TableConfig { seats: 2, betting: BettingStructure::PotLimit, ..config() }.validate(18), Ok(()));
+
+        let fixed = BettingStructure::FixedLimit { small_bet: 10, big_bet: 20, max_raises: 4 };
+        assert_eq!(TableConfig { betting: fixed, ..config() }.validate(18), Ok(()));
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        assert!(TableConfig { small_blind: 0, ..config() }.validate(18).is_err());
+        assert!(TableConfig { small_blind: 11, ..config() }.validate(18).is_err());
+        assert!(TableConfig { ante: 11, ..config() }.validate(18).is_err());
+        assert!(TableConfig { min_buy_in: 9, ..config() }.validate(18).is_err());
+        assert!(TableConfig { max_buy_in: 199, ..config() }.validate(18).is_err());
+        assert!(TableConfig { seats: 1, ..config() }.validate(18).is_err());
+        assert!(TableConfig { seats: 10, ..config() }.validate(18).is_err());
+        assert!(TableConfig { action_timeout: 0, ..config() }.validate(18).is_err());
+        assert!(config().validate(17).is_err());
+
+        let fixed = |small_bet, big_bet, max_raises| TableConfig {
+            betting: BettingStructure::FixedLimit { small_bet, big_bet, max_raises },
+            ..config()
+        };
+        assert!(fixed(0, 20, 4).validate(18).is_err());
+        assert!(fixed(10, 5, 4).validate(18).is_err());
+        assert!(fixed(10, 20, 0).validate(18).is_err());
+    }
+
+    #[test]
+    fn buy_in_is_bounded() {
+        assert!(config().allows_buy_in(200));
+        assert!(config().allows_buy_in(1000));
+        assert!(!config().allows_buy_in(199));
+        assert!(!config().allows_buy_in(1001));
+    }
+}
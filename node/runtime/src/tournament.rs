@@ -0,0 +1,581 @@
+use crate::{poker, table, sit_and_go, balancing};
+use crate::poker::GameId;
+use crate::error::PokerError;
+
+use support::{decl_module, decl_storage, decl_event, StorageValue, StorageMap};
+use support::traits::ReservableCurrency;
+use support::dispatch::Result;
+use system::ensure_signed;
+
+use rstd::prelude::*;
+use core::debug_assert;
+
+use runtime_primitives::traits::{As, Zero};
+
+///Multi-table tournament: entrants are seated at several tables of the poker module,
+///which are balanced and broken as entrants are eliminated, until one has all chips
+pub trait Trait: poker::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+pub type TournamentId = u64;
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Tournament {
+		///Identifier which will be assigned to the next created tournament
+		NextTournamentId get(next_tournament_id): TournamentId;
+
+		///Account which has created the tournament, only it can start the tournament
+		Host get(host): map TournamentId => Option<T::AccountId>;
+		///Seats, timeout and betting structure of every table
+		TableRules get(table_rules): map TournamentId => table::TableConfig<T::Balance>;
+		///Buy-in, starting stack, levels of blinds and payouts
+		Schedule get(schedule): map TournamentId => Option<sit_and_go::SitAndGo<T::Balance>>;
+		MaxEntrants get(max_entrants): map TournamentId => u32;
+
+		///Registered accounts, their buy-ins stay reserved until the end
+		Entrants get(entrants): map TournamentId => Vec<T::AccountId>;
+		///Block at which the tournament was started
+		Started get(started): map TournamentId => Option<T::BlockNumber>;
+		PrizePool get(prize_pool): map TournamentId => T::Balance;
+
+		///Tables of the poker module which are still in play
+		Tables get(tables): map TournamentId => Vec<GameId>;
+		TableOf get(table_of): map GameId => Option<TournamentId>;
+		///Index of the level of blinds played at the table
+		TableLevel get(table_level): map GameId => u32;
+
+		///Number of entrants who still have chips
+		Remaining get(remaining): map TournamentId => u32;
+		///Entrants who have lost all chips, in the order of elimination
+		Eliminated get(eliminated): map TournamentId => Vec<T::AccountId>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event<T>() = default;
+
+		fn create_tournament(origin,
+				config: table::TableConfig<T::Balance>,
+				schedule: sit_and_go::SitAndGo<T::Balance>,
+				max_entrants: u32) -> Result {
+			let who = ensure_signed(origin)?;
+
+			if max_entrants < 2 {
+				return Self::error(who, PokerError::TournamentTooSmall);
+			}
+			if let Err(error) = schedule.validate(max_entrants) {
+				return Self::error(who, error);
+			}
+			//tables play with different pace, so only time is common for all of them
+			if let sit_and_go::Escalation::Hands(_) = schedule.escalation {
+				return Self::error(who, PokerError::EscalationByHands);
+			}
+
+			let first = &schedule.levels[0];
+			let config = table::TableConfig {
+				small_blind: first.small_blind,
+				big_blind: first.big_blind,
+				ante: first.ante,
+				min_buy_in: schedule.starting_stack,
+				max_buy_in: schedule.starting_stack,
+				..config
+			};
+			if let Err(error) = config.validate(<T as poker::Trait>::ACTION_TIMEOUT) {
+				return Self::error(who, error);
+			}
+
+			let tournament = Self::next_tournament_id();
+			<NextTournamentId<T>>::put(tournament + 1);
+
+			<Host<T>>::insert(tournament, &who);
+			<TableRules<T>>::insert(tournament, config);
+			<Schedule<T>>::insert(tournament, schedule);
+			<MaxEntrants<T>>::insert(tournament, max_entrants);
+			Self::deposit_event(RawEvent::TournamentCreated(tournament, who));
+			Ok(())
+		}
+
+		fn register(origin, tournament: TournamentId) -> Result {
+			let who = ensure_signed(origin)?;
+
+			let schedule = match Self::schedule(tournament) {
+				Some(schedule) => schedule,
+				None => return Self::error(who, PokerError::NoSuchTournament),
+			};
+			if Self::started(tournament).is_some() {
+				return Self::error(who, PokerError::RegistrationClosed);
+			}
+			let entrants = Self::entrants(tournament);
+			if entrants.contains(&who) {
+				return Self::error(who, PokerError::AlreadyRegistered);
+			}
+			if entrants.len() >= Self::max_entrants(tournament) as usize {
+				return Self::error(who, PokerError::TournamentFull);
+			}
+
+			if <balances::Module<T> as ReservableCurrency<_>>::reserve(&who, schedule.buy_in).is_err() {
+				return Self::error(who, PokerError::InsufficientFunds);
+			}
+			<Entrants<T>>::mutate(tournament, |entrants| entrants.push(who.clone()));
+			<PrizePool<T>>::mutate(tournament, |pool| *pool += schedule.buy_in);
+			Self::deposit_event(RawEvent::Registered(tournament, who));
+			Ok(())
+		}
+
+		fn unregister(origin, tournament: TournamentId) -> Result {
+			let who = ensure_signed(origin)?;
+
+			if Self::started(tournament).is_some() {
+				return Self::error(who, PokerError::TournamentAlreadyStarted);
+			}
+			let mut entrants = Self::entrants(tournament);
+			let index = match entrants.iter().position(|e| e == &who) {
+				Some(index) => index,
+				None => return Self::error(who, PokerError::NotRegistered),
+			};
+			let buy_in = Self::schedule(tournament).map(|s| s.buy_in).unwrap_or(Zero::zero());
+
+			entrants.remove(index);
+			<Entrants<T>>::insert(tournament, entrants);
+			<PrizePool<T>>::mutate(tournament, |pool| *pool -= buy_in);
+			<balances::Module<T> as ReservableCurrency<_>>::unreserve(&who, buy_in);
+			Self::deposit_event(RawEvent::Unregistered(tournament, who));
+			Ok(())
+		}
+
+		///Closes registration and seats entrants at as few tables as possible,
+		///in the order of registration one table after another
+		fn start_tournament(origin, tournament: TournamentId) -> Result {
+			let who = ensure_signed(origin)?;
+
+			if Self::host(tournament).as_ref() != Some(&who) {
+				return Self::error(who, PokerError::NotHost);
+			}
+			if Self::started(tournament).is_some() {
+				return Self::error(who, PokerError::TournamentAlreadyStarted);
+			}
+			let entrants = Self::entrants(tournament);
+			if entrants.len() < 2 {
+				return Self::error(who, PokerError::NotEnoughEntrants);
+			}
+			let schedule = match Self::schedule(tournament) {
+				Some(schedule) => schedule,
+				None => return Self::error(who, PokerError::NoSuchTournament),
+			};
+
+			//everything is checked before storage changes, so seating can't fail
+			//halfway and leave the tournament started with entrants missing
+			let config = Self::table_rules(tournament);
+			if config.seats < 2 {
+				return Self::error(who, PokerError::InvalidSeats);
+			}
+			let count = balancing::tables_needed(entrants.len() as u32, config.seats);
+
+			let tables: Vec<GameId> = (0..count)
+				.map(|_| <poker::Module<T>>::open_table(config.clone()))
+				.collect();
+			for &game in tables.iter() {
+				<TableOf<T>>::insert(game, tournament);
+			}
+
+			<Started<T>>::insert(tournament, <system::Module<T>>::block_number());
+			<Remaining<T>>::insert(tournament, entrants.len() as u32);
+			<Tables<T>>::insert(tournament, &tables);
+			Self::deposit_event(RawEvent::TournamentStarted(tournament, Self::prize_pool(tournament), count));
+
+			for (i, entrant) in entrants.into_iter().enumerate() {
+				//registration keeps entrants unique and they go round the tables,
+				//so no table gets more than its seats
+				let game = tables[i % tables.len()];
+				let seated = <poker::Module<T>>::seat(game, entrant.clone(), schedule.starting_stack);
+				debug_assert!(seated.is_ok());
+				Self::deposit_event(RawEvent::PlayerSeated(tournament, entrant, game));
+			}
+			Ok(())
+		}
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId,
+							Balance = <T as balances::Trait>::Balance {
+		TournamentCreated(TournamentId, AccountId),
+		Registered(TournamentId, AccountId),
+		Unregistered(TournamentId, AccountId),
+		///Prize pool and number of tables
+		TournamentStarted(TournamentId, Balance, u32),
+
+		///Entrant has taken a seat at the table
+		PlayerSeated(TournamentId, AccountId, GameId),
+		///Entrant has been moved from the first table to the second one
+		PlayerMoved(TournamentId, AccountId, GameId, GameId),
+		///All participants of the table have been moved to other tables
+		TableBroken(TournamentId, GameId),
+
+		///Entrant has lost all chips and finished at the place
+		Eliminated(TournamentId, AccountId, u32),
+		PrizeAwarded(TournamentId, AccountId, u32, Balance),
+		TournamentFinished(TournamentId, AccountId),
+
+		///Transaction of the account has failed
+		Error(AccountId, PokerError),
+	}
+);
+
+impl<T: Trait> poker::OnTableChange<T::AccountId> for Module<T> {
+	fn on_eliminated(game: GameId, who: &T::AccountId) {
+		let tournament = match Self::table_of(game) {
+			Some(tournament) => tournament,
+			None => return,
+		};
+
+		let place = Self::remaining(tournament);
+		if place == 0 {
+			return;
+		}
+		<Remaining<T>>::insert(tournament, place - 1);
+		<Eliminated<T>>::mutate(tournament, |eliminated| eliminated.push(who.clone()));
+		Self::deposit_event(RawEvent::Eliminated(tournament, who.clone(), place));
+
+		if <poker::Module<T>>::seats(game).is_empty() {
+			Self::drop_table(tournament, game);
+		}
+		if place == 2 {
+			Self::finish(tournament);
+		}
+	}
+
+	fn on_hand_finished(game: GameId) {
+		let tournament = match Self::table_of(game) {
+			Some(tournament) => tournament,
+			None => return,
+		};
+
+		Self::update_blinds(tournament, game);
+		Self::balance(tournament, game);
+	}
+}
+
+impl<T: Trait> Module<T> {
+	///Blinds change only between hands
+	fn update_blinds(tournament: TournamentId, game: GameId) {
+		let (schedule, start) = match (Self::schedule(tournament), Self::started(tournament)) {
+			(Some(schedule), Some(start)) => (schedule, start),
+			_ => return,
+		};
+
+		let blocks = (<system::Module<T>>::block_number() - start).as_();
+		let level = schedule.level_at(blocks, 0);
+		if level as u32 > Self::table_level(game) {
+			<TableLevel<T>>::insert(game, level as u32);
+			<poker::Module<T>>::set_blinds(game, &schedule.levels[level]);
+		}
+	}
+
+	///Moves participants from the table which has just finished a hand,
+	///the one who would post big blind next goes first
+	fn balance(tournament: TournamentId, game: GameId) {
+		let tables = Self::tables(tournament);
+		let current = match tables.iter().position(|&t| t == game) {
+			Some(current) => current,
+			None => return,
+		};
+		let sizes: Vec<u32> = tables.iter()
+			.map(|&t| <poker::Module<T>>::seats(t).len() as u32)
+			.collect();
+		let seats = Self::table_rules(tournament).seats;
+
+		for target in balancing::moves(&sizes, current, seats).into_iter() {
+			let who = match <poker::Module<T>>::next_big_blind(game)
+				.or_else(|| <poker::Module<T>>::seats(game).into_iter().next()) {
+				Some(who) => who,
+				None => break,
+			};
+
+			let to = tables[target];
+			let moved = <poker::Module<T>>::unseat(game, &who)
+				.and_then(|chips| <poker::Module<T>>::seat(to, who.clone(), chips));
+			if moved.is_err() {
+				break;
+			}
+			Self::deposit_event(RawEvent::PlayerMoved(tournament, who, game, to));
+		}
+
+		if <poker::Module<T>>::seats(game).is_empty() {
+			Self::deposit_event(RawEvent::TableBroken(tournament, game));
+			Self::drop_table(tournament, game);
+		}
+	}
+
+	fn drop_table(tournament: TournamentId, game: GameId) {
+		<Tables<T>>::mutate(tournament, |tables| tables.retain(|&t| t != game));
+		<TableOf<T>>::remove(game);
+		<TableLevel<T>>::remove(game);
+	}
+
+	///Pays out the prize pool by places: the last entrant with chips is the first
+	fn finish(tournament: TournamentId) {
+		let schedule = match Self::schedule(tournament) {
+			Some(schedule) => schedule,
+			None => return,
+		};
+
+		let mut places: Vec<T::AccountId> = Self::tables(tournament).iter()
+			.flat_map(|&game| <poker::Module<T>>::seats(game).into_iter())
+			.collect();
+		places.extend(Self::eliminated(tournament).into_iter().rev());
+
+		let prizes = schedule.prizes(Self::prize_pool(tournament));
+		let prize = |place: usize| prizes.get(place).cloned().unwrap_or(Zero::zero());
+		Self::pay_out(&places, &prize, schedule.buy_in);
+
+		for (place, who) in places.iter().enumerate() {
+			if prize(place) > Zero::zero() {
+				Self::deposit_event(RawEvent::PrizeAwarded(tournament, who.clone(), place as u32 + 1, prize(place)));
+			}
+		}
+		if let Some(winner) = places.into_iter().next() {
+			Self::deposit_event(RawEvent::TournamentFinished(tournament, winner));
+		}
+
+		for game in Self::tables(tournament).into_iter() {
+			<poker::Module<T>>::close_table(game);
+			Self::drop_table(tournament, game);
+		}
+		Self::remove_tournament(tournament);
+	}
+
+	///Every entrant has the buy-in reserved; those who have won more receive
+	///the difference from reserves of those who have won less
+	fn pay_out<F>(places: &[T::AccountId], prize: &F, buy_in: T::Balance)
+		where F: Fn(usize) -> T::Balance {
+		let mut losses: Vec<(T::AccountId, T::Balance)> = places.iter()
+			.enumerate()
+			.filter(|&(place, _)| prize(place) < buy_in)
+			.map(|(place, who)| (who.clone(), buy_in - prize(place)))
+			.collect();
+
+		for (place, winner) in places.iter().enumerate() {
+			if prize(place) <= buy_in {
+				continue;
+			}
+			let mut gain = prize(place) - buy_in;
+			for (loser, loss) in losses.iter_mut() {
+				let amount = rstd::cmp::min(gain, *loss);
+				if amount == Zero::zero() {
+					continue;
+				}
+				let _ = <balances::Module<T> as ReservableCurrency<_>>::repatriate_reserved(loser, winner, amount);
+				*loss -= amount;
+				gain -= amount;
+			}
+		}
+
+		//repatriated funds are already free, what is left of buy-ins is released
+		for (place, who) in places.iter().enumerate() {
+			let left = rstd::cmp::min(prize(place), buy_in);
+			<balances::Module<T> as ReservableCurrency<_>>::unreserve(who, left);
+		}
+	}
+
+	fn remove_tournament(tournament: TournamentId) {
+		<Host<T>>::remove(tournament);
+		<TableRules<T>>::remove(tournament);
+		<Schedule<T>>::remove(tournament);
+		<MaxEntrants<T>>::remove(tournament);
+		<Entrants<T>>::remove(tournament);
+		<Started<T>>::remove(tournament);
+		<PrizePool<T>>::remove(tournament);
+		<Tables<T>>::remove(tournament);
+		<Remaining<T>>::remove(tournament);
+		<Eliminated<T>>::remove(tournament);
+	}
+
+	fn error(who: T::AccountId, error: PokerError) -> Result {
+		Self::deposit_event(RawEvent::Error(who, error));
+		Err(error.message())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok};
+	use support::dispatch::Dispatchable;
+	use runtime_primitives::BuildStorage;
+	use runtime_primitives::traits::{BlakeTwo256, IdentityLookup};
+	use runtime_primitives::testing::{Digest, DigestItem, Header};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type DustRemoval = ();
+		type TransferPayment = ();
+	}
+
+	impl poker::Trait for Test {
+		type Event = ();
+		const ACTION_TIMEOUT: u64 = 100;
+		type OnTableChange = Tournament;
+		const RAKE_PERCENT: u32 = 5;
+		const RAKE_CAP: u32 = 3;
+		type OnRake = ();
+		const CARD_PROOF_FEE: u64 = 1;
+		const MAX_PROOFS_PER_BLOCK: u32 = 4;
+		const HAND_RETENTION: u64 = 1000;
+	}
+
+	impl Trait for Test {
+		type Event = ();
+	}
+
+	type Tournament = Module<Test>;
+	type Poker = poker::Module<Test>;
+	type Balances = balances::Module<Test>;
+
+	const BALANCE: u64 = 1_000_000;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test> {
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit: 0,
+			transfer_fee: 0,
+			creation_fee: 0,
+			balances: (1..10).map(|who| (who, BALANCE)).collect(),
+			vesting: vec![],
+		}.build_storage().unwrap().0);
+		t.into()
+	}
+
+	///Tables of three seats, the buy-in of 100 is paid 70% to 30%
+	fn start(entrants: &[u64]) -> TournamentId {
+		let tournament = Tournament::next_tournament_id();
+		let config = table::TableConfig { seats: 3, action_timeout: 10, ..Default::default() };
+		let schedule = sit_and_go::SitAndGo {
+			buy_in: 100,
+			starting_stack: 1500,
+			levels: vec![sit_and_go::Level { small_blind: 10, big_blind: 20, ante: 0 }],
+			escalation: sit_and_go::Escalation::Blocks(100),
+			payouts: vec![70, 30],
+		};
+		assert_ok!(Tournament::create_tournament(Origin::signed(1), config, schedule, 9));
+		for &p in entrants.iter() {
+			assert_ok!(Tournament::register(Origin::signed(p), tournament));
+		}
+		assert_ok!(Tournament::start_tournament(Origin::signed(1), tournament));
+		tournament
+	}
+
+	///Dispatchables of the poker module are private, so it is called the way extrinsics are
+	fn leave(who: u64, game: GameId) {
+		assert_ok!(poker::Call::<Test>::leave_game_anyway(game).dispatch(Origin::signed(who)));
+	}
+
+	///The hand at the table is over, the tournament balances tables
+	fn finish_hand(game: GameId) {
+		<Tournament as poker::OnTableChange<u64>>::on_hand_finished(game);
+	}
+
+	#[test]
+	fn tables_are_balanced_and_broken_as_entrants_leave() {
+		with_externalities(&mut new_test_ext(), || {
+			let tournament = start(&[1, 2, 3, 4, 5, 6]);
+			let tables = Tournament::tables(tournament);
+			assert_eq!(tables.len(), 2);
+			let (a, b) = (tables[0], tables[1]);
+			assert_eq!(Poker::seats(a), vec![1, 3, 5]);
+			assert_eq!(Poker::seats(b), vec![2, 4, 6]);
+			assert_eq!(Poker::stacks((a, 1)), 1500);
+
+			leave(3, a);
+			leave(5, a);
+			assert_eq!(Tournament::remaining(tournament), 4);
+
+			//the next big blind of the bigger table moves to the smaller one
+			finish_hand(b);
+			assert_eq!(Poker::seats(a), vec![1, 6]);
+			assert_eq!(Poker::seats(b), vec![2, 4]);
+			assert_eq!(Poker::stacks((a, 6)), 1500);
+
+			//three entrants fit at one table, so the other one is broken
+			leave(4, b);
+			finish_hand(b);
+			assert_eq!(Poker::seats(a), vec![1, 6, 2]);
+			assert!(Poker::seats(b).is_empty());
+			assert_eq!(Tournament::tables(tournament), vec![a]);
+			assert_eq!(Tournament::table_of(b), None);
+
+			leave(6, a);
+			assert_eq!(Tournament::eliminated(tournament), vec![3, 5, 4, 6]);
+			leave(2, a);
+
+			//the prize pool of 600 is paid by places, the last table is closed
+			assert_eq!(Tournament::schedule(tournament), None);
+			assert!(Tournament::tables(tournament).is_empty());
+			assert!(Poker::seats(a).is_empty());
+			for &(p, balance) in [(1, BALANCE + 320), (2, BALANCE + 80), (3, BALANCE - 100),
+					(4, BALANCE - 100), (5, BALANCE - 100), (6, BALANCE - 100)].iter() {
+				assert_eq!(Balances::free_balance(&p), balance);
+				assert_eq!(Balances::reserved_balance(&p), 0);
+			}
+		});
+	}
+
+	#[test]
+	fn tournament_is_not_started_by_others() {
+		with_externalities(&mut new_test_ext(), || {
+			let tournament = Tournament::next_tournament_id();
+			let config = table::TableConfig { seats: 3, action_timeout: 10, ..Default::default() };
+			let schedule = sit_and_go::SitAndGo {
+				buy_in: 100,
+				starting_stack: 1500,
+				levels: vec![sit_and_go::Level { small_blind: 10, big_blind: 20, ante: 0 }],
+				escalation: sit_and_go::Escalation::Blocks(100),
+				payouts: vec![100],
+			};
+			assert_ok!(Tournament::create_tournament(Origin::signed(1), config, schedule, 9));
+			assert_ok!(Tournament::register(Origin::signed(2), tournament));
+			assert_eq!(Tournament::start_tournament(Origin::signed(1), tournament),
+				Err(PokerError::NotEnoughEntrants.message()));
+			assert_ok!(Tournament::register(Origin::signed(3), tournament));
+			assert_eq!(Tournament::start_tournament(Origin::signed(2), tournament),
+				Err(PokerError::NotHost.message()));
+
+			//nothing has been opened by the failed attempts
+			assert_eq!(Tournament::started(tournament), None);
+			assert!(Tournament::tables(tournament).is_empty());
+			assert_eq!(Poker::next_game_id(), 0);
+		});
+	}
+}
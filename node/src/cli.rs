@@ -3,11 +3,22 @@ use futures::{future, Future, sync::oneshot};
 use std::cell::RefCell;
 use tokio::runtime::Runtime;
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{informant, parse_and_execute, impl_augment_clap, NoCustom};
 use substrate_service::{ServiceFactory, Roles as ServiceRoles};
 use crate::chain_spec;
 use std::ops::Deref;
 use log::info;
+use structopt::StructOpt;
+
+///Options of the node on top of the Substrate ones
+#[derive(Debug, Clone, StructOpt)]
+pub struct PokerParams {
+	///Listen to poker RPC on this port instead of 9955, on the interface of the node's HTTP RPC
+	#[structopt(long = "poker-rpc-port", value_name = "PORT")]
+	poker_rpc_port: Option<u16>,
+}
+
+impl_augment_clap!(PokerParams);
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
@@ -15,9 +26,10 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+	parse_and_execute::<service::Factory, NoCustom, PokerParams, _, _, _, _, _>(
 		load_spec, &version, "substrate-node", args, exit,
-	 	|exit, _custom_args, config| {
+	 	|exit, custom_args, mut config| {
+			config.custom.poker_rpc_port = custom_args.poker_rpc_port;
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2017, 2018", version.author);
@@ -0,0 +1,113 @@
+//! JSON-RPC methods of the poker module.
+//!
+//! The service of this Substrate version doesn't let nodes extend its RPC,
+//! so the methods are served by a separate HTTP server on its own port,
+//! which is set by `--poker-rpc-port`. The server listens on the interface
+//! of the node's HTTP RPC, allows the same origins and is closed with the node.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use jsonrpc_core::{IoHandler, Error, Params, Value};
+use jsonrpc_http_server::{ServerBuilder, Server, DomainsValidation, AccessControlAllowOrigin};
+use log::{info, warn};
+use parity_codec::Encode;
+use primitives::hexdisplay::HexDisplay;
+use serde_json::json;
+use substrate_client::runtime_api::ProvideRuntimeApi;
+use runtime_primitives::generic::BlockId;
+use poker_runtime::{AccountId, Balance, api::{GameId, PokerApi, Table}};
+
+use crate::service::Factory;
+use substrate_service::FullClient;
+
+///Port used when none is given on the command line
+pub const DEFAULT_PORT: u16 = 9955;
+
+///Starts the server on the address; the node keeps working without it,
+///the caller closes the returned server when the node exits
+pub fn start(client: Arc<FullClient<Factory>>, address: SocketAddr, cors: Option<&Vec<String>>) -> Option<Server> {
+	let mut io = IoHandler::new();
+	io.add_method("poker_getTable", move |params: Params| {
+		let (game,): (GameId,) = params.parse()?;
+		let best = client.info()
+			.map_err(|e| internal(e.to_string()))?
+			.chain.best_hash;
+		let table = client.runtime_api()
+			.table(&BlockId::hash(best), game)
+			.map_err(|e| internal(e.to_string()))?;
+		Ok(table.map(to_json).unwrap_or(Value::Null))
+	});
+
+	//origins are checked the same way as by the node's RPC: any origin if `--rpc-cors` is not given
+	let origins: DomainsValidation<AccessControlAllowOrigin> = cors
+		.map(|origins| origins.iter().map(|origin| origin.as_str().into()).collect::<Vec<_>>())
+		.into();
+
+	match ServerBuilder::new(io).threads(1).cors(origins).start_http(&address) {
+		Ok(server) => {
+			info!("Poker RPC is served on {}", address);
+			Some(server)
+		},
+		Err(e) => {
+			warn!("Poker RPC can't be served on {}: {}", address, e);
+			None
+		},
+	}
+}
+
+fn internal(message: String) -> Error {
+	Error {
+		code: jsonrpc_core::ErrorCode::InternalError,
+		message,
+		data: None,
+	}
+}
+
+fn account(who: &AccountId) -> Value {
+	Value::String(format!("0x{}", HexDisplay::from(&who.encode())))
+}
+
+//balances don't fit into numbers of JavaScript
+fn balance(amount: Balance) -> Value {
+	Value::String(amount.to_string())
+}
+
+fn to_json(table: Table<AccountId, Balance>) -> Value {
+	let seats: Vec<Value> = table.seats.iter()
+		.map(|seat| json!({
+			"who": account(&seat.who),
+			"chips": balance(seat.chips),
+			"bet": balance(seat.bet),
+			"inHand": seat.in_hand,
+		}))
+		.collect();
+
+	let actions = table.actions.map(|actions| json!({
+		"check": actions.check,
+		"call": actions.call.map(balance),
+		"raise": actions.raise.map(|(min, max)| json!({
+			"min": balance(min),
+			"max": balance(max),
+		})),
+		"option": actions.option,
+	}));
+
+	//pairs of nominal and suit
+	let cards: Vec<Value> = table.shared_cards.chunks(2)
+		.map(|card| json!([card[0], card[1]]))
+		.collect();
+
+	json!({
+		"seats": seats,
+		"button": table.button,
+		"stage": table.stage,
+		"smallBlind": balance(table.small_blind),
+		"bigBlind": balance(table.big_blind),
+		"ante": balance(table.ante),
+		"pot": balance(table.pot),
+		"toAct": table.to_act.as_ref().map(account),
+		"actions": actions,
+		"sharedCards": cards,
+	})
+}
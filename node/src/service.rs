@@ -3,6 +3,8 @@
 #![warn(unused_extern_crates)]
 
 use std::sync::Arc;
+use std::net::SocketAddr;
+use futures::Future;
 use log::info;
 use transaction_pool::{self, txpool::{Pool as TransactionPool}};
 use poker_runtime::{self, GenesisConfig, opaque::Block, RuntimeApi};
@@ -32,6 +34,8 @@ native_executor_instance!(
 #[derive(Default)]
 pub struct NodeConfig {
 	inherent_data_providers: InherentDataProviders,
+	///Port of poker RPC, the default one if not given
+	pub poker_rpc_port: Option<u16>,
 }
 
 construct_simple_protocol! {
@@ -52,9 +56,22 @@ construct_service_factory! {
 		Genesis = GenesisConfig,
 		Configuration = NodeConfig,
 		FullService = FullComponents<Self>
-			{ |config: FactoryFullConfiguration<Self>, executor: TaskExecutor|
-				FullComponents::<Factory>::new(config, executor)
-			},
+			{ |config: FactoryFullConfiguration<Self>, executor: TaskExecutor| {
+				//poker RPC is available where the node's HTTP RPC is, `--rpc-external` included
+				let port = config.custom.poker_rpc_port.unwrap_or(crate::rpc::DEFAULT_PORT);
+				let address = config.rpc_http.map(|rpc| SocketAddr::new(rpc.ip(), port));
+				let cors = config.rpc_cors.clone();
+				let service = FullComponents::<Factory>::new(config, executor.clone())?;
+
+				let server = address.and_then(|address| crate::rpc::start(service.client(), address, cors.as_ref()));
+				if let Some(server) = server {
+					executor.spawn(service.on_exit().then(move |_| {
+						server.close();
+						Ok(())
+					}));
+				}
+				Ok(service)
+			}},
 		AuthoritySetup = {
 			|service: Self::FullService, executor: TaskExecutor, key: Option<Arc<Pair>>| {
 				if let Some(key) = key {
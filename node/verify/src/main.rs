@@ -0,0 +1,169 @@
+//! Replays a hand from its on-chain log and checks that the recorded
+//! cards, winners and payouts are the ones the rules of poker give.
+//!
+//! Usage: `poker-verify <hand id> [--url http://127.0.0.1:9933] [--rake 5] [--rake-cap 3]`
+//! or `poker-verify --log <hex of the SCALE-encoded log>`.
+
+mod replay;
+
+use parity_codec::{Encode, Decode};
+use poker_runtime::history::HandId;
+use primitives::blake2_256;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:9933";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let option = |name: &str| args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned());
+    let rake = match option("--rake") {
+        Some(rake) => rake.parse().unwrap_or_else(|_| fail("The rake must be a percentage")),
+        None => poker_runtime::RAKE_PERCENT,
+    };
+    let rake_cap = match option("--rake-cap") {
+        Some(cap) => cap.parse().unwrap_or_else(|_| fail("The cap of the rake must be a number of big blinds")),
+        None => poker_runtime::RAKE_CAP,
+    };
+
+    let encoded = match option("--log") {
+        Some(log) => from_hex(&log).unwrap_or_else(|| fail("The log must be in hex")),
+        None => {
+            let hand: HandId = match args.first().and_then(|a| a.parse().ok()) {
+                Some(hand) => hand,
+                None => fail("Usage: poker-verify <hand id> [--url <node RPC>] [--rake <percent>] [--rake-cap <big blinds>] | --log <hex>"),
+            };
+            let url = option("--url").unwrap_or_else(|| DEFAULT_URL.to_string());
+            fetch_log(&url, hand).unwrap_or_else(|e| fail(&e))
+        },
+    };
+
+    let log: replay::Log = Decode::decode(&mut &encoded[..])
+        .unwrap_or_else(|| fail("The log can't be decoded, is it a log of a hand?"));
+
+    let report = replay::verify(&log, rake, rake_cap);
+    for check in report.passed.iter() {
+        println!("ok      {}", check);
+    }
+    for check in report.failed.iter() {
+        println!("FAILED  {}", check);
+    }
+
+    if report.is_fair() {
+        println!("The hand is verified: {} checks have passed", report.passed.len());
+    } else {
+        println!("The hand is NOT verified: {} checks have failed", report.failed.len());
+        process::exit(1);
+    }
+}
+
+///Key of `HandActions` in the storage of the `Poker` module:
+///maps hash the prefix together with the encoded key
+fn storage_key(hand: HandId) -> Vec<u8> {
+    let mut key = b"Poker HandActions".to_vec();
+    hand.encode_to(&mut key);
+    blake2_256(&key).to_vec()
+}
+
+///Requests the log with `state_getStorage` over plain HTTP/1.0,
+///so the body of the response is neither chunked nor kept alive
+fn fetch_log(url: &str, hand: HandId) -> Result<Vec<u8>, String> {
+    let address = url.trim_start_matches("http://").trim_end_matches('/');
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "state_getStorage",
+        "params": [format!("0x{}", to_hex(&storage_key(hand)))],
+    }).to_string();
+
+    let mut stream = TcpStream::connect(address)
+        .map_err(|e| format!("Can't connect to the node at {}: {}", url, e))?;
+    write!(stream, "POST / HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n\r\n{}", address, request.len(), request)
+        .map_err(|e| format!("Can't send the request to the node at {}: {}", url, e))?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)
+        .map_err(|e| format!("Can't read the response of the node at {}: {}", url, e))?;
+    read_log(&response, hand)
+}
+
+///Takes the log out of the HTTP response; a failure of the node
+///is told apart from a log which is not in the storage
+fn read_log(response: &str, hand: HandId) -> Result<Vec<u8>, String> {
+    let mut parts = response.splitn(2, "\r\n\r\n");
+    let head = parts.next().unwrap_or("");
+    let body = parts.next().unwrap_or("");
+
+    let status = head.lines().next().unwrap_or("");
+    let code = status.split_whitespace().nth(1);
+    if !status.starts_with("HTTP/") || code != Some("200") {
+        return Err(format!("The node has failed to answer: {}", status));
+    }
+
+    let reply: serde_json::Value = serde_json::from_str(body)
+        .map_err(|_| format!("Unexpected response of the node: {}", body))?;
+    if let Some(error) = reply.get("error") {
+        return Err(format!("The node has returned an error: {}", error));
+    }
+    match reply.get("result") {
+        Some(serde_json::Value::String(data)) => from_hex(data)
+            .ok_or_else(|| "The node has returned invalid hex".to_string()),
+        Some(serde_json::Value::Null) =>
+            Err(format!("There is no log of the hand {}, it has expired or never existed", hand)),
+        _ => Err(format!("Unexpected response of the node: {}", body)),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim().trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: &str, body: &str) -> String {
+        format!("{}\r\nContent-Type: application/json\r\n\r\n{}", status, body)
+    }
+
+    #[test]
+    fn log_is_read_from_the_result() {
+        let ok = response("HTTP/1.0 200 OK", r#"{"jsonrpc":"2.0","result":"0x0102","id":1}"#);
+        assert_eq!(read_log(&ok, 7), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn missing_log_differs_from_failures() {
+        let missing = response("HTTP/1.0 200 OK", r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+        assert!(read_log(&missing, 7).unwrap_err().contains("no log of the hand 7"));
+
+        let error = response("HTTP/1.0 200 OK",
+            r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#);
+        assert!(read_log(&error, 7).unwrap_err().contains("Method not found"));
+
+        let failed = response("HTTP/1.0 500 Internal Server Error", "");
+        assert!(read_log(&failed, 7).unwrap_err().contains("500"));
+        assert!(read_log("", 7).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+use poker_runtime::{AccountId, Balance};
+use poker_runtime::history::Action;
+use poker_runtime::stage::{self, StageId};
+use poker_runtime::{cards, commutative, pots, ranking};
+
+pub type Log = Vec<Action<AccountId, Balance>>;
+
+///Result of the replay: checks which have passed and those which have failed
+#[derive(Default, Debug)]
+pub struct Report {
+    pub passed: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl Report {
+    pub fn is_fair(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    fn check(&mut self, ok: bool, what: String) {
+        if ok {
+            self.passed.push(what);
+        } else {
+            self.failed.push(what);
+        }
+    }
+}
+
+///Amounts of chips by account, in the order of appearance
+type Ledger = Vec<(AccountId, Balance)>;
+
+fn amount(ledger: &Ledger, who: &AccountId) -> Balance {
+    ledger.iter().find(|(p, _)| p == who).map(|(_, v)| *v).unwrap_or(0)
+}
+
+fn add(ledger: &mut Ledger, who: AccountId, value: Balance) {
+    match ledger.iter_mut().find(|(p, _)| *p == who) {
+        Some(entry) => entry.1 += value,
+        None => ledger.push((who, value)),
+    }
+}
+
+///State of the hand which is recomputed from the log
+#[derive(Default)]
+struct Hand {
+    participants: Vec<AccountId>,
+    keys: Vec<(AccountId, [Vec<u8>; 4])>,
+    secrets: Vec<(AccountId, StageId, Vec<u8>)>,
+    deck: Vec<u8>,
+    shared: Vec<u8>,
+    open: Vec<(AccountId, Vec<u8>)>,
+    bets: Ledger,
+    committed: Ledger,
+    folded: Vec<AccountId>,
+    big_blind: Balance,
+    rake: Balance,
+    awarded: Ledger,
+    refunded: Ledger,
+    forfeited: Ledger,
+    cancelled: bool,
+}
+
+impl Hand {
+    fn deal_order(&self) -> Vec<AccountId> {
+        let mut order = self.participants.clone();
+        if !order.is_empty() {
+            let button = order.remove(0);
+            order.push(button);
+        }
+        order
+    }
+
+    fn in_hand(&self) -> Vec<AccountId> {
+        self.deal_order().into_iter()
+            .filter(|p| !self.folded.contains(p))
+            .collect()
+    }
+
+    fn secret(&self, who: &AccountId, stage: StageId) -> Option<&Vec<u8>> {
+        self.secrets.iter()
+            .find(|(p, s, _)| p == who && *s == stage)
+            .map(|(_, _, secret)| secret)
+    }
+
+    fn card(&self, i: usize) -> &[u8] {
+        &self.deck[i * commutative::ELEMENT_SIZE..(i + 1) * commutative::ELEMENT_SIZE]
+    }
+
+    ///Encrypted cards of the stage as they were dealt from the locked deck
+    fn dealt(&self, stage: StageId) -> Vec<u8> {
+        let shared = 2 * self.participants.len();
+        let positions: Vec<usize> = match stage {
+            stage::FLOP => (shared + 1..shared + 4).collect(),
+            stage::TURN => vec![shared + 5],
+            stage::RIVER => vec![shared + 7],
+            _ => vec![],
+        };
+        positions.into_iter().flat_map(|i| self.card(i).to_vec()).collect()
+    }
+
+    fn pocket(&self, who: &AccountId) -> Option<Vec<u8>> {
+        let n = self.participants.len();
+        let position = self.deal_order().iter().position(|p| p == who)?;
+        let mut cards = self.card(position).to_vec();
+        cards.extend_from_slice(self.card(position + n));
+        Some(cards)
+    }
+
+    fn matches(&self, who: &AccountId, stage: StageId, secret: &[u8]) -> bool {
+        let index = match stage {
+            stage::SHOWDOWN => 0,
+            stage::FLOP => 1,
+            stage::TURN => 2,
+            _ => 3,
+        };
+        self.keys.iter()
+            .find(|(p, _)| p == who)
+            .map(|(_, keys)| commutative::keypair_is_valid(&keys[index], secret))
+            .unwrap_or(false)
+    }
+
+    ///Same as `collect_bets` of the runtime: an uncalled bet is returned
+    fn collect_bets(&mut self) {
+        let mut bets: Ledger = self.participants.iter()
+            .map(|p| (p.clone(), amount(&self.bets, p)))
+            .collect();
+        self.bets.clear();
+
+        if let Some((who, excess)) = pots::uncalled(&bets[..]) {
+            if !self.folded.contains(&who) {
+                for bet in bets.iter_mut().filter(|bet| bet.0 == who) {
+                    bet.1 -= excess;
+                }
+            }
+        }
+        for (p, bet) in bets.into_iter() {
+            add(&mut self.committed, p, bet);
+        }
+    }
+
+    fn pots(&self) -> pots::Pots<AccountId, Balance> {
+        let committed: Vec<(AccountId, Balance, bool)> = self.participants.iter()
+            .map(|p| (p.clone(), amount(&self.committed, p), !self.folded.contains(p)))
+            .collect();
+        pots::split(&committed[..])
+    }
+}
+
+///Replays the hand from its log: checks revealed secrets against public keys,
+///decrypts dealt cards again, recomputes pots, the rake and prizes;
+///the rake is a percentage of the pots, but not more than `rake_cap` big blinds
+pub fn verify(log: &[Action<AccountId, Balance>], rake_percent: u32, rake_cap: u32) -> Report {
+    let mut report = Report::default();
+    let mut hand = Hand::default();
+
+    for action in log.iter().cloned() {
+        match action {
+            Action::Started(stacks) => {
+                hand.participants = stacks.into_iter().map(|(p, _)| p).collect();
+            },
+            Action::Blinds(_, big_blind) => hand.big_blind = big_blind,
+            Action::Keys(who, hand_key, flop, turn, river) => {
+                hand.keys.push((who, [hand_key, flop, turn, river]));
+            },
+            Action::Dealt(deck) => {
+                let complete = deck.len() == cards::DECK_SIZE * commutative::ELEMENT_SIZE;
+                report.check(complete, "the locked deck is complete".into());
+                if !complete {
+                    return report;
+                }
+                hand.deck = deck;
+            },
+            Action::Ante(who, ante) => add(&mut hand.committed, who, ante),
+            Action::Blind(who, bet) | Action::Call(who, bet)
+                | Action::Raise(who, bet) | Action::AllIn(who, bet) => {
+                hand.bets.retain(|(p, _)| *p != who);
+                hand.bets.push((who, bet));
+            },
+            Action::Check(_) | Action::Nonce(_, _) | Action::TimedOut(_) | Action::InvalidShuffle(_) => {},
+            Action::Fold(who) => hand.folded.push(who),
+            Action::Secret(who, stage, secret) => {
+                report.check(hand.matches(&who, stage, &secret),
+                    format!("the secret of {:?} for stage {} matches the public key", who, stage));
+                hand.secrets.push((who, stage, secret));
+            },
+            Action::WrongSecret(who, stage, secret) => {
+                //the participant has been punished, the evidence must hold
+                report.check(!hand.matches(&who, stage, &secret),
+                    format!("the rejected secret of {:?} for stage {} doesn't match the public key", who, stage));
+            },
+            Action::Cards(stage, opened) => {
+                hand.collect_bets();
+                let decrypted = hand.participants.iter()
+                    .try_fold(hand.dealt(stage), |cards, p| {
+                        let secret = hand.secret(p, stage)?;
+                        commutative::decrypt(&cards, secret).ok()
+                    })
+                    .and_then(|cards| commutative::decode(&cards))
+                    .map(|cards| cards::encode(cards.iter().collect()));
+                report.check(decrypted.as_ref() == Some(&opened),
+                    format!("shared cards of stage {} are decrypted correctly", stage));
+                hand.shared.extend(opened);
+            },
+            Action::Showdown(who, opened) => {
+                let decrypted = hand.secret(&who, stage::SHOWDOWN)
+                    .and_then(|secret| commutative::decrypt(&hand.pocket(&who)?, secret).ok())
+                    .and_then(|cards| commutative::decode(&cards))
+                    .map(|cards| cards::encode(cards.iter().collect()));
+                report.check(decrypted.as_ref() == Some(&opened),
+                    format!("pocket cards of {:?} are decrypted correctly", who));
+                hand.open.push((who, opened));
+            },
+            Action::Rake(rake) => hand.rake += rake,
+            Action::PotAwarded(who, prize) => add(&mut hand.awarded, who, prize),
+            Action::Cancelled => hand.cancelled = true,
+            Action::Refunded(who, refund) => add(&mut hand.refunded, who, refund),
+            Action::Forfeited(who, lost) => add(&mut hand.forfeited, who, lost),
+        }
+    }
+
+    hand.collect_bets();
+    if hand.cancelled {
+        check_refunds(&mut report, &hand);
+        return report;
+    }
+    if hand.awarded.is_empty() {
+        report.passed.push("the hand wasn't finished, nobody has won chips".into());
+        return report;
+    }
+
+    let mut pots = hand.pots();
+
+    //tournaments are not raked, otherwise the rake is taken the same way as in the runtime
+    if hand.rake > 0 {
+        report.check(!hand.shared.is_empty(), "the rake is taken only when the flop is seen".into());
+        let cap = hand.big_blind * rake_cap as Balance;
+        let taken = pots::rake(&mut pots, rake_percent, cap);
+        report.check(taken == hand.rake, format!("the rake of {} is {}% of the pots, but not more than {}",
+            hand.rake, rake_percent, cap));
+    }
+
+    let expected = expected_prizes(&hand, &pots);
+    check_prizes(&mut report, &expected, &hand.awarded);
+    report
+}
+
+///A cancelled hand gives committed chips back, except those of late participants
+///which are shared among the others; folded participants may have left the table,
+///then their chips are lost
+fn check_refunds(report: &mut Report, hand: &Hand) {
+    for (who, refund) in hand.refunded.iter() {
+        let committed = amount(&hand.committed, who);
+        report.check(*refund == committed, format!("{:?} got {} chips back, committed {}", who, refund, committed));
+    }
+    for (who, lost) in hand.forfeited.iter() {
+        let committed = amount(&hand.committed, who);
+        report.check(*lost == committed, format!("{:?} has lost {} chips, committed {}", who, lost, committed));
+        report.check(!hand.refunded.iter().any(|(p, _)| p == who),
+            format!("{:?} isn't refunded the lost chips", who));
+    }
+    for who in hand.in_hand().iter().filter(|p| amount(&hand.committed, p) > 0) {
+        let settled = hand.refunded.iter().chain(hand.forfeited.iter()).any(|(p, _)| p == who);
+        report.check(settled, format!("chips committed by {:?} are refunded or lost", who));
+    }
+
+    //nobody is compensated when everybody got the chips back
+    let expected = if hand.forfeited.is_empty() {
+        vec![]
+    } else {
+        let penalty = hand.forfeited.iter().map(|(_, lost)| *lost).sum();
+        let others: Vec<AccountId> = hand.refunded.iter().map(|(p, _)| p.clone()).collect();
+        pots::share(penalty, &others[..])
+    };
+    check_prizes(report, &expected, &hand.awarded);
+}
+
+fn check_prizes(report: &mut Report, expected: &Ledger, awarded: &Ledger) {
+    let mut accounts: Vec<AccountId> = expected.iter().map(|(p, _)| p.clone()).collect();
+    for (p, _) in awarded.iter() {
+        if !accounts.contains(p) {
+            accounts.push(p.clone());
+        }
+    }
+    for who in accounts.into_iter() {
+        let (want, got) = (amount(expected, &who), amount(awarded, &who));
+        report.check(want == got, format!("{:?} has won {} chips, expected {}", who, got, want));
+    }
+}
+
+///Prizes as the runtime must have awarded them: every pot is played
+///among participants eligible for it, odd chips go to the first winners
+fn expected_prizes(hand: &Hand, pots: &pots::Pots<AccountId, Balance>) -> Ledger {
+    let mut prizes = vec![];
+    let contenders = hand.in_hand();
+
+    if contenders.len() == 1 {
+        let total = pots.iter().map(|(amount, _)| amount).sum();
+        add(&mut prizes, contenders[0].clone(), total);
+        return prizes;
+    }
+
+    let shared = cards::decode(&hand.shared);
+    let hands: Vec<(AccountId, ranking::RankedHand)> = contenders.iter()
+        .filter_map(|who| {
+            let (_, open) = hand.open.iter().find(|(p, _)| p == who)?;
+            let mut seven = cards::decode(open);
+            seven.extend(shared.iter().cloned());
+            Some((who.clone(), ranking::choose_strongest_five(&seven[..])))
+        })
+        .collect();
+
+    for (amount, eligible) in pots.iter() {
+        let ranked: Vec<&(AccountId, ranking::RankedHand)> = hands.iter()
+            .filter(|(who, _)| eligible.contains(who))
+            .collect();
+        let best = match ranked.iter().map(|(_, hand)| hand).max() {
+            Some(best) => best,
+            None => continue,
+        };
+        let winners: Vec<AccountId> = ranked.iter()
+            .filter(|(_, hand)| hand == best)
+            .map(|(who, _)| who.clone())
+            .collect();
+
+        for (who, prize) in pots::share(*amount, &winners[..]) {
+            add(&mut prizes, who, prize);
+        }
+    }
+    prizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_prover as prover;
+    use primitives::sr25519;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn account(seed: u8) -> AccountId {
+        sr25519::Public([seed; 32])
+    }
+
+    #[test]
+    fn fold_returns_the_uncalled_bet() {
+        let (a, b) = (account(1), account(2));
+        let log = vec![
+            Action::Started(vec![(a.clone(), 1000), (b.clone(), 1000)]),
+            Action::Blind(a.clone(), 5),
+            Action::Blind(b.clone(), 10),
+            Action::Raise(a.clone(), 30),
+            Action::Fold(b.clone()),
+            Action::PotAwarded(a.clone(), 20),
+        ];
+        assert_eq!(verify(&log, 5, 3).failed, Vec::<String>::new());
+
+        let mut wrong = log.clone();
+        wrong[5] = Action::PotAwarded(a.clone(), 40);
+        assert_eq!(verify(&wrong, 5, 3).failed.len(), 1);
+
+        //no flop, no drop
+        let mut raked = log.clone();
+        raked.insert(5, Action::Rake(1));
+        raked[6] = Action::PotAwarded(a.clone(), 19);
+        assert!(!verify(&raked, 5, 3).is_fair());
+    }
+
+    #[test]
+    fn antes_count_and_excess_is_returned() {
+        let (a, b, c) = (account(1), account(2), account(3));
+        let log = vec![
+            Action::Started(vec![(a.clone(), 100), (b.clone(), 300), (c.clone(), 300)]),
+            Action::Ante(a.clone(), 1),
+            Action::Ante(b.clone(), 1),
+            Action::Ante(c.clone(), 1),
+            Action::Blind(b.clone(), 5),
+            Action::Blind(c.clone(), 10),
+            Action::Raise(a.clone(), 50),
+            Action::AllIn(b.clone(), 299),
+            Action::Fold(c.clone()),
+            Action::Fold(a.clone()),
+            Action::PotAwarded(b.clone(), 113),
+        ];
+        assert_eq!(verify(&log, 5, 3).failed, Vec::<String>::new());
+    }
+
+    #[test]
+    fn cancelled_hand_refunds_committed_chips() {
+        let (a, b, c) = (account(1), account(2), account(3));
+        let log = vec![
+            Action::Started(vec![(a.clone(), 1000), (b.clone(), 1000), (c.clone(), 1000)]),
+            Action::Blind(b.clone(), 5),
+            Action::Blind(c.clone(), 10),
+            Action::Call(a.clone(), 10),
+            Action::Call(b.clone(), 10),
+            Action::Check(c.clone()),
+            Action::TimedOut(c.clone()),
+            Action::Refunded(a.clone(), 10),
+            Action::Refunded(b.clone(), 10),
+            Action::Forfeited(c.clone(), 10),
+            Action::PotAwarded(a.clone(), 5),
+            Action::PotAwarded(b.clone(), 5),
+            Action::Cancelled,
+        ];
+        assert_eq!(verify(&log, 5, 3).failed, Vec::<String>::new());
+
+        let mut overpaid = log.clone();
+        overpaid[7] = Action::Refunded(a.clone(), 20);
+        assert_eq!(verify(&overpaid, 5, 3).failed.len(), 1);
+
+        let mut kept = log.clone();
+        kept.remove(8);
+        assert!(!verify(&kept, 5, 3).is_fair());
+
+        //nobody is late when opened cards are invalid, everybody gets the chips back
+        let mut invalid: Log = log[..6].to_vec();
+        for p in [&a, &b, &c].iter() {
+            invalid.push(Action::Refunded((*p).clone(), 10));
+        }
+        invalid.push(Action::Cancelled);
+        assert_eq!(verify(&invalid, 5, 3).failed, Vec::<String>::new());
+        invalid.insert(9, Action::PotAwarded(a.clone(), 10));
+        assert!(!verify(&invalid, 5, 3).is_fair());
+    }
+
+    #[test]
+    fn cards_are_decrypted_again() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let (a, b) = (account(1), account(2));
+        let players = vec![a.clone(), b.clone()];
+        let keys: Vec<prover::RoundKeys> = players.iter().map(|_| prover::round_keys(&mut rng)).collect();
+
+        //the button is the first participant and the last one in the order of dealing;
+        //the deck is encrypted without permuting, so cards are dealt in the initial order
+        let order = [1, 0];
+        let shuffle_keys: Vec<Vec<u8>> = order.iter().map(|_| prover::keypair(&mut rng).encryption).collect();
+        let mut decks = vec![commutative::encode(cards::deck().iter().collect())];
+        for key in shuffle_keys.iter() {
+            let next = commutative::encrypt(decks.last().unwrap(), key).unwrap();
+            decks.push(next);
+        }
+        for (position, &i) in order.iter().enumerate() {
+            let locked = prover::lock(&decks, 2, &shuffle_keys[position], &keys[i], &mut rng);
+            decks.push(locked.deck);
+        }
+        let deck = decks.pop().unwrap();
+
+        let all = cards::deck();
+        let encoded = |range: std::ops::Range<usize>| cards::encode(all[range].iter().collect());
+
+        let mut log: Log = vec![
+            Action::Started(vec![(a.clone(), 1000), (b.clone(), 1000)]),
+            Action::Blinds(5, 10),
+        ];
+        for (p, k) in players.iter().zip(keys.iter()) {
+            log.push(Action::Keys(p.clone(), k.hand.public.clone(), k.flop.public.clone(),
+                k.turn.public.clone(), k.river.public.clone()));
+        }
+        log.push(Action::Dealt(deck));
+        log.push(Action::Blind(a.clone(), 5));
+        log.push(Action::Blind(b.clone(), 10));
+        log.push(Action::Call(a.clone(), 10));
+        log.push(Action::Check(b.clone()));
+        for (p, k) in players.iter().zip(keys.iter()) {
+            log.push(Action::Secret(p.clone(), stage::FLOP, k.flop.decryption.clone()));
+        }
+        log.push(Action::Cards(stage::FLOP, encoded(5..8)));
+        assert_eq!(verify(&log, 5, 3).failed, Vec::<String>::new());
+
+        let mut forged = log.clone();
+        forged.pop();
+        forged.push(Action::Cards(stage::FLOP, encoded(8..11)));
+        assert_eq!(verify(&forged, 5, 3).failed.len(), 1);
+
+        let mut wrong_secret = log.clone();
+        let index = wrong_secret.len() - 2;
+        wrong_secret[index] = Action::Secret(b.clone(), stage::FLOP, keys[1].turn.decryption.clone());
+        assert_eq!(verify(&wrong_secret, 5, 3).failed.len(), 2);
+
+        //a participant punished for a wrong secret can't be framed with a valid one
+        let mut punished = log.clone();
+        punished.push(Action::WrongSecret(a.clone(), stage::TURN, keys[0].flop.decryption.clone()));
+        assert_eq!(verify(&punished, 5, 3).failed, Vec::<String>::new());
+        punished.push(Action::WrongSecret(a.clone(), stage::TURN, keys[0].turn.decryption.clone()));
+        assert_eq!(verify(&punished, 5, 3).failed.len(), 1);
+
+        //a bets after the flop and b folds: the house takes 5% of the pot of 20
+        let mut raked = log.clone();
+        raked.push(Action::Raise(a.clone(), 100));
+        raked.push(Action::Fold(b.clone()));
+        raked.push(Action::Rake(1));
+        raked.push(Action::PotAwarded(a.clone(), 19));
+        assert_eq!(verify(&raked, 5, 3).failed, Vec::<String>::new());
+        //the cap is a rule of the runtime, not the logged amount
+        assert_eq!(verify(&raked, 5, 0).failed.len(), 2);
+
+        //b shows his pocket cards: the second and the fourth ones in the deck
+        let mut showdown = log.clone();
+        showdown.push(Action::Secret(b.clone(), stage::SHOWDOWN, keys[1].hand.decryption.clone()));
+        showdown.push(Action::Showdown(b.clone(), cards::encode(vec![&all[0], &all[2]])));
+        assert_eq!(verify(&showdown, 5, 3).failed, Vec::<String>::new());
+    }
+}
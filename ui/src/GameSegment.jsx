@@ -6,6 +6,7 @@ import { Icon, Label, Header, Segment, Button } from 'semantic-ui-react';
 import { Bond } from 'oo7';
 import { If, Rspan } from 'oo7-react';
 import { SignerBond } from './AccountIdBond.jsx';
+import { InputBond } from './InputBond.jsx';
 import { TransactButton } from './TransactButton.jsx';
 import { BlinkingLabel } from './BlinkingLabel.jsx';
 import { RaiseSelector } from './RaiseSelector.jsx';
@@ -13,18 +14,37 @@ import { NotificationContainer } from 'react-notifications';
 import { Pretty } from './Pretty';
 import { SvgRow } from './SvgRow';
 
-import { calls, post, runtime } from 'oo7-substrate';
+import { calls, runtime, addCodecTransform } from 'oo7-substrate';
 import {secretStore} from 'oo7-substrate/src/secretStore.js';
 
 const bufEq = require('arraybuffer-equal');
 
-import { decode, image, hidden } from './cards.js';
-import { decrypt } from './naive_rsa.js';
-import { BONDS } from './keys.js';
+import { decode, image } from './cards.js';
 import {BalanceBond} from "./BalanceBond";
-const keys = require('./keys.js');
 const stages = require('./stages.js');
 
+//types of the poker module which aren't known to the codec
+addCodecTransform('GameId', 'u64');
+addCodecTransform('BettingStructure<Balance>', { _enum: {
+    NoLimit: null,
+    PotLimit: null,
+    FixedLimit: { small_bet: 'Balance', big_bet: 'Balance', max_raises: 'u32' }
+}});
+addCodecTransform('TableConfig<Balance>', {
+    small_blind: 'Balance',
+    big_blind: 'Balance',
+    ante: 'Balance',
+    min_buy_in: 'Balance',
+    max_buy_in: 'Balance',
+    seats: 'u32',
+    action_timeout: 'u64',
+    betting: 'BettingStructure<Balance>'
+});
+
+//tables created here are no-limit ones for 9 players with 3 minutes to act
+const SEATS = 9;
+const ACTION_TIMEOUT = 18;
+
 function accountsAreEqualAndNotNull(left, right) {
     return left !== null && right !== null
         && bufEq(left.buffer, right.buffer);
@@ -34,6 +54,12 @@ function bondsAccountsAreEqualAndNotNull(left, right) {
     return left.map(d => right.map(u => accountsAreEqualAndNotNull(d, u)));
 }
 
+function gameIdValidator(value) {
+    return /^\d+$/.test(value)
+        ? { internal: value, external: parseInt(value) }
+        : null;
+}
+
 export class GameSegment extends React.Component {
     constructor (props) {
         super(props);
@@ -44,75 +70,24 @@ export class GameSegment extends React.Component {
         this.isLoggedIn = (new Bond).default(false);
         this.isLoggedOut = this.isLoggedIn.map(flag => !flag);
 
-        this.dealer = runtime.poker.dealer;
-        this.player = runtime.poker.player;
-
-        this.isDealer = bondsAccountsAreEqualAndNotNull(this.dealer, this.user);
-        this.isPlayer = bondsAccountsAreEqualAndNotNull(this.player, this.user);
-
-        this.opponent = this.isDealer.map(isDealer => {
-            if (isDealer) {
-                return this.player;
-            } else {
-                return this.dealer;
-            }
-        });
+        //the table which is displayed, events of other tables are ignored
+        this.gameId = new Bond;
+        this.gameIsChosen = this.gameId.ready();
 
-        this.dealerIsJoined = this.dealer.map(d => d !== null);
-        this.playerIsJoined = this.player.map(p => p !== null);
+        this.seats = runtime.poker.seats(this.gameId);
+        this.tableExists = this.seats.map(seats => seats.length > 0);
+        this.isSeated = this.seats.map(seats =>
+            this.user.map(user => seats.some(s => accountsAreEqualAndNotNull(s, user))));
 
-        this.isJoined = this.isDealer.map(d =>
-            this.isPlayer.map(p =>
-                d || p));
-
-        this.handKey = new Bond();
-        this.flopKey = new Bond();
-        this.turnKey = new Bond();
-        this.riverKey = new Bond();
-
-        this.pocketCards = runtime.poker.pocketCards(game.user);
-        this.opponentCards = this.opponent.map(runtime.poker.openCards);
-
-        this.sharedCards = runtime.poker.sharedCards;
-        this.stage = runtime.poker.stage;
+        this.config = runtime.poker.config(this.gameId);
+        this.stage = runtime.poker.stage(this.gameId);
+        this.isIdle = this.stage.map(stage => stage === stages.IDLE);
+        this.sharedCards = runtime.poker.sharedCards(this.gameId);
+        this.betsNow = runtime.poker.betsNow(this.gameId);
+        this.betLevel = runtime.poker.betLevel(this.gameId);
+        this.stack = runtime.poker.stacks(Bond.all([this.gameId, this.user]));
 
         this.raise = new Bond();
-        this.betsAreMade = runtime.poker.betsNow.map(who => who === null);
-
-        this.pocketCardsAreDealt = this.pocketCards.map(encrypted => encrypted.length !== 0);
-        this.opponentCardsAreRevealed = this.opponentCards.map(encrypted => encrypted.length !== 0);
-
-        this.isJoined.tie(joined => {
-            this.isLoggedIn.then(logged => {
-                if (joined && logged) { //joining a game while being logged in
-                    this.pocketCardsAreDealt.then(dealt => {
-                        console.log("Joining the game");
-                        keys.generate();
-                        this.requestPreflop();
-                    });
-                }
-            });
-        });
-
-        this.isLoggedIn.tie(logged => {
-            this.isJoined.then(joined => {
-                if (joined && logged) { //logging in while being joined to a game
-                    this.pocketCardsAreDealt.then(dealt => {
-                        console.log("Resuming to the game");
-                        if (dealt) {
-                            keys.load();
-                        } else {
-                            keys.generate();
-                            this.requestPreflop();
-                        }
-                    });
-                }
-            });
-        });
-
-        this.betsAreMade.tie(playersAreReady => {
-            this.requestNextStage(playersAreReady)
-        });
     }
 
     logIn () {
@@ -129,51 +104,6 @@ export class GameSegment extends React.Component {
         }
     }
 
-    requestPreflop () {
-        game.opponent.tie((other, id) => {
-            if (other !== null) {
-                console.log("Requesting a new round");
-                let tx = {
-                    sender: this.user,
-                    call: calls.poker.preflop(
-                        keys.hand.map(key => key.modulus),
-                        keys.flop.map(key => key.modulus),
-                        keys.turn.map(key => key.modulus),
-                        keys.river.map(key => key.modulus))
-                };
-                let status = post(tx);
-                status.tie((s,id) => {
-                    if (s.confirmed || s.scheduled) {
-                        console.log("Request for a new round registered");
-                        status.untie(id);
-                    }
-                });
-                if (id) {
-                    game.opponent.untie(id);
-                }
-            }
-        });
-    }
-
-    requestNextStage (playersAreReady) {
-        if (playersAreReady) {
-            let tx = {
-                sender: this.user,
-                call: calls.poker.nextStage(this.stage.map(stage => {
-                    return stages.secretFromStage(stage);
-                }))
-            };
-            let status = post(tx);
-            game.nextStageRequested = true;
-            status.tie((s,id) => {
-                if (s.confirmed || s.scheduled) {
-                    console.log("Request for a new stage registered");
-                }
-                game.nextStageRequested = false;
-            });
-        }
-    }
-
     render () {
         return <Segment style={{ margin: '1em' }} padded>
             <Header as='h2'>
@@ -199,76 +129,74 @@ export class GameSegment extends React.Component {
                 <If condition={this.isLoggedOut} then={<span>
                     <div style={{ fontSize: 'small' }}>Please input account information:</div>
                     <SignerBond bond={this.user} onKeyDown={this.logInKeyPressHandler}/>
-					<div style={{ paddingTop: '1em' }}>
+                    <div style={{ paddingTop: '1em' }}>
                         <If condition={this.user.ready()} then={
                             <Button onClick={this.logIn} content="Log in" icon="sign in" color="orange"/>
                         } else={
                             <Button content="Log in" icon="sign in" />
                         } />
-					</div>
-				</span>} />
+                    </div>
+                </span>} />
 
-				{/* User logged in */}
+                {/* User logged in */}
                 <If condition={this.isLoggedIn} then={<span>
                     { this.displayAccountInfo() }
-
-                    <If condition={this.dealerIsJoined} then={<div style={{ paddingTop: '1em' }}>
-                        <table><tbody><tr>
-                            <td>Blinds are </td>
-                            <td><Label color="violet" size="large">
-                                <Pretty value={runtime.poker.blinds
-                                    .map(blinds => `${blinds[0][0]}/${blinds[0][1]}`)
-                                }/>
-                            </Label></td>
-                            <td> in this game</td>
-                        </tr></tbody></table>
-
-                        <If condition={this.pocketCardsAreDealt}
-                            else={this.displayParticipant(this.dealer, false)}/>
-                        <If condition={this.playerIsJoined} then={<div>
-                            <If condition={this.pocketCardsAreDealt}
-                                else={this.displayParticipant(this.player, false)}/>
-                            <p />
-
-                            <If condition={this.isJoined} then={
-                                this.renderGameTable()
-                            } else={
-                                this.displayMessage("Sorry, at the moment here are only two chairs...")
-                            }/>
+                    { this.renderChooseGameSection() }
+
+                    <If condition={this.gameIsChosen} then={
+                        <If condition={this.tableExists} then={<div style={{ paddingTop: '1em' }}>
+                            { this.displayRules() }
+                            <If condition={this.isSeated}
+                                then={this.renderGameTable()}
+                                else={this.renderJoinGameSection()}/>
                         </div>} else={
-                            <If condition={this.isJoined} then={
-                                this.renderWaitingSection()
-                            } else={<span>
-                                { this.displayMessage("One person is waiting at the table.") }
-                                { this.renderJoinGameSection() }
-                            </span>}/>
+                            this.displayMessage("There is nobody at this table.")
                         }/>
-                    </div>} else={<span>
-                        { this.displayMessage("There is nobody in the room.") }
-                        { this.renderCreateGameSection() }
-                    </span>}/>
-				</span>} />
+                    } else={
+                        this.renderCreateGameSection()
+                    }/>
+                </span>} />
             </div>
         </Segment>
     }
 
+    renderChooseGameSection () {
+        return <div style={{ paddingTop: '1em' }}>
+            <div style={{ fontSize: 'small' }}>number of the table</div>
+            <InputBond bond={this.gameId} validator={gameIdValidator} placeholder="0"/>
+        </div>;
+    }
+
     renderCreateGameSection () {
+        let smallBlind = new Bond;
+        let bigBlind = new Bond;
+        let minBuyIn = new Bond;
+        let maxBuyIn = new Bond;
         let buyIn = new Bond;
-        let blind = new Bond;
+
+        let config = Bond.all([smallBlind, bigBlind, minBuyIn, maxBuyIn])
+            .map(([small, big, min, max]) => ({
+                small_blind: small,
+                big_blind: big,
+                ante: 0,
+                min_buy_in: min,
+                max_buy_in: max,
+                seats: SEATS,
+                action_timeout: ACTION_TIMEOUT,
+                betting: { NoLimit: null }
+            }));
 
         return <div style={{ paddingTop: '1em' }}>
-            <div style={{ paddingBottom: '1em' }}>
-                <div style={{ fontSize: 'small' }}>minimal amount to bet</div>
-                <BalanceBond bond={blind} />
-            </div>
-            <div style={{ paddingBottom: '1em' }}>
-                <div style={{ fontSize: 'small' }}>amount to put on the table</div>
-                <BalanceBond bond={buyIn} />
-            </div>
+            { this.displayMessage("Choose a table or set up a new one.") }
+            { this.displayInput("small blind", smallBlind) }
+            { this.displayInput("big blind", bigBlind) }
+            { this.displayInput("minimal buy-in", minBuyIn) }
+            { this.displayInput("maximal buy-in", maxBuyIn) }
+            { this.displayInput("amount to put on the table", buyIn) }
             <div style={{ paddingTop: '1em' }}>
                 <TransactButton tx={{
                     sender: this.user,
-                    call: calls.poker.createGame(buyIn, blind),
+                    call: calls.poker.createGame(config, buyIn),
                     compact: false,
                     longevity: true
                 }} color="green" icon="sign in"
@@ -277,33 +205,16 @@ export class GameSegment extends React.Component {
         </div>;
     }
 
-    renderWaitingSection () {
-        return <div>
-            { this.displayMessage("You are waiting at the table...") }
-            <div style={{ paddingTop: '1em' }}>
-                <TransactButton tx={{
-                    sender: this.user,
-                    call: calls.poker.leaveGame(),
-                    compact: false,
-                    longevity: true
-                }} color="orange" icon="sign in"
-                   content="Leave"/>
-            </div>
-        </div>;
-    }
-
     renderJoinGameSection () {
         let buyIn = new Bond;
 
         return <div style={{ paddingTop: '1em' }}>
-            <div style={{ paddingBottom: '1em' }}>
-                <div style={{ fontSize: 'small' }}>amount to put on table</div>
-                <BalanceBond bond={buyIn} />
-            </div>
+            { this.displayParticipants() }
+            { this.displayInput("amount to put on the table", buyIn) }
             <div style={{ paddingTop: '1em' }}>
                 <TransactButton tx={{
                     sender: this.user,
-                    call: calls.poker.joinGame(buyIn),
+                    call: calls.poker.joinGame(this.gameId, buyIn),
                     compact: false,
                     longevity: true
                 }} color="green" icon="sign in"
@@ -315,65 +226,53 @@ export class GameSegment extends React.Component {
     renderGameTable () {
         return <div style={{
             'width': '1282px',
-            'height': '679px',
             'backgroundColor': 'green',
             'border': '10px solid darkgreen',
             'borderRadius': '20px',
-            'paddingTop': '20px',
-            'paddingLeft': '20px',
-            'paddingRight': '20px',
-            'paddingBottom': '20px',
+            'padding': '20px',
         }}>
-            <If condition={this.pocketCardsAreDealt} then={<span>
-                {/*Players have received cards on their hands*/}
-                <table><tbody><tr>
-                        <td>
-                            <table><tbody><tr><td>
-                                { this.displayParticipant(this.opponent, true)}
-                                { this.displayOpponentCards() }
-                                { this.displayBet(this.opponent) }
-                            </td></tr>
-                            <tr height="30"><td></td></tr>
-                            <tr><td>
-                                { this.displayBet(this.user) }
-                                { this.displayPocketCards() }
-                                { this.displayParticipant(this.user, true)}
-                            </td></tr></tbody></table>
-                        </td>
-                        <td>
-                            <table><tbody><tr height="230"><td>
-                                <div style={{
-                                    'paddingLeft': '24px'}}>
-                                    <div style={{
-                                        'height': '265px',
-                                        'width': '838px',
-                                        'backgroundColor': 'forestgreen',
-                                        'border': '6px solid greenyellow',
-                                        'borderRadius': '12px',
-                                        'paddingTop': '12px',
-                                        'paddingLeft': '12px',
-                                        'paddingRight': '12px',
-                                        'paddingBottom': '12px',}}>
-                                        <If condition={this.sharedCards.map(encoded => encoded.length > 0)}
-                                            then={this.displaySharedCards()}/>
-                                    </div>
-                                </div>
-                            </td></tr><tr><td>
-                                <div align="center">
-                                    <If condition={bondsAccountsAreEqualAndNotNull(runtime.poker.betsNow, game.user)}
-                                        then={this.displayActions()}
-                                        else={<Label color="blue">
-                                            Waiting for opponent's decision
-                                        </Label>}/>
-                                </div>
-                            </td></tr></tbody></table>
-                        </td>
-                </tr></tbody></table>
-            </span>} else={<span>
-                {/*Players haven't received cards yet*/}
-                {this.displayStatus("Providing round keys and waiting for cards...")}
-                {this.displayMessage("Good luck and have fun.")}
-            </span>}/>
+            <table><tbody><tr>
+                <td style={{ verticalAlign: 'top' }}>
+                    { this.displayParticipants() }
+                </td>
+                <td>
+                    <div style={{
+                        'height': '265px',
+                        'width': '838px',
+                        'backgroundColor': 'forestgreen',
+                        'border': '6px solid greenyellow',
+                        'borderRadius': '12px',
+                        'padding': '12px',}}>
+                        <If condition={this.sharedCards.map(encoded => encoded.length > 0)}
+                            then={this.displaySharedCards()}/>
+                    </div>
+                    <div align="center" style={{ paddingTop: '1em' }}>
+                        <If condition={this.isIdle} then={
+                            this.renderWaitingSection()
+                        } else={
+                            <If condition={bondsAccountsAreEqualAndNotNull(this.betsNow, this.user)}
+                                then={this.displayActions()}
+                                else={this.displayStatus(this.stage.map(stages.name))}/>
+                        }/>
+                    </div>
+                </td>
+            </tr></tbody></table>
+        </div>;
+    }
+
+    renderWaitingSection () {
+        return <div>
+            {/*keys, the deck and secrets are proven with the `prover` crate*/}
+            { this.displayMessage("Waiting for the next hand: keys and the deck are submitted by the prover client") }
+            <div style={{ paddingTop: '1em' }}>
+                <TransactButton tx={{
+                    sender: this.user,
+                    call: calls.poker.leaveGame(this.gameId),
+                    compact: false,
+                    longevity: true
+                }} color="orange" icon="sign in"
+                   content="Leave"/>
+            </div>
         </div>;
     }
 
@@ -392,60 +291,41 @@ export class GameSegment extends React.Component {
         </div>;
     }
 
-    displayParticipantProperty(participant, callback) {
-        return <Pretty value={participant.map(account => {
-            if (account !== null) {
-                return callback(account);
-            }
-            return "";
-        })}/>;
+    displayRules () {
+        return <table><tbody><tr>
+            <td>Blinds are </td>
+            <td><Label color="violet" size="large">
+                <Pretty value={this.config.map(config =>
+                    `${config.small_blind}/${config.big_blind}`)}/>
+            </Label></td>
+            <td> at this table, </td>
+            <td><Pretty value={this.stage.map(stages.name)}/></td>
+        </tr></tbody></table>;
     }
 
-    displayParticipant (participant, markDealer) {
-        let content = <span>
-            <Label color="blue">
-                { this.displayParticipantProperty(participant,
-                    account => game.accounts.find(account).name) }
-            </Label>
-            <Label>
-                { this.displayParticipantProperty(participant,
-                    account => runtime.poker.stacks(account)) }
-            </Label>
-            <If condition={bondsAccountsAreEqualAndNotNull(participant, this.user)}
-                then={<Label color="yellow">You</Label>}
-                else={<Label color="yellow">Opponent</Label>}/>
-        </span>;
-
-        return <table><tbody>
-            <If condition={markDealer} then={<tr>
-                <td width="259px">{content}</td>
-                <td>
-                    <If condition={bondsAccountsAreEqualAndNotNull(participant, this.dealer)}
-                        then={<Label color="red"><Pretty value="Dealer" /></Label>}/>
-                </td>
-            </tr>} else={<tr><td>
-                {content}
-            </td></tr>}/>
-        </tbody></table>;
+    displayParticipants () {
+        return <Rspan>{
+            this.seats.map(seats => seats.map((account, i) =>
+                <div key={'seat' + i}>{ this.displayParticipant(account) }</div>))
+        }</Rspan>;
     }
 
-    displayPocketCards () {
-        return SvgRow("hand",
-            this.pocketCards.map(encrypted =>
-                keys.hand.map(key => {
-                    let decrypted = decrypt(encrypted, key.modulus, key.exponent);
-                    let cards = decode(decrypted);
-                    return cards.map(image);
-                }))
-        );
-    }
-
-    displayBet (participant) {
-        let bet = runtime.poker.bets(participant);
-        return <div align="center" style={{'height': '30px'}}>
-            <If condition={bet.map(v => v !== 0)}
+    displayParticipant (account) {
+        let key = this.gameId.map(id => [id, account]);
+        let bet = runtime.poker.bets(key);
+        let openCards = runtime.poker.openCards(key);
+
+        return <table><tbody><tr><td>
+            <Label color="blue">{ game.accounts.find(account).name }</Label>
+            <Label><Pretty value={runtime.poker.stacks(key)}/></Label>
+            <If condition={this.user.map(user => accountsAreEqualAndNotNull(account, user))}
+                then={<Label color="yellow">You</Label>}/>
+            <If condition={bet.map(v => v != 0)}
                 then={<Label color="olive">Bet: <Pretty value={bet}/></Label>}/>
-        </div>;
+        </td></tr><tr><td>
+            <If condition={openCards.map(encoded => encoded.length !== 0)}
+                then={SvgRow("open" + account, openCards.map(encoded => decode(encoded).map(image)))}/>
+        </td></tr></tbody></table>;
     }
 
     displayActions () {
@@ -453,36 +333,38 @@ export class GameSegment extends React.Component {
             <tr><td>
                 <TransactButton color="red" content="Leave" tx={{
                     sender: this.user,
-                    call: calls.poker.leaveGameAnyway()
+                    call: calls.poker.leaveGameAnyway(this.gameId)
                 }}/>
                 <TransactButton color="red" content="Fold" tx={{
                     sender: this.user,
-                    call: calls.poker.fold()
+                    call: calls.poker.fold(this.gameId)
                 }} size="massive"/>
                 <TransactButton color="yellow" content="Raise" tx={{
                     sender: this.user,
-                    call: calls.poker.raise(game.raise)
+                    call: calls.poker.raise(this.gameId, game.raise)
                 }} size="massive"/>
                 <TransactButton color="green" content="Call" tx={{
                     sender: this.user,
-                    call: calls.poker.call()
+                    call: calls.poker.call(this.gameId)
                 }} size="massive"/>
                 <TransactButton color="blue" content="Check" tx={{
                     sender: this.user,
-                    call: calls.poker.check()
+                    call: calls.poker.check(this.gameId)
                 }}/>
             </td></tr><tr><td>
                 <Rspan className="value">{
-                    runtime.poker.betLevel.map(level => {
-                        return runtime.poker.blinds.map(blinds => {
-                            return runtime.poker.stacks(game.user).map(stack => {
+                    this.betLevel.map(level => {
+                        return this.config.map(config => {
+                            return this.stack.map(stack => {
+                                level = level || 0;
                                 if (level >= stack) {
                                     game.raise.reset();
                                     return <span/>;
                                 } else {
+                                    //the raise is the total bet of the betting round
                                     let maxRaise = stack.valueOf();
                                     let minRaise = Math.min(maxRaise,
-                                        level + Math.max(level, blinds[0][1]));
+                                        level + Math.max(level, config.big_blind));
                                     return <RaiseSelector
                                         maxValue={maxRaise}
                                         minValue={minRaise}
@@ -495,17 +377,6 @@ export class GameSegment extends React.Component {
         </tbody></table>;
     }
 
-    displayOpponentCards () {
-        let hiddenCards = new Bond();
-        hiddenCards.changed([...Array(2).keys()]
-            .map(_ => hidden()));
-
-        return <If condition={this.opponentCardsAreRevealed}
-           then={SvgRow("opponent-revealed",
-               this.opponentCards.map(encoded => decode(encoded).map(image)))}
-           else={SvgRow("opponent-hidden", hiddenCards)}/>;
-    }
-
     displaySharedCards () {
         return SvgRow("shared",
             this.sharedCards.map(encoded => {
@@ -515,10 +386,17 @@ export class GameSegment extends React.Component {
         );
     }
 
+    displayInput (caption, bond) {
+        return <div style={{ paddingBottom: '1em' }}>
+            <div style={{ fontSize: 'small' }}>{ caption }</div>
+            <BalanceBond bond={bond} />
+        </div>;
+    }
+
     displayStatus (status) {
         return <div style={{ paddingTop: '1em', paddingBottom: '1em' }}>
             <BlinkingLabel size="massive" color="yellow">
-                { status }
+                <Pretty value={status}/>
             </BlinkingLabel>
         </div>;
     }
@@ -530,17 +408,8 @@ export class GameSegment extends React.Component {
             </Label>
         </div>;
     }
-
-    displayGameResult () {
-        return <Label>TODO: WINNER</Label>
-    }
 }
 
 //todo: Try to use `bonds.me`, see this doc for details: https://wiki.parity.io/oo7-Parity-Examples
-//todo: Implement codec for some structures. Though they are not actually used, they produce warnings.
 //todo: Remove direct state mutation and use `setState()`!
 //todo: implement `any` combinator for bonds, similar to `all`
-
-// const {} = require('oo7-react');
-// const {} = require('oo7-parity');
-// const {AccountLabel} = require('parity-reactive-ui');
\ No newline at end of file
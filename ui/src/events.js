@@ -5,7 +5,6 @@ const bufEq = require('arraybuffer-equal');
 async function main () {
     // Create our API with a default connection to the local node
     const api = await ApiPromise.create();
-    const decoder = new TextDecoder("utf-8");
 
     // subscribe to system events via storage
     api.query.system.events((events) => {
@@ -16,56 +15,81 @@ async function main () {
             const types = event.typeDef;
 
             if (event.section === 'poker') {
-                if (event.method === "Announcement") {
-                    let message = decoder.decode(event.data[0]);
-                    NotificationManager.warning(message);
-                } else if (event.method === "ErrorMessage") {
+                if (event.method === "Error") {
                     performIfAddressedForUs(event, data => {
-                        let message = decoder.decode(data[1]);
-                        NotificationManager.error(message);
+                        NotificationManager.error(`Transaction has failed: ${data[1]}`);
                     });
-                } else if (event.method === "InfoMessage") {
-                    performIfAddressedForUs(event, data => {
-                        let message = decoder.decode(data[1]);
-                        NotificationManager.info(message);
-                    });
-                } else if (event.method === "NewParticipant") {
-                    let buyIn = event.data[1];
-                    NotificationManager.success(`${name(event)} took a seat with ${buyIn} chips`);
-                } else if (event.method === "NewDealer") {
-                    NotificationManager.info(`${name(event)} becomes new dealer`);
-                } else if (event.method === "ParticipantLeft") {
-                    NotificationManager.warning(`${name(event)} left the game`);
-                } else if (event.method === "AllIn") {
-                    NotificationManager.warning(`${name(event)} goes all-in!`);
-                } else if (event.method === "Raise") {
-                    let diff = event.data[1];
-                    NotificationManager.warning(`${name(event)} bets ${diff} more`);
-                } else if (event.method === "Call") {
-                    NotificationManager.info(`${name(event)} calls the bet`);
-                } else if (event.method === "Check") {
-                    NotificationManager.info(`${name(event)} checks`);
-                } else if (event.method === "Fold") {
-                    NotificationManager.warning(`${name(event)} folds`);
                 } else {
-                    NotificationManager.info(`${event.method}: ${event.data}`);
+                    performIfAtOurTable(event, () => announce(event));
                 }
+            } else if (event.section === 'tournament' && event.method === "Error") {
+                performIfAddressedForUs(event, data => {
+                    NotificationManager.error(`Transaction has failed: ${data[1]}`);
+                });
             }
         });
     });
 }
 
+//the id of the table goes first in every event of the poker module except errors
+function announce(event) {
+    if (event.method === "HandStarted") {
+        NotificationManager.info(`Hand #${event.data[1]} starts, blinds ${event.data[3]}/${event.data[4]}`);
+    } else if (event.method === "ShuffleTurn") {
+        NotificationManager.info(`${name(event, 1)} submits the deck`);
+    } else if (event.method === "CardsDealt") {
+        NotificationManager.info("Cards are dealt");
+    } else if (event.method === "BlindPosted") {
+        NotificationManager.info(`${name(event, 1)} posts ${event.data[2]} blind`);
+    } else if (event.method === "StageRevealed") {
+        NotificationManager.info("Shared cards are opened");
+    } else if (event.method === "PotAwarded") {
+        NotificationManager.success(`${name(event, 1)} wins ${event.data[2]} chips`);
+    } else if (event.method === "RoundCancelled") {
+        NotificationManager.warning("The round is cancelled, chips go back except those of late participants");
+    } else if (event.method === "NewParticipant") {
+        let buyIn = event.data[2];
+        NotificationManager.success(`${name(event, 1)} took a seat with ${buyIn} chips`);
+    } else if (event.method === "RolesSwapped") {
+        NotificationManager.info(`${name(event, 1)} becomes new dealer`);
+    } else if (event.method === "ParticipantLeft") {
+        NotificationManager.warning(`${name(event, 1)} left the game`);
+    } else if (event.method === "AllIn") {
+        NotificationManager.warning(`${name(event, 1)} goes all-in!`);
+    } else if (event.method === "Raise") {
+        let diff = event.data[2];
+        NotificationManager.warning(`${name(event, 1)} bets ${diff} more`);
+    } else if (event.method === "Call") {
+        NotificationManager.info(`${name(event, 1)} calls the bet`);
+    } else if (event.method === "Check") {
+        NotificationManager.info(`${name(event, 1)} checks`);
+    } else if (event.method === "Fold") {
+        NotificationManager.warning(`${name(event, 1)} folds`);
+    } else {
+        NotificationManager.info(`${event.method}: ${event.data}`);
+    }
+}
+
+function performIfAtOurTable(event, callback) {
+    if (game.gameId.isReady() && event.data[0].toNumber() === game.gameId._value) {
+        callback();
+    }
+}
+
 function performIfAddressedForUs(event, callback) {
     game.user.then(user => {
-        if (event.data[0] == null || event.data[0].value.buffer == undefined
-                || bufEq(user.buffer, event.data[0].value.buffer)) {
+        //errors of the poker module have an optional account, of the tournament module a plain one
+        let who = event.data[0] != null && event.data[0].value !== undefined
+            ? event.data[0].value
+            : event.data[0];
+        if (who == null || who.buffer == undefined || bufEq(user.buffer, who.buffer)) {
             callback(event.data);
         }
     });
 }
 
-function name(event) {
-    return game.accounts.find(event.data[0]).name;
+function name(event, index) {
+    return game.accounts.find(event.data[index]).name;
 }
 
 main().catch((error) => {
@@ -1,16 +1,14 @@
-const keys = require('./keys.js');
+//the same as `stage.rs` of the runtime
+export const IDLE = 0;
+export const PREFLOP = 1;
+export const FLOP = 2;
+export const TURN = 3;
+export const RIVER = 4;
+export const SHOWDOWN = 5;
+export const SHUFFLE = 6;
 
-//Be careful, stage_ui = stage_node - 1
-const HAND = 0;  // 4 % 4 == 0, i.e. revealing hand cards after RIVER
-const FLOP = 1;  //PREFLOP at node side
-const TURN = 2;  //FLOP at node side
-const RIVER = 3; //TURN at node side
-//const SHOWDOWN = 4;
+const NAMES = ['waiting for players', 'preflop', 'flop', 'turn', 'river', 'showdown', 'shuffling'];
 
-export const STAGES = [HAND, FLOP, TURN, RIVER];
-export const NAMES = ['hand', 'flop', 'turn', 'river'];
-
-export function secretFromStage(stage) {
-    let next = stage % (STAGES.length);
-    return keys.BONDS[next].map(key => key.exponent);
-}
\ No newline at end of file
+export function name(stage) {
+    return NAMES[stage] || `stage ${stage}`;
+}